@@ -1,7 +1,9 @@
 use super::*;
+use crate::daos::Daos;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, clap::Args)]
-/// Get account balance for HNT, MOBILE, and IOT tokens
+/// Get account balance for HNT and the tokens of its subDAOs
 pub struct Account {
     account: Pubkey,
 }
@@ -9,7 +11,8 @@ pub struct Account {
 impl Account {
     pub async fn run(self, rpc_client: rpc::Client) -> Result {
         let rpc_client = Arc::new(rpc_client);
-        let helium_balances = HeliumBalances::fetch(&rpc_client, &self.account).await?;
+        let daos = Daos::discover(&rpc_client).await?;
+        let helium_balances = HeliumBalances::fetch(&rpc_client, &daos, &self.account).await?;
         let output = serde_json::to_string_pretty(&helium_balances)?;
         println!("{}", output);
         Ok(())
@@ -19,8 +22,9 @@ impl Account {
 #[derive(serde::Serialize)]
 pub struct HeliumBalances {
     pub hnt: Balance,
-    pub iot: Balance,
-    pub mobile: Balance,
+    /// The balance of the token of each subDAO, by its symbol
+    #[serde(flatten)]
+    pub sub_daos: BTreeMap<String, Balance>,
 }
 
 #[derive(serde::Serialize)]
@@ -31,32 +35,42 @@ pub struct Balance {
 }
 
 impl HeliumBalances {
-    pub async fn fetch(rpc_client: &Arc<rpc::Client>, account: &Pubkey) -> Result<Self> {
-        let hnt_mint = Pubkey::from_str(HNT_MINT)?;
-        let iot_mint = Pubkey::from_str(IOT_MINT)?;
-        let mobile_mint = Pubkey::from_str(MOBILE_MINT)?;
-        let (hnt_account, iot_account, mobile_account) = tokio::join!(
-            get_account(rpc_client, &hnt_mint, account),
-            get_account(rpc_client, &iot_mint, account),
-            get_account(rpc_client, &mobile_mint, account)
+    pub async fn fetch(
+        rpc_client: &Arc<rpc::Client>,
+        daos: &Daos,
+        account: &Pubkey,
+    ) -> Result<Self> {
+        let dao = daos.dao().ok_or(Error::Custom("Helium DAO not found"))?;
+        let (hnt_account, sub_dao_accounts) = tokio::join!(
+            get_account(rpc_client, &dao.mint, account),
+            futures::future::try_join_all(daos.sub_daos().map(|sub_dao| get_account(
+                rpc_client,
+                &sub_dao.mint,
+                account
+            )))
         );
 
+        let sub_daos = daos
+            .sub_daos()
+            .zip(sub_dao_accounts?)
+            .map(|(sub_dao, sub_dao_account)| {
+                (
+                    sub_dao.symbol.clone(),
+                    Balance {
+                        mint: sub_dao.mint.to_string(),
+                        amount: sub_dao_account.token,
+                        decimals: sub_dao.decimals,
+                    },
+                )
+            })
+            .collect();
         Ok(Self {
             hnt: Balance {
-                mint: HNT_MINT.to_string(),
+                mint: dao.mint.to_string(),
                 amount: hnt_account?.token,
-                decimals: 8,
-            },
-            iot: Balance {
-                mint: IOT_MINT.to_string(),
-                amount: iot_account?.token,
-                decimals: 6,
-            },
-            mobile: Balance {
-                mint: MOBILE_MINT.to_string(),
-                amount: mobile_account?.token,
-                decimals: 6,
+                decimals: dao.decimals,
             },
+            sub_daos,
         })
     }
 }
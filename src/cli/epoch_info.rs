@@ -5,6 +5,7 @@ use anchor_lang::AccountDeserialize;
 /// Scrape all SubDao epoch info
 pub struct EpochInfo {}
 
+use crate::daos::Daos;
 use helium_anchor_gen::{
     helium_sub_daos::{SubDaoEpochInfoV0, SubDaoEpochInfoV0Trait},
     voter_stake_registry::PRECISION_FACTOR,
@@ -12,72 +13,104 @@ use helium_anchor_gen::{
 use rpc::GetProgramAccountsFilter;
 use rust_decimal::Decimal;
 use serde::Serialize;
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::BTreeMap;
 
 pub async fn get_epoch_summaries(rpc_client: &rpc::Client) -> Result<Vec<EpochSummary>> {
     let helium_dao_id = Pubkey::from_str(HELIUM_DAO_ID)?;
     const SUB_DAO_EPOCH_INFO_DESCRIMINATOR: [u8; 8] = [45, 249, 177, 20, 170, 251, 37, 37];
+    // the low byte of the epoch, which follows the discriminator
+    const EPOCH_OFFSET: usize = 8;
 
     let memcmp =
         GetProgramAccountsFilter::Memcmp(rpc::Memcmp::new(0, &SUB_DAO_EPOCH_INFO_DESCRIMINATOR));
     let filters = vec![GetProgramAccountsFilter::DataSize(204), memcmp];
+    // only the keys are scanned, the epoch infos themselves are fetched below
+    let keys: Vec<Pubkey> = rpc_client
+        .get_program_accounts_sharded(
+            &helium_dao_id,
+            filters,
+            EPOCH_OFFSET,
+            Some(rpc::DataSlice::new(0, 0)),
+        )
+        .await?
+        .value
+        .into_iter()
+        .map(|(pubkey, _)| pubkey)
+        .collect();
     let accounts = rpc_client
-        .get_program_accounts_with_filter(&helium_dao_id, filters)
+        .get_multiple_accounts_cached(&keys.iter().collect::<Vec<_>>(), None)
         .await?;
 
-    let mut iot_epochs = HashMap::new();
-    let mut mobile_epochs = HashMap::new();
-    let mut epochs = Vec::new();
+    // the epoch infos of each epoch, by subDAO
+    let mut epochs: BTreeMap<u64, BTreeMap<Pubkey, SubDaoEpochInfo>> = BTreeMap::new();
 
-    for (_pubkey, account) in &accounts {
+    for (pubkey, account) in keys.iter().zip(accounts.value) {
+        let Some(account) = account else {
+            continue;
+        };
         let mut data = account.data.as_slice();
         if let Ok(sub_dao_epoch_info) = SubDaoEpochInfoV0::try_deserialize(&mut data) {
-            let sub_dao_epoch_info = SubDaoEpochInfo::try_from(sub_dao_epoch_info)?;
-            epochs.push(sub_dao_epoch_info.clone());
-            match sub_dao_epoch_info.sub_dao {
-                SubDao::Iot => {
-                    iot_epochs.insert(sub_dao_epoch_info.epoch, sub_dao_epoch_info);
-                }
-                SubDao::Mobile => {
-                    mobile_epochs.insert(sub_dao_epoch_info.epoch, sub_dao_epoch_info);
-                }
-                SubDao::Unknown => {
-                    return Err(Error::Custom("Unknown subdao"));
-                }
+            // an epoch info no longer changes once its rewards are issued
+            if sub_dao_epoch_info.rewards_issued_at.is_some()
+                && rpc_client.account_cache().get(pubkey).is_none()
+            {
+                let account = rpc::WithContext {
+                    slots: accounts.slots,
+                    value: account.clone(),
+                };
+                rpc_client
+                    .account_cache()
+                    .insert(*pubkey, account, rpc::CachePolicy::Immutable);
             }
+            let sub_dao_epoch_info = SubDaoEpochInfo::try_from(sub_dao_epoch_info)?;
+            epochs
+                .entry(sub_dao_epoch_info.epoch)
+                .or_default()
+                .insert(sub_dao_epoch_info.sub_dao, sub_dao_epoch_info);
         }
     }
 
-    epochs.sort_by(|a, b| a.epoch.cmp(&b.epoch));
-
+    // an epoch is summarized once each subDAO of the epoch before has an
+    // initialized epoch info for it, so that subDAOs may be added over time
     let mut output = Vec::new();
-    for (i, iot) in iot_epochs {
-        if let Some(mobile) = mobile_epochs.get(&i) {
-            if iot.initialized && mobile.initialized {
-                // TODO: assert the rewards issued at are close enough
-                // iot.rewards_issued_at == mobile.rewards_issued_at
-                let epoch_summary = {
-                    EpochSummary {
-                        epoch: i,
-                        iot_dc_burned: iot.dc_burned,
-                        mobile_dc_burned: mobile.dc_burned,
-                        iot_vehnt_at_epoch_start: iot.vehnt_at_epoch_start.try_into()?,
-                        mobile_vehnt_at_epoch_start: mobile.vehnt_at_epoch_start.try_into()?,
-                        iot_delegation_rewards_issued: iot.delegation_rewards_issued,
-                        mobile_delegation_rewards_issued: mobile.delegation_rewards_issued,
-                        iot_utility_score: iot.utility_score,
-                        mobile_utility_score: mobile.utility_score,
-                        epoch_start_at_ts: iot.start_ts,
-                        rewards_issued_at_ts: iot.rewards_issued_at_ts,
-                        rewards_issued_at: iot.rewards_issued_at,
-                        initialized: true,
-                    }
-                };
-                output.push(epoch_summary);
-            }
+    let mut previous_sub_daos = Vec::new();
+    for (epoch, infos) in epochs {
+        let complete = infos.values().all(|info| info.initialized)
+            && previous_sub_daos
+                .iter()
+                .all(|sub_dao| infos.contains_key(sub_dao));
+        previous_sub_daos = infos.keys().copied().collect();
+        if !complete {
+            continue;
         }
+        // TODO: assert the rewards issued at of the subDAOs are close enough
+        let epoch_start_at_ts = infos.values().find_map(|info| info.start_ts);
+        let rewards_issued_at_ts = infos.values().find_map(|info| info.rewards_issued_at_ts);
+        let rewards_issued_at = infos.values().find_map(|info| info.rewards_issued_at);
+        let sub_daos = infos
+            .into_iter()
+            .map(|(sub_dao, info)| {
+                Ok((
+                    sub_dao,
+                    SubDaoEpochSummary {
+                        dc_burned: info.dc_burned,
+                        vehnt_at_epoch_start: info.vehnt_at_epoch_start.try_into()?,
+                        delegation_rewards_issued: info.delegation_rewards_issued,
+                        utility_score: info.utility_score,
+                    },
+                ))
+            })
+            .collect::<Result<_>>()?;
+        output.push(EpochSummary {
+            epoch,
+            sub_daos,
+            epoch_start_at_ts,
+            rewards_issued_at_ts,
+            rewards_issued_at,
+            initialized: true,
+        });
     }
-    output.sort_by(|a, b| a.epoch.cmp(&b.epoch));
     // the last epoch does not have epoch_start_at, so we will copy it from penultimate epoch
     let len = output.len();
     let penultimate = &output[len - 2];
@@ -87,18 +120,29 @@ pub async fn get_epoch_summaries(rpc_client: &rpc::Client) -> Result<Vec<EpochSu
 
 impl EpochInfo {
     pub async fn run(self, rpc_client: rpc::Client) -> Result {
+        let daos = Daos::discover(&rpc_client).await?;
         let summaries = get_epoch_summaries(&rpc_client).await?;
         use csv::Writer;
         let mut wtr = Writer::from_path("epochs.csv")?;
+        if let Some(summary) = summaries.first() {
+            wtr.write_record(summary.fields(&daos).iter().map(|(name, _)| name))?;
+        }
+        let values = |summary: &EpochSummary| -> Vec<Field> {
+            summary
+                .fields(&daos)
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect()
+        };
         for record in &summaries {
             println!("{:?}", record);
-            wtr.serialize(record)?;
+            wtr.serialize(values(record))?;
         }
 
         for mut record in summaries {
             record.scale_down();
             println!("{:?}", record);
-            wtr.serialize(record)?;
+            wtr.serialize(values(&record))?;
         }
         Ok(())
     }
@@ -107,7 +151,7 @@ impl EpochInfo {
 #[derive(Debug, Clone, Serialize)]
 pub struct SubDaoEpochInfo {
     pub epoch: u64,
-    pub sub_dao: SubDao,
+    pub sub_dao: Pubkey,
     pub dc_burned: u64,
     pub vehnt_at_epoch_start: u64,
     pub vehnt_in_closing_positions: u128,
@@ -127,20 +171,20 @@ pub struct SubDaoEpochInfo {
     pub initialized: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// The epoch of a subDAO, as far as delegation rewards are concerned
+#[derive(Debug, Clone)]
+pub struct SubDaoEpochSummary {
+    pub dc_burned: u64,
+    pub vehnt_at_epoch_start: VeHnt,
+    pub delegation_rewards_issued: u64,
+    pub utility_score: Option<u128>,
+}
+
+#[derive(Debug, Clone)]
 pub struct EpochSummary {
     pub epoch: u64,
-    pub iot_dc_burned: u64,
-    pub mobile_dc_burned: u64,
-
-    pub iot_vehnt_at_epoch_start: VeHnt,
-    pub mobile_vehnt_at_epoch_start: VeHnt,
-
-    pub iot_delegation_rewards_issued: u64,
-    pub mobile_delegation_rewards_issued: u64,
-
-    pub iot_utility_score: Option<u128>,
-    pub mobile_utility_score: Option<u128>,
+    /// By `SubDaoV0` account. SubDAOs created after the epoch are missing.
+    pub sub_daos: BTreeMap<Pubkey, SubDaoEpochSummary>,
 
     pub epoch_start_at_ts: Option<i64>,
     pub rewards_issued_at_ts: Option<i64>,
@@ -149,34 +193,111 @@ pub struct EpochSummary {
     pub initialized: bool,
 }
 
+/// A value of an epoch summary, as served and written to CSV
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Field {
+    Unsigned(Option<u128>),
+    Signed(Option<i64>),
+    VeHnt(Option<VeHnt>),
+    Date(Option<DateTime<Utc>>),
+    Bool(bool),
+}
+
 impl EpochSummary {
     pub fn scale_down(&mut self) {
-        self.iot_utility_score = self.iot_utility_score.map(|s| s / PRECISION_FACTOR);
-        self.mobile_utility_score = self.mobile_utility_score.map(|s| s / PRECISION_FACTOR);
+        for sub_dao in self.sub_daos.values_mut() {
+            sub_dao.utility_score = sub_dao.utility_score.map(|s| s / PRECISION_FACTOR);
+        }
     }
 
+    /// The epoch to come, of which only the veHNT delegated to each subDAO
+    /// is known
     pub fn from_partial_data(
         epoch: u64,
-        mobile_vehnt: u128,
-        iot_vehnt: u128,
+        vehnt: impl IntoIterator<Item = (Pubkey, u128)>,
         epoch_start_at_ts: i64,
     ) -> Result<Self> {
+        let sub_daos = vehnt
+            .into_iter()
+            .map(|(sub_dao, vehnt)| {
+                Ok((
+                    sub_dao,
+                    SubDaoEpochSummary {
+                        dc_burned: 0,
+                        vehnt_at_epoch_start: VeHnt::try_from(vehnt)?,
+                        delegation_rewards_issued: 0,
+                        utility_score: None,
+                    },
+                ))
+            })
+            .collect::<Result<_>>()?;
         Ok(EpochSummary {
             epoch,
-            iot_dc_burned: 0,
-            mobile_dc_burned: 0,
-            iot_vehnt_at_epoch_start: VeHnt::try_from(iot_vehnt)?,
-            mobile_vehnt_at_epoch_start: VeHnt::try_from(mobile_vehnt)?,
-            iot_delegation_rewards_issued: 0,
-            mobile_delegation_rewards_issued: 0,
-            iot_utility_score: None,
-            mobile_utility_score: None,
+            sub_daos,
             epoch_start_at_ts: Some(epoch_start_at_ts),
             rewards_issued_at_ts: None,
             rewards_issued_at: None,
             initialized: false,
         })
     }
+
+    /// The fields of the summary, those of each subDAO of `daos` named
+    /// `{symbol}_{field}`, such as `iot_dc_burned`
+    pub fn fields(&self, daos: &Daos) -> Vec<(String, Field)> {
+        type SubDaoField = fn(Option<&SubDaoEpochSummary>) -> Field;
+        let sub_dao_fields: [(&str, SubDaoField); 4] = [
+            ("dc_burned", |s| {
+                Field::Unsigned(s.map(|s| s.dc_burned.into()))
+            }),
+            ("vehnt_at_epoch_start", |s| {
+                Field::VeHnt(s.map(|s| s.vehnt_at_epoch_start.clone()))
+            }),
+            ("delegation_rewards_issued", |s| {
+                Field::Unsigned(s.map(|s| s.delegation_rewards_issued.into()))
+            }),
+            ("utility_score", |s| {
+                Field::Unsigned(s.and_then(|s| s.utility_score))
+            }),
+        ];
+        let mut fields = vec![(
+            "epoch".to_string(),
+            Field::Unsigned(Some(self.epoch.into())),
+        )];
+        for (name, field) in sub_dao_fields {
+            for dao in daos.sub_daos() {
+                fields.push((
+                    format!("{}_{name}", dao.symbol),
+                    field(self.sub_daos.get(&dao.key)),
+                ));
+            }
+        }
+        fields.extend([
+            (
+                "epoch_start_at_ts".to_string(),
+                Field::Signed(self.epoch_start_at_ts),
+            ),
+            (
+                "rewards_issued_at_ts".to_string(),
+                Field::Signed(self.rewards_issued_at_ts),
+            ),
+            (
+                "rewards_issued_at".to_string(),
+                Field::Date(self.rewards_issued_at),
+            ),
+            ("initialized".to_string(), Field::Bool(self.initialized)),
+        ]);
+        fields
+    }
+
+    /// The summary as served, with the fields of `fields`
+    pub fn to_json(&self, daos: &Daos) -> Result<Value> {
+        let mut json = serde_json::Map::new();
+        for (name, field) in self.fields(daos) {
+            json.insert(name, serde_json::to_value(field)?);
+        }
+        Ok(Value::Object(json))
+    }
 }
 
 impl TryFrom<SubDaoEpochInfoV0> for SubDaoEpochInfo {
@@ -184,7 +305,7 @@ impl TryFrom<SubDaoEpochInfoV0> for SubDaoEpochInfo {
     fn try_from(value: SubDaoEpochInfoV0) -> Result<Self> {
         Ok(Self {
             epoch: value.epoch,
-            sub_dao: SubDao::try_from(value.sub_dao)?,
+            sub_dao: value.sub_dao,
             dc_burned: value.dc_burned,
             vehnt_at_epoch_start: value.vehnt_at_epoch_start,
             vehnt_in_closing_positions: value.vehnt_in_closing_positions,
@@ -1,7 +1,8 @@
 use super::*;
 
 use helium_anchor_gen::voter_stake_registry::{PositionV0, Registrar, VotingMintConfigV0};
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 
 #[derive(Debug, Clone, clap::Args)]
 /// Fetches all delegated positions and total HNT, veHNT, and subDAO delegations.
@@ -14,24 +15,77 @@ pub struct Data {
     pub positions: Vec<(Pubkey, PositionV0)>,
     pub mint_configs: HashMap<Pubkey, VotingMintConfigV0>,
     pub registrar_to_mint: HashMap<Pubkey, Pubkey>,
+    /// Slots the positions and registrars were read at
+    pub slots: rpc::SlotRange,
+    /// Positions that are new or whose data changed since the previous scan
+    pub changed: HashSet<Pubkey>,
+    /// To pass to the next scan
+    pub decoded: DecodedPositions,
+}
+
+/// The positions of a scan along with a hash of their account data, so that
+/// the next scan only decodes the positions that changed
+#[derive(Default, Clone)]
+pub struct DecodedPositions(HashMap<Pubkey, (u64, PositionV0)>);
+
+pub fn data_hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
 }
 
-/// This function will work until there's too many to fetch in a single call
 pub async fn get_data(rpc_client: &rpc::Client) -> Result<Data> {
+    get_data_since(rpc_client, &DecodedPositions::default()).await
+}
+
+/// Positions are scanned by the first byte of their mint so that no single
+/// call has to return every position. Only the positions whose data differs
+/// from `previous` are decoded.
+pub async fn get_data_since(rpc_client: &rpc::Client, previous: &DecodedPositions) -> Result<Data> {
     const POSITION_V0_DESCRIMINATAOR: [u8; 8] = [152, 131, 154, 46, 158, 42, 31, 233];
+    // the mint follows the discriminator and the registrar
+    const MINT_OFFSET: usize = 40;
     let helium_vsr_id = Pubkey::from_str(HELIUM_VSR_ID)?;
     let memcmp =
         rpc::GetProgramAccountsFilter::Memcmp(rpc::Memcmp::new(0, &POSITION_V0_DESCRIMINATAOR));
-    let accounts = rpc_client
-        .get_program_accounts_with_filter(&helium_vsr_id, vec![memcmp])
+    let positions = rpc_client
+        .get_program_accounts_sharded_decoded(
+            &helium_vsr_id,
+            vec![memcmp],
+            MINT_OFFSET,
+            None,
+            |pubkey, account| {
+                let hash = data_hash(&account.data);
+                if let Some((previous_hash, position)) = previous.0.get(&pubkey) {
+                    if *previous_hash == hash {
+                        return Ok(Some((pubkey, hash, *position, false)));
+                    }
+                }
+                PositionV0::try_deserialize(&mut account.data.as_slice())
+                    .map(|position| Some((pubkey, hash, position, true)))
+                    .map_err(|e| rpc::Error::AccountDecode(pubkey, e.to_string()))
+            },
+        )
         .await?;
-    let positions = accounts
+    let slots = positions.slots;
+    let changed = positions
+        .value
         .iter()
-        .map(|(pubkey, account)| {
-            let mut data = account.data.as_slice();
-            (*pubkey, PositionV0::try_deserialize(&mut data).unwrap())
-        })
-        .collect::<Vec<(Pubkey, PositionV0)>>();
+        .filter(|(_, _, _, changed)| *changed)
+        .map(|(pubkey, ..)| *pubkey)
+        .collect();
+    let decoded = DecodedPositions(
+        positions
+            .value
+            .iter()
+            .map(|(pubkey, hash, position, _)| (*pubkey, (*hash, *position)))
+            .collect(),
+    );
+    let positions: Vec<(Pubkey, PositionV0)> = positions
+        .value
+        .into_iter()
+        .map(|(pubkey, _, position, _)| (pubkey, position))
+        .collect();
 
     let registrar_keys: Vec<&Pubkey> = positions
         .iter()
@@ -40,17 +94,24 @@ pub async fn get_data(rpc_client: &rpc::Client) -> Result<Data> {
         .into_iter()
         .collect();
 
+    // registrars must not be read at an earlier slot than the positions. They
+    // never change once created, so they are only fetched once.
     let registrars_raw = rpc_client
-        .get_multiple_accounts_data(&registrar_keys)
+        .clone()
+        .with_min_context_slot(slots.max)
+        .get_multiple_accounts_cached(&registrar_keys, Some(rpc::CachePolicy::Immutable))
         .await?;
+    let slots = slots.merge(registrars_raw.slots);
     let registrars_raw: Vec<Registrar> = registrars_raw
-        .iter()
-        .map(|data| {
-            let data = data.clone();
-            Registrar::try_deserialize(&mut data.as_slice())
+        .value
+        .into_iter()
+        .zip(&registrar_keys)
+        .map(|(account, pubkey)| {
+            let account = account.ok_or(rpc::Error::AccountNotFound)?;
+            Ok(Registrar::try_deserialize(&mut account.data.as_slice())
+                .map_err(|e| rpc::Error::AccountDecode(**pubkey, e.to_string()))?)
         })
-        .map(|result| result.unwrap())
-        .collect();
+        .collect::<Result<Vec<Registrar>>>()?;
 
     let mut mint_configs = HashMap::new();
     let mut registrar_to_mint = HashMap::new();
@@ -64,31 +125,36 @@ pub async fn get_data(rpc_client: &rpc::Client) -> Result<Data> {
         positions,
         mint_configs,
         registrar_to_mint,
+        slots,
+        changed,
+        decoded,
     })
 }
 
 impl Locked {
     pub async fn run(self, rpc_client: rpc::Client) -> Result {
         let data = get_data(&rpc_client).await?;
+        let daos = crate::daos::Daos::discover(&rpc_client).await?;
 
-        let mut total_hnt = 0;
-        let mut total_mobile = 0;
-        let mut total_iot = 0;
+        let mut totals: HashMap<Pubkey, u64> = HashMap::new();
         for (_pubkey, position) in data.positions.iter() {
             if let Some(mint) = data.registrar_to_mint.get(&position.registrar) {
-                match mint.to_string().as_str() {
-                    HNT_MINT => total_hnt += position.amount_deposited_native,
-                    MOBILE_MINT => total_mobile += position.amount_deposited_native,
-                    IOT_MINT => total_iot += position.amount_deposited_native,
-                    _ => println!("Unknown mint {}", mint),
-                }
+                *totals.entry(*mint).or_default() += position.amount_deposited_native;
             } else {
                 println!("No mint found for registrar {}", position.registrar)
             }
         }
-        println!("Total HNT locked   : {}", format_hnt(total_hnt));
-        println!("Total MOBILE locked: {}", format_dnt(total_mobile));
-        println!("Total IOT locked   : {}", format_dnt(total_iot));
+        for dao in daos.iter() {
+            let total = totals.remove(&dao.mint).unwrap_or_default();
+            println!(
+                "Total {:<6} locked: {}",
+                dao.symbol.to_uppercase(),
+                format_tokens(total, dao.decimals)
+            );
+        }
+        for mint in totals.keys() {
+            println!("Unknown mint {}", mint);
+        }
 
         Ok(())
     }
@@ -1,9 +1,6 @@
 use super::*;
 
-use anchor_lang::{
-    prelude::{AccountDeserialize, Result as AnchorResult},
-    solana_program::pubkey::Pubkey,
-};
+use anchor_lang::{prelude::AccountDeserialize, solana_program::pubkey::Pubkey};
 
 pub mod accounts;
 pub mod epoch_info;
@@ -15,10 +12,84 @@ pub mod supply;
 #[clap(version = env!("CARGO_PKG_VERSION"))]
 #[clap(about = "hnt-explorer-api")]
 pub struct Cli {
+    #[command(flatten)]
+    rpc: RpcArgs,
     #[command(subcommand)]
     cmd: Cmd,
 }
 
+/// Settings of the Solana RPC client. Endpoints, rate limits and cassettes
+/// are read from the environment by `rpc::ClientBuilder::from_env`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct RpcArgs {
+    /// Header sent with every RPC request, as `name: value`. May be repeated.
+    /// Added to those of `SOL_RPC_HEADERS`, one per line, since header
+    /// values may contain any other separator.
+    #[arg(long = "rpc-header", global = true)]
+    headers: Vec<String>,
+    /// User agent sent with every RPC request
+    #[arg(long = "rpc-user-agent", env = "SOL_RPC_USER_AGENT", global = true)]
+    user_agent: Option<String>,
+    /// Seconds to wait for a connection to an RPC node
+    #[arg(
+        long = "rpc-connect-timeout",
+        env = "SOL_RPC_CONNECT_TIMEOUT",
+        global = true
+    )]
+    connect_timeout: Option<u64>,
+    /// Seconds to wait for account and token reads. Scans and DAS calls
+    /// have their own timeouts.
+    #[arg(long = "rpc-timeout", env = "SOL_RPC_TIMEOUT", global = true)]
+    timeout: Option<u64>,
+    /// Seconds to wait for `getProgramAccounts` scans
+    #[arg(long = "rpc-scan-timeout", env = "SOL_RPC_SCAN_TIMEOUT", global = true)]
+    scan_timeout: Option<u64>,
+    /// Seconds to wait for DAS calls
+    #[arg(long = "rpc-das-timeout", env = "SOL_RPC_DAS_TIMEOUT", global = true)]
+    das_timeout: Option<u64>,
+    /// HTTP(S) proxy to send RPC requests through
+    #[arg(long = "rpc-proxy", env = "SOL_RPC_PROXY", global = true)]
+    proxy: Option<String>,
+    /// Times a failed RPC request is retried
+    #[arg(long = "rpc-max-retries", env = "SOL_RPC_MAX_RETRIES", global = true)]
+    max_retries: Option<u32>,
+}
+
+impl RpcArgs {
+    pub fn client(&self) -> Result<rpc::Client> {
+        let mut builder = rpc::ClientBuilder::from_env()?;
+        let env_headers = std::env::var("SOL_RPC_HEADERS").unwrap_or_default();
+        let env_headers = env_headers.lines().filter(|line| !line.trim().is_empty());
+        for header in env_headers.chain(self.headers.iter().map(String::as_str)) {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or(Error::Custom("RPC headers must be given as `name: value`"))?;
+            builder = builder.header(name.trim().to_string(), value.trim().to_string());
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent.clone());
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(std::time::Duration::from_secs(connect_timeout));
+        }
+        let mut timeouts = rpc::Timeouts::default();
+        let secs = std::time::Duration::from_secs;
+        timeouts.account = self.timeout.map_or(timeouts.account, secs);
+        timeouts.scan = self.scan_timeout.map_or(timeouts.scan, secs);
+        timeouts.das = self.das_timeout.map_or(timeouts.das, secs);
+        builder = builder.timeouts(timeouts);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        if let Some(max_retries) = self.max_retries {
+            builder = builder.max_retries(max_retries);
+        }
+        Ok(builder
+            .build()?
+            .with_encoding(rpc::EncodingType::Base64Zstd))
+    }
+}
+
 #[derive(Debug, Clone, clap::Subcommand)]
 pub enum Cmd {
     /// View account information
@@ -36,6 +107,10 @@ pub enum Cmd {
 }
 
 impl Cli {
+    pub fn rpc_client(&self) -> Result<rpc::Client> {
+        self.rpc.client()
+    }
+
     pub async fn run(self, rpc_client: rpc::Client) -> Result {
         match self.cmd {
             Cmd::Account(cmd) => cmd.run(rpc_client).await,
@@ -1,6 +1,11 @@
 use super::*;
+use crate::daos::{Dao, Daos};
 use rust_decimal::prelude::ToPrimitive;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 #[derive(Debug, Clone, clap::Args)]
 /// Fetches all delegated positions and total HNT, veHNT, and subDAO delegations.
@@ -19,38 +24,34 @@ use helium_anchor_gen::{
     },
 };
 
-#[allow(unused)]
-/// This function can be used when a single query is too big
-async fn get_stake_accounts_incremental(
-    rpc_client: &rpc::Client,
-) -> Result<Vec<(Pubkey, rpc::Account)>> {
-    let mut prefix = [251, 212, 32, 100, 102, 1, 247, 81, 0];
-    let mut accounts = Vec::new();
-    for i in 0..255 {
-        prefix[8] = i;
-        accounts.extend(get_accounts_with_prefix(rpc_client, &prefix).await?);
-    }
-    Ok(accounts)
-}
-
-/// This function will work until there's too many to fetch in a single call
+/// Scans by the first byte of the position key so that no single call has
+/// to return every delegated position. Each comes with a hash of its account
+/// data. Accounts that do not decode are skipped.
 pub async fn get_delegated_positions(
     rpc_client: &rpc::Client,
-) -> Result<Vec<(Pubkey, rpc::Account)>> {
+) -> Result<rpc::WithContext<Vec<(Pubkey, u64, DelegatedPositionV0)>>> {
     const DELEGATE_POSITION_V0_DESCRIMINATOR: [u8; 8] = [251, 212, 32, 100, 102, 1, 247, 81];
-    get_accounts_with_prefix(rpc_client, &DELEGATE_POSITION_V0_DESCRIMINATOR).await
-}
-
-async fn get_accounts_with_prefix(
-    rpc_client: &rpc::Client,
-    input: &[u8],
-) -> Result<Vec<(Pubkey, rpc::Account)>> {
+    const POSITION_OFFSET: usize = 8;
     let helium_dao_id = Pubkey::from_str(HELIUM_DAO_ID)?;
-    let memcmp = rpc::GetProgramAccountsFilter::Memcmp(rpc::Memcmp::new(0, input));
-    let accounts = rpc_client
-        .get_program_accounts_with_filter(&helium_dao_id, vec![memcmp])
+    let memcmp = rpc::GetProgramAccountsFilter::Memcmp(rpc::Memcmp::new(
+        0,
+        &DELEGATE_POSITION_V0_DESCRIMINATOR,
+    ));
+    let delegated_positions = rpc_client
+        .get_program_accounts_sharded_decoded(
+            &helium_dao_id,
+            vec![memcmp],
+            POSITION_OFFSET,
+            None,
+            |pubkey, account| {
+                let mut data = account.data.as_slice();
+                Ok(DelegatedPositionV0::try_deserialize(&mut data)
+                    .ok()
+                    .map(|position| (pubkey, locked::data_hash(&account.data), position)))
+            },
+        )
         .await?;
-    Ok(accounts)
+    Ok(delegated_positions)
 }
 
 #[derive(Default, Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -65,9 +66,13 @@ pub struct DaoPositionData {
 #[derive(Default, Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct AllPositionsData {
     pub stats: Metadata,
-    pub vehnt: DaoPositionData,
-    pub vemobile: DaoPositionData,
-    pub veiot: DaoPositionData,
+    /// The DAOs discovered at the time of this snapshot
+    pub daos: Daos,
+    /// The positions of each DAO, by the name of its voting power
+    pub positions: BTreeMap<String, DaoPositionData>,
+    /// Range of slots the positions, registrars and delegated positions of
+    /// this snapshot were read at
+    pub slots: Option<rpc::SlotRange>,
 }
 
 #[derive(Default, Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -75,8 +80,9 @@ pub struct Metadata {
     pub timestamp: i64,
     pub network: Data,
     pub undelegated: Data,
-    pub mobile: Data,
-    pub iot: Data,
+    /// The veHNT delegated to each subDAO, by its symbol
+    #[serde(flatten)]
+    pub sub_daos: BTreeMap<String, Data>,
 }
 
 #[derive(Default, Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -147,16 +153,32 @@ impl Data {
 }
 
 impl AllPositionsData {
-    pub fn new() -> Self {
-        let curr_ts = Utc::now().timestamp();
+    /// `timestamp` should be the on-chain time of the snapshot (see
+    /// `rpc::Client::get_clock`) so voting power matches the programs'
+    pub fn new(timestamp: i64, daos: Daos) -> Self {
         Self {
-            stats: Metadata::new(curr_ts),
-            vehnt: DaoPositionData::new(curr_ts),
-            vemobile: DaoPositionData::new(curr_ts),
-            veiot: DaoPositionData::new(curr_ts),
+            stats: Metadata::new(timestamp),
+            positions: daos
+                .iter()
+                .map(|dao| (dao.ve_name(), DaoPositionData::new(timestamp)))
+                .collect(),
+            daos,
+            slots: None,
         }
     }
 
+    /// The positions of a DAO by the name of its voting power or its symbol
+    pub fn dao_positions(&self, name: &str) -> Option<&DaoPositionData> {
+        let dao = self.daos.by_name(name)?;
+        self.positions.get(&dao.ve_name())
+    }
+
+    /// The veHNT positions, the only ones delegated to subDAOs
+    pub fn vehnt(&self) -> Option<&DaoPositionData> {
+        let dao = self.daos.dao()?;
+        self.positions.get(&dao.ve_name())
+    }
+
     pub fn scale_down(&mut self) {
         self.stats.scale_down();
     }
@@ -172,9 +194,8 @@ impl Metadata {
 
     pub fn scale_down(&mut self) {
         self.network.scale_down();
-        self.iot.scale_down();
-        self.mobile.scale_down();
         self.undelegated.scale_down();
+        self.sub_daos.values_mut().for_each(Data::scale_down);
     }
 }
 
@@ -188,12 +209,16 @@ impl DaoPositionData {
 }
 
 pub async fn get_positions_of_mint(
+    rpc_client: &rpc::Client,
     positions_data: &locked::Data,
-    position_owners_map: &mut HashMap<Pubkey, Pubkey>,
-    mint: &str,
+    position_owners_map: &mut PositionOwnersMap,
+    mint: &Pubkey,
     timestamp: i64,
 ) -> Result<(HashMap<Pubkey, PositionV0>, HashMap<Pubkey, Position>)> {
-    let voting_mint_config = &positions_data.mint_configs[&Pubkey::from_str(mint)?];
+    // a DAO without positions has no registrar among those read
+    let Some(voting_mint_config) = positions_data.mint_configs.get(mint) else {
+        return Ok((HashMap::new(), HashMap::new()));
+    };
     let voting_mint_config = VotingMintConfigV0 {
         mint: voting_mint_config.mint,
         baseline_vote_weight_scaled_factor: voting_mint_config.baseline_vote_weight_scaled_factor,
@@ -211,20 +236,17 @@ pub async fn get_positions_of_mint(
 
     for (pubkey, position) in positions_data.positions.iter() {
         if let Some(position_mint) = positions_data.registrar_to_mint.get(&position.registrar) {
-            if position_mint.to_string().as_str() == mint {
+            if position_mint == mint {
                 positions_raw.insert(*pubkey, *position);
                 let owner: Result<Pubkey> = match position_owners_map.get(pubkey) {
-                    Some(owner) => Ok(*owner),
-                    None => {
-                        let client = rpc::Client::default();
-                        match client.get_owner_by_mint(&position.mint).await {
-                            Ok(owner) => {
-                                position_owners_map.insert(*pubkey, owner);
-                                Ok(owner)
-                            }
-                            Err(e) => Err(e.into()),
+                    Some(holding) => Ok(holding.owner),
+                    None => match rpc_client.get_holding_by_mint(&position.mint).await {
+                        Ok(holding) => {
+                            position_owners_map.insert(*pubkey, holding);
+                            Ok(holding.owner)
                         }
-                    }
+                        Err(e) => Err(e.into()),
+                    },
                 };
                 match owner {
                     Err(e) => println!("Warning: could not get owner for position {pubkey}: {e}"),
@@ -245,19 +267,226 @@ pub async fn get_positions_of_mint(
             println!("No mint found for registrar {}", position.registrar)
         }
     }
+
+    // many owners hold several positions
+    let owners: Vec<Pubkey> = positions
+        .keys()
+        .filter_map(|pubkey| position_owners_map.get(pubkey).map(|holding| holding.owner))
+        .collect::<HashSet<Pubkey>>()
+        .into_iter()
+        .collect();
+    // owners whose kind cannot be read are left unknown
+    let owner_kinds: HashMap<Pubkey, rpc::OwnerKind> = match rpc_client
+        .get_owner_kinds(&owners.iter().collect::<Vec<&Pubkey>>())
+        .await
+    {
+        Ok(kinds) => owners.iter().copied().zip(kinds).collect(),
+        Err(e) => {
+            println!("Warning: could not get the kinds of position owners: {e}");
+            HashMap::new()
+        }
+    };
+    for (pubkey, position) in positions.iter_mut() {
+        if let Some(kind) = position_owners_map
+            .get(pubkey)
+            .and_then(|holding| owner_kinds.get(&holding.owner))
+        {
+            position.owner_kind = *kind;
+        }
+    }
     Ok((positions_raw, positions))
 }
-pub type PositionOwnersMap = HashMap<Pubkey, Pubkey>;
+/// Where the NFT of each position was last found
+pub type PositionOwnersMap = HashMap<Pubkey, rpc::Holding>;
 #[derive(Default)]
 pub struct PositionOwners {
-    pub vehnt: PositionOwnersMap,
-    pub veiot: PositionOwnersMap,
-    pub vemobile: PositionOwnersMap,
+    /// The owners of the positions of each DAO, by the name of its voting
+    /// power
+    owners: HashMap<String, PositionOwnersMap>,
+    reconciled_at: Option<Instant>,
 }
 
+/// How often the owners of known positions are checked for NFT transfers
+const OWNER_RECONCILIATION_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
 impl PositionOwners {
     pub fn is_empty(&self) -> bool {
-        self.vehnt.is_empty() && self.veiot.is_empty() && self.vemobile.is_empty()
+        self.owners.values().all(|owners| owners.is_empty())
+    }
+
+    pub fn of_mut(&mut self, dao: &Dao) -> &mut PositionOwnersMap {
+        self.owners.entry(dao.ve_name()).or_default()
+    }
+
+    fn reconciliation_due(&self) -> bool {
+        match self.reconciled_at {
+            Some(at) => at.elapsed() >= OWNER_RECONCILIATION_INTERVAL,
+            None => true,
+        }
+    }
+
+    /// Checks that the NFT of every known position is still held by the
+    /// token account it was found in, reading the token accounts in bulk, and
+    /// resolves the holding again of the positions whose NFT moved or whose
+    /// token account was closed. Positions that no longer exist are
+    /// forgotten, and those whose holding cannot be resolved are left as they
+    /// were until the next reconciliation. Returns the number of transfers
+    /// detected.
+    pub async fn reconcile(
+        &mut self,
+        rpc_client: &rpc::Client,
+        positions: &[(Pubkey, PositionV0)],
+    ) -> Result<usize> {
+        let mints: HashMap<Pubkey, Pubkey> = positions
+            .iter()
+            .map(|(pubkey, position)| (*pubkey, position.mint))
+            .collect();
+        let mut transfers = 0;
+        for map in self.owners.values_mut() {
+            map.retain(|position, _| mints.contains_key(position));
+            let known: Vec<(Pubkey, Pubkey, rpc::Holding)> = map
+                .iter()
+                .map(|(position, holding)| (*position, mints[position], *holding))
+                .collect();
+            let data = rpc_client
+                .get_multiple_accounts_data(
+                    &known
+                        .iter()
+                        .map(|(_, _, holding)| &holding.token_account)
+                        .collect::<Vec<&Pubkey>>(),
+                )
+                .await?;
+            let moved: Vec<&(Pubkey, Pubkey, rpc::Holding)> = known
+                .iter()
+                .zip(data)
+                .filter(|((_, mint, holding), data)| {
+                    let holder = data
+                        .as_deref()
+                        .map(|data| rpc::nft_holder(&holding.token_account, mint, data));
+                    !matches!(holder, Some(Ok(holder)) if holder == holding.owner)
+                })
+                .map(|(known, _)| known)
+                .collect();
+            if moved.is_empty() {
+                continue;
+            }
+            let holdings = rpc_client
+                .get_all_holdings_by_mint(
+                    &moved
+                        .iter()
+                        .map(|(_, mint, _)| mint)
+                        .collect::<Vec<&Pubkey>>(),
+                    100,
+                )
+                .await?;
+            for ((position, _, previous), holding) in moved.into_iter().zip(holdings) {
+                match holding {
+                    Ok(holding) => {
+                        if holding.owner != previous.owner {
+                            transfers += 1;
+                        }
+                        map.insert(*position, holding);
+                    }
+                    Err(e) => println!("Warning: could not get owner for position {position}: {e}"),
+                }
+            }
+        }
+        self.reconciled_at = Some(Instant::now());
+        Ok(transfers)
+    }
+}
+
+/// What a refresh keeps for the next one, so that only the positions and
+/// delegations that changed are decoded and have their pending rewards
+/// computed from scratch. Voting weights depend on the time and are always
+/// recomputed.
+#[derive(Default)]
+pub struct RefreshCache {
+    positions: locked::DecodedPositions,
+    delegations: HashMap<Pubkey, CachedDelegation>,
+    /// The DAOs and when they were discovered
+    daos: Option<(Instant, Daos)>,
+}
+
+/// How often the DAOs are discovered again, which takes two program account
+/// scans, to pick up new subDAOs
+const DAOS_DISCOVERY_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+impl RefreshCache {
+    /// The DAOs of a previous refresh, discovered again once they are old.
+    /// The previous ones are kept if that fails.
+    async fn daos(&mut self, rpc_client: &rpc::Client) -> Result<Daos> {
+        if let Some((at, daos)) = &self.daos {
+            if at.elapsed() < DAOS_DISCOVERY_INTERVAL {
+                return Ok(daos.clone());
+            }
+        }
+        match Daos::discover(rpc_client).await {
+            Ok(daos) => {
+                self.daos = Some((Instant::now(), daos.clone()));
+                Ok(daos)
+            }
+            Err(e) => match &self.daos {
+                Some((_, daos)) => {
+                    println!("Warning: could not discover the DAOs again: {e}");
+                    Ok(daos.clone())
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+struct CachedDelegation {
+    data_hash: u64,
+    /// The number of epoch summaries the pending rewards were computed with
+    epochs: usize,
+    delegated: DelegatedPosition,
+}
+
+impl CachedDelegation {
+    /// The delegation, whose account data hashes to `data_hash`, with its
+    /// pending rewards over `epochs`. Those of `cached` are kept, adding the
+    /// epochs completed since, unless the delegation or its position changed.
+    fn refresh(
+        cached: Option<Self>,
+        (pubkey, data_hash, delegated_position): (Pubkey, u64, DelegatedPositionV0),
+        position: &PositionV0,
+        position_changed: bool,
+        daos: &Daos,
+        epochs: &[epoch_info::EpochSummary],
+        voting_mint_config: &VotingMintConfigV0,
+    ) -> Result<Self> {
+        let delegated = match cached {
+            Some(mut cached)
+                if cached.data_hash == data_hash
+                    && cached.epochs <= epochs.len()
+                    && !position_changed =>
+            {
+                cached.delegated.add_completed_epochs(
+                    cached.epochs,
+                    epochs,
+                    position,
+                    voting_mint_config,
+                )?;
+                // the subDAO may have been discovered since
+                cached.delegated.sub_dao = daos.name_of(&delegated_position.sub_dao);
+                cached.delegated
+            }
+            _ => DelegatedPosition::try_from_delegated_position_v0(
+                pubkey,
+                delegated_position,
+                daos,
+                epochs,
+                position,
+                voting_mint_config,
+            )?,
+        };
+        Ok(Self {
+            data_hash,
+            epochs: epochs.len(),
+            delegated,
+        })
     }
 }
 
@@ -265,103 +494,121 @@ pub async fn get_data(
     rpc_client: &rpc::Client,
     epoch_info: Arc<Vec<epoch_info::EpochSummary>>,
     position_owners_map: &mut PositionOwners,
+    cache: &mut RefreshCache,
 ) -> Result<AllPositionsData> {
-    let mut all_data = AllPositionsData::new();
-    let d = &mut all_data.vehnt;
-    let s = &mut all_data.stats;
-
-    let positions_data = locked::get_data(rpc_client).await?;
+    let mut positions_data = locked::get_data_since(rpc_client, &cache.positions).await?;
+    let decoded_positions = std::mem::take(&mut positions_data.decoded);
+    // pin every later read at or after the slot the positions were read at
+    let rpc_client = &rpc_client
+        .clone()
+        .with_min_context_slot(positions_data.slots.max);
+    let mut slots = positions_data.slots;
+
+    // voting power is computed at the Clock sysvar time rather than the local
+    // clock, so that it matches what the helium_sub_daos program computes
+    let clock = rpc_client.get_clock().await?;
+    slots = slots.merge(clock.slots);
+    let timestamp = clock.value.unix_timestamp;
+    let daos = cache.daos(rpc_client).await?;
+    let mut all_data = AllPositionsData::new(timestamp, daos.clone());
     // if the map is empty, we assume it hasn't been initialized and so we initialize it
     if position_owners_map.is_empty() {
         println!("Initializing position owners map");
-        let client = rpc::Client::default();
         let position_keys = positions_data
             .positions
             .iter()
             .map(|p| &p.1.mint)
             .collect::<Vec<&Pubkey>>();
-        let owners = client.get_all_owners_by_mint(&position_keys, 100).await?;
-        positions_data
-            .positions
-            .iter()
-            .zip(owners.iter())
-            .for_each(|(p, k)| {
-                if let Some(mint) = positions_data.registrar_to_mint.get(&p.1.registrar) {
-                    match mint.to_string().as_ref() {
-                        HNT_MINT => {
-                            position_owners_map.vehnt.insert(p.0, *k);
-                        }
-                        IOT_MINT => {
-                            position_owners_map.veiot.insert(p.0, *k);
-                        }
-                        MOBILE_MINT => {
-                            position_owners_map.vemobile.insert(p.0, *k);
-                        }
-                        _ => println!("Warning: Unknown mint {} for position {}", mint, p.0),
+        let holdings = rpc_client
+            .get_all_holdings_by_mint(&position_keys, 100)
+            .await?;
+        for ((pubkey, position), holding) in positions_data.positions.iter().zip(holdings) {
+            let holding = match holding {
+                Ok(holding) => holding,
+                // looked up again along with new positions
+                Err(e) => {
+                    println!("Warning: could not get owner for position {pubkey}: {e}");
+                    continue;
+                }
+            };
+            if let Some(mint) = positions_data.registrar_to_mint.get(&position.registrar) {
+                match daos.by_mint(mint) {
+                    Some(dao) => {
+                        position_owners_map.of_mut(dao).insert(*pubkey, holding);
                     }
-                } else {
-                    println!("Warning: No mint found for registrar {}", p.1.registrar)
+                    None => println!("Warning: Unknown mint {} for position {}", mint, pubkey),
                 }
-            });
+            } else {
+                println!(
+                    "Warning: No mint found for registrar {}",
+                    position.registrar
+                )
+            }
+        }
+        position_owners_map.reconciled_at = Some(Instant::now());
+    } else if position_owners_map.reconciliation_due() {
+        // owners are otherwise only resolved for new positions
+        match position_owners_map
+            .reconcile(rpc_client, &positions_data.positions)
+            .await
+        {
+            Ok(transfers) => println!("Reconciled position owners: {transfers} transfers"),
+            Err(e) => println!("Warning: could not reconcile position owners: {e}"),
+        }
     }
 
-    let (_veiot_positions_raw, veiot_positions) = get_positions_of_mint(
-        &positions_data,
-        &mut position_owners_map.veiot,
-        IOT_MINT,
-        d.timestamp,
-    )
-    .await?;
-    all_data.veiot.positions = veiot_positions
-        .into_values()
-        .map(|mut p| {
-            p.voting_weight /= PRECISION_FACTOR;
-            p
-        })
-        .collect();
-    all_data.veiot.positions_total_len = all_data.veiot.positions.len();
-    println!("veiot    positions: {:>#5}", all_data.veiot.positions.len());
-
-    let (_vemobile_positions_raw, vemobile_positions) = get_positions_of_mint(
-        &positions_data,
-        &mut position_owners_map.vemobile,
-        MOBILE_MINT,
-        d.timestamp,
-    )
-    .await?;
-    all_data.vemobile.positions = vemobile_positions
-        .into_values()
-        .map(|mut p| {
-            p.voting_weight /= PRECISION_FACTOR;
-            p
-        })
-        .collect();
-    all_data.vemobile.positions_total_len = all_data.vemobile.positions.len();
-    println!(
-        "vemobile positions: {:>#5}",
-        all_data.vemobile.positions.len()
-    );
+    for dao in daos.sub_daos() {
+        let (_, positions) = get_positions_of_mint(
+            rpc_client,
+            &positions_data,
+            position_owners_map.of_mut(dao),
+            &dao.mint,
+            timestamp,
+        )
+        .await?;
+        let data = all_data
+            .positions
+            .entry(dao.ve_name())
+            .or_insert_with(|| DaoPositionData::new(timestamp));
+        data.positions = positions
+            .into_values()
+            .map(|mut p| {
+                p.voting_weight /= PRECISION_FACTOR;
+                p
+            })
+            .collect();
+        data.positions_total_len = data.positions.len();
+        println!(
+            "{:<8} positions: {:>#5}",
+            dao.ve_name(),
+            data.positions.len()
+        );
+    }
 
+    let hnt = daos.dao().ok_or(Error::Custom("Helium DAO not found"))?;
     let (vehnt_positions_raw, mut vehnt_positions) = get_positions_of_mint(
+        rpc_client,
         &positions_data,
-        &mut position_owners_map.vehnt,
-        HNT_MINT,
-        d.timestamp,
+        position_owners_map.of_mut(hnt),
+        &hnt.mint,
+        timestamp,
     )
     .await?;
+    let s = &mut all_data.stats;
+    let d = all_data
+        .positions
+        .entry(hnt.ve_name())
+        .or_insert_with(|| DaoPositionData::new(timestamp));
 
     // this next section only applies to veHNT since veHNT can delegate towards subDAOs
     let delegated_positions = get_delegated_positions(rpc_client).await?;
-    let delegated_positions = delegated_positions
-        .iter()
-        .map(|(pubkey, account)| {
-            let mut data = account.data.as_slice();
-            DelegatedPositionV0::try_deserialize(&mut data).map(|position| (pubkey, position))
-        })
-        .filter(|result| result.is_ok())
-        .collect::<AnchorResult<Vec<_>>>()?;
+    slots = slots.merge(delegated_positions.slots);
+    let delegated_positions = delegated_positions.value;
 
-    let voting_mint_config = &positions_data.mint_configs[&Pubkey::from_str(HNT_MINT)?];
+    let voting_mint_config = positions_data
+        .mint_configs
+        .get(&hnt.mint)
+        .ok_or(Error::NoRegistrarForMint(hnt.mint))?;
     let voting_mint_config = VotingMintConfigV0 {
         mint: voting_mint_config.mint,
         baseline_vote_weight_scaled_factor: voting_mint_config.baseline_vote_weight_scaled_factor,
@@ -374,18 +621,24 @@ pub async fn get_data(
         digit_shift: voting_mint_config.digit_shift,
     };
 
-    for (pubkey, delegated_position) in delegated_positions {
+    let mut delegations = HashMap::new();
+    for (pubkey, data_hash, delegated_position) in delegated_positions {
         let position_v0 = vehnt_positions_raw.get(&delegated_position.position);
         let position = vehnt_positions.get_mut(&delegated_position.position);
         match (position_v0, position) {
             (Some(position_v0), Some(position)) => {
-                position.delegated = Some(DelegatedPosition::try_from_delegated_position_v0(
-                    *pubkey,
-                    delegated_position,
-                    &epoch_info,
+                let position_changed = positions_data.changed.contains(&delegated_position.position);
+                let cached = CachedDelegation::refresh(
+                    cache.delegations.remove(&pubkey),
+                    (pubkey, data_hash, delegated_position),
                     position_v0,
+                    position_changed,
+                    &daos,
+                    &epoch_info,
                     &voting_mint_config,
-                )?);
+                )?;
+                position.delegated = Some(cached.delegated.clone());
+                delegations.insert(pubkey, cached);
                 d.delegated_positions
                     .push(PositionLegacy::from(position.clone()));
             }
@@ -404,51 +657,27 @@ pub async fn get_data(
         }
     }
 
-    let mut hnt_amounts = vec![];
-    let mut vehnt_amounts = vec![];
-    let mut lockups = vec![];
+    let mut network = Amounts::default();
+    let mut undelegated = Amounts::default();
+    // every subDAO is listed, even without delegations
+    let mut sub_daos: BTreeMap<String, Amounts> = daos
+        .sub_daos()
+        .map(|dao| (dao.symbol.clone(), Amounts::default()))
+        .collect();
 
     // stats for veHNT positions
     for (_, position) in vehnt_positions {
         let duration = (position.end_ts - position.start_ts) as u128;
-        s.network.total.hnt += position.locked_tokens;
-        s.network.total.fall_rate += position.vehnt_info.pre_genesis_end_fall_rate;
-        s.network.total.vehnt += position.voting_weight;
-        s.network.total.count += 1;
-        s.network.total.lockup += duration;
-
-        if let Some(delegated) = &position.delegated {
-            hnt_amounts.push((position.locked_tokens, delegated.sub_dao));
-            vehnt_amounts.push((position.voting_weight, delegated.sub_dao));
-            lockups.push((duration, delegated.sub_dao));
-            match delegated.sub_dao {
-                SubDao::Mobile => {
-                    s.mobile.total.hnt += position.locked_tokens;
-                    s.mobile.total.fall_rate += position.vehnt_info.pre_genesis_end_fall_rate;
-                    s.mobile.total.vehnt += position.voting_weight;
-                    s.mobile.total.count += 1;
-                    s.mobile.total.lockup += duration;
-                }
-                SubDao::Iot => {
-                    s.iot.total.hnt += position.locked_tokens;
-                    s.iot.total.fall_rate += position.vehnt_info.pre_genesis_end_fall_rate;
-                    s.iot.total.vehnt += position.voting_weight;
-                    s.iot.total.count += 1;
-                    s.iot.total.lockup += duration;
-                }
-                SubDao::Unknown => {
-                    return Err(Error::Custom("Unknown subdao"));
-                }
-            }
-        } else {
-            hnt_amounts.push((position.locked_tokens, SubDao::Unknown));
-            vehnt_amounts.push((position.voting_weight, SubDao::Unknown));
-            lockups.push((duration, SubDao::Unknown));
-            s.undelegated.total.hnt += position.locked_tokens;
-            s.undelegated.total.fall_rate += position.vehnt_info.pre_genesis_end_fall_rate;
-            s.undelegated.total.vehnt += position.voting_weight;
-            s.undelegated.total.count += 1;
-            s.undelegated.total.lockup += duration;
+        network.push(&position, duration);
+        match &position.delegated {
+            Some(delegated) => match sub_daos.get_mut(&delegated.sub_dao) {
+                Some(amounts) => amounts.push(&position, duration),
+                None => println!(
+                    "Warning: position {} is delegated to unknown subDAO {}",
+                    position.position_key, delegated.sub_dao_key
+                ),
+            },
+            None => undelegated.push(&position, duration),
         }
         let mut position_copy = position.clone();
         position_copy.voting_weight /= PRECISION_FACTOR;
@@ -457,67 +686,76 @@ pub async fn get_data(
     d.positions_total_len = d.positions.len();
     println!("vehnt    positions: {:>#5}", d.positions.len());
 
-    s.network.stats = get_stats(
-        None,
-        s.network.total,
-        vehnt_amounts.clone(),
-        hnt_amounts.clone(),
-        lockups.clone(),
-    );
-    s.iot.stats = get_stats(
-        Some(SubDao::Iot),
-        s.iot.total,
-        vehnt_amounts.clone(),
-        hnt_amounts.clone(),
-        lockups.clone(),
-    );
-    s.mobile.stats = get_stats(
-        Some(SubDao::Mobile),
-        s.mobile.total,
-        vehnt_amounts.clone(),
-        hnt_amounts.clone(),
-        lockups.clone(),
-    );
-    s.undelegated.stats = get_stats(
-        Some(SubDao::Unknown),
-        s.undelegated.total,
-        vehnt_amounts.clone(),
-        hnt_amounts.clone(),
-        lockups.clone(),
-    );
-
-    let total_positions = all_data.vehnt.positions_total_len
-        + all_data.vemobile.positions_total_len
-        + all_data.veiot.positions_total_len;
+    s.network = network.data();
+    s.undelegated = undelegated.data();
+    s.sub_daos = sub_daos
+        .into_iter()
+        .map(|(symbol, amounts)| (symbol, amounts.data()))
+        .collect();
+
+    let total_positions: usize = all_data
+        .positions
+        .values()
+        .map(|data| data.positions_total_len)
+        .sum();
     println!("Organized data for positions {} positions", total_positions);
 
+    all_data.slots = Some(slots);
+    cache.positions = decoded_positions;
+    cache.delegations = delegations;
     Ok(all_data)
 }
 
-fn get_stats(
-    filter: Option<SubDao>,
+/// The positions of a group, such as those delegated to a subDAO, to
+/// compute its `Data` from
+#[derive(Default)]
+struct Amounts {
     total: Total,
-    mut vehnt_amounts: Vec<(u128, SubDao)>,
-    mut hnt_amounts: Vec<(u64, SubDao)>,
-    mut lockups: Vec<(u128, SubDao)>,
-) -> Stats {
-    if let Some(filter) = filter {
-        vehnt_amounts.retain(|(_, sub_dao)| *sub_dao == filter);
-        hnt_amounts.retain(|(_, sub_dao)| *sub_dao == filter);
-        lockups.retain(|(_, sub_dao)| *sub_dao == filter);
+    vehnt: Vec<u128>,
+    hnt: Vec<u64>,
+    lockups: Vec<u128>,
+}
+
+impl Amounts {
+    fn push(&mut self, position: &Position, duration: u128) {
+        self.total.hnt += position.locked_tokens;
+        self.total.fall_rate += position.vehnt_info.pre_genesis_end_fall_rate;
+        self.total.vehnt += position.voting_weight;
+        self.total.count += 1;
+        self.total.lockup += duration;
+        self.vehnt.push(position.voting_weight);
+        self.hnt.push(position.locked_tokens);
+        self.lockups.push(duration);
     }
 
+    fn data(self) -> Data {
+        Data {
+            total: self.total,
+            stats: get_stats(self.total, self.vehnt, self.hnt, self.lockups),
+        }
+    }
+}
+
+fn get_stats(
+    total: Total,
+    mut vehnt_amounts: Vec<u128>,
+    mut hnt_amounts: Vec<u64>,
+    mut lockups: Vec<u128>,
+) -> Stats {
     let mut stats = Stats::default();
-    vehnt_amounts.sort_by(|a, b| b.0.cmp(&a.0));
-    stats.median_vehnt = vehnt_amounts[total.count / 2].0;
+    if total.count == 0 {
+        return stats;
+    }
+    vehnt_amounts.sort_by(|a, b| b.cmp(a));
+    stats.median_vehnt = vehnt_amounts[total.count / 2];
     stats.avg_vehnt = total.vehnt / total.count as u128;
 
-    hnt_amounts.sort_by(|a, b| b.0.cmp(&a.0));
-    stats.median_hnt = hnt_amounts[total.count / 2].0;
+    hnt_amounts.sort_by(|a, b| b.cmp(a));
+    stats.median_hnt = hnt_amounts[total.count / 2];
     stats.avg_hnt = total.hnt / total.count as u64;
 
-    lockups.sort_by(|a, b| b.0.cmp(&a.0));
-    stats.median_lockup = lockups[total.count / 2].0;
+    lockups.sort_by(|a, b| b.cmp(a));
+    stats.median_lockup = lockups[total.count / 2];
     stats.avg_lockup = total.lockup / total.count as u128;
     stats
 }
@@ -529,72 +767,61 @@ impl Positions {
             &rpc_client,
             epoch_summaries.into(),
             &mut PositionOwners::default(),
+            &mut RefreshCache::default(),
         )
         .await?;
-        let d = all_data.vehnt;
+        let positions_total_len = all_data.vehnt().map_or(0, |d| d.positions_total_len);
         let s = all_data.stats;
 
         if self.verify {
-            let iot_sub_dao_raw = rpc_client
-                .get_account(&Pubkey::from_str(IOT_SUBDAO).unwrap())
-                .await?;
-            let iot_sub_dao = SubDaoV0::try_deserialize(&mut iot_sub_dao_raw.data.as_slice())?;
-            let mobile_sub_dao_raw = rpc_client
-                .get_account(&Pubkey::from_str(MOBILE_SUBDAO).unwrap())
-                .await?;
-            let mobile_sub_dao =
-                SubDaoV0::try_deserialize(&mut mobile_sub_dao_raw.data.as_slice())?;
-
-            let mobile_vehnt_est = mobile_sub_dao.vehnt_delegated
-                + mobile_sub_dao.vehnt_fall_rate
-                    * u128::try_from(d.timestamp - mobile_sub_dao.vehnt_last_calculated_ts)
-                        .unwrap();
-
-            let iot_vehnt_est = iot_sub_dao.vehnt_delegated
-                + iot_sub_dao.vehnt_fall_rate
-                    * u128::try_from(d.timestamp - iot_sub_dao.vehnt_last_calculated_ts).unwrap();
-
-            println!("Total MOBILE veHNT : {}", s.mobile.total.vehnt);
-            println!("Est MOBILE veHNT   : {}", mobile_vehnt_est);
-            println!(
-                "MOBILE veHNT Diff  : {}",
-                i128::try_from(mobile_vehnt_est).unwrap()
-                    - i128::try_from(s.mobile.total.vehnt).unwrap()
-            );
-            println!("Total IOT veHNT    : {}", s.iot.total.vehnt);
-            println!("Est IOT veHNT      : {}", iot_vehnt_est);
-            println!(
-                "IOT veHNT Diff     : {}",
-                i128::try_from(iot_vehnt_est).unwrap() - i128::try_from(s.iot.total.vehnt).unwrap()
-            );
+            // compare against sub DAOs read no earlier than the snapshot
+            let rpc_client = match all_data.slots {
+                Some(slots) => rpc_client.clone().with_min_context_slot(slots.max),
+                None => rpc_client.clone(),
+            };
+            for dao in all_data.daos.sub_daos() {
+                let sub_dao_raw = rpc_client.get_account(&dao.key).await?;
+                let sub_dao = SubDaoV0::try_deserialize(&mut sub_dao_raw.data.as_slice())?;
+                let total = s
+                    .sub_daos
+                    .get(&dao.symbol)
+                    .map(|data| data.total)
+                    .unwrap_or_default();
+                let vehnt_est = sub_dao.vehnt_delegated
+                    + sub_dao.vehnt_fall_rate
+                        * u128::try_from(s.timestamp - sub_dao.vehnt_last_calculated_ts).unwrap();
+                let name = dao.symbol.to_uppercase();
+                println!("Total {name} veHNT : {}", total.vehnt);
+                println!("Est {name} veHNT   : {}", vehnt_est);
+                println!(
+                    "{name} veHNT Diff  : {}",
+                    i128::try_from(vehnt_est).unwrap() - i128::try_from(total.vehnt).unwrap()
+                );
+                println!("{} fall        : {}", dao.symbol, total.fall_rate);
+                println!("{} est fall    : {}", dao.symbol, sub_dao.vehnt_fall_rate);
+                println!(
+                    "{} diff        : {}",
+                    dao.symbol,
+                    i128::try_from(sub_dao.vehnt_fall_rate).unwrap()
+                        - i128::try_from(total.fall_rate).unwrap()
+                );
+            }
             println!("Total veHNT        : {}", s.network.total.vehnt);
-            println!("mobile fall        : {}", s.mobile.total.fall_rate);
-            println!("mobile est fall    : {}", mobile_sub_dao.vehnt_fall_rate);
-            println!(
-                "mobile diff        : {}",
-                i128::try_from(mobile_sub_dao.vehnt_fall_rate).unwrap()
-                    - i128::try_from(s.mobile.total.fall_rate).unwrap()
-            );
-            println!("iot fall           : {}", s.iot.total.fall_rate);
-            println!("iot est fall       : {}", iot_sub_dao.vehnt_fall_rate);
-            println!(
-                "iot diff           : {}",
-                i128::try_from(iot_sub_dao.vehnt_fall_rate).unwrap()
-                    - i128::try_from(s.iot.total.fall_rate).unwrap()
-            );
         } else {
-            let delegated_hnt = s.mobile.total.vehnt + s.iot.total.vehnt;
+            let delegated_hnt: u128 = s.sub_daos.values().map(|data| data.total.vehnt).sum();
             let undelegated_hnt = s.network.total.vehnt - delegated_hnt;
-            println!(
-                "Total MOBILE veHNT     :   {} ({}% of delegated)",
-                format_vehnt(s.mobile.total.vehnt),
-                percentage(s.mobile.total.vehnt, delegated_hnt)
-            );
-            println!(
-                "Total IOT veHNT        : {} ({}% of delegated)",
-                format_vehnt(s.iot.total.vehnt),
-                percentage(s.iot.total.vehnt, delegated_hnt)
-            );
+            for dao in all_data.daos.sub_daos() {
+                let vehnt = s
+                    .sub_daos
+                    .get(&dao.symbol)
+                    .map_or(0, |data| data.total.vehnt);
+                println!(
+                    "Total {:<6} veHNT     : {} ({}% of delegated)",
+                    dao.symbol.to_uppercase(),
+                    format_vehnt(vehnt),
+                    percentage(vehnt, delegated_hnt)
+                );
+            }
             println!(
                 "Total undelegated veHNT:   {} ({}% of total)",
                 format_vehnt(undelegated_hnt),
@@ -605,7 +832,7 @@ impl Positions {
                 format_vehnt(s.network.total.vehnt)
             );
         }
-        println!("Total positions        :         {}", d.positions_total_len);
+        println!("Total positions        :         {}", positions_total_len);
 
         println!(
             "Total HNT locked       :    {}",
@@ -626,8 +853,9 @@ impl Positions {
         );
 
         println!("Network {}", s.network);
-        println!("MOBILE {}", s.mobile);
-        println!("IOT {}", s.iot);
+        for (symbol, data) in &s.sub_daos {
+            println!("{} {}", symbol.to_uppercase(), data);
+        }
 
         Ok(())
     }
@@ -643,6 +871,8 @@ use helium_api::models::Hnt;
 #[derive(Clone, Default, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Position {
     pub owner: String,
+    #[serde(default)]
+    pub owner_kind: rpc::OwnerKind,
     pub mint: String,
     pub position_key: String,
     pub locked_tokens: u64,
@@ -663,7 +893,7 @@ pub struct PositionLegacy {
     pub position_key: String,
     pub delegated_position_key: String,
     pub hnt_amount: Hnt,
-    pub sub_dao: SubDao,
+    pub sub_dao: String,
     pub last_claimed_epoch: u64,
     pub start_ts: i64,
     pub genesis_end_ts: i64,
@@ -697,71 +927,117 @@ impl From<Position> for PositionLegacy {
 #[derive(Clone, Default, Debug, serde::Serialize, serde::Deserialize)]
 pub struct DelegatedPosition {
     pub delegated_position_key: String,
-    pub sub_dao: SubDao,
+    /// The symbol of the subDAO the position is delegated to, or its
+    /// account if the subDAO was not discovered
+    pub sub_dao: String,
+    /// The `SubDaoV0` account the position is delegated to
+    #[serde(default)]
+    pub sub_dao_key: String,
     pub last_claimed_epoch: u64,
     pub pending_rewards: u64,
 }
 
+const FIRST_EPOCH: usize = 19465;
+const FIRST_EPOCH_WITH_VEHNT: usize = 19467;
+
 impl DelegatedPosition {
-    fn try_from_delegated_position_v0(
+    pub fn try_from_delegated_position_v0(
         delegated_position_key: Pubkey,
         delegated_position: DelegatedPositionV0,
+        daos: &Daos,
         epochs: &[epoch_info::EpochSummary],
         position: &PositionV0,
         voting_mint_config: &VotingMintConfigV0,
     ) -> Result<Self> {
-        let mut pending_rewards = 0;
-        const FIRST_EPOCH: usize = 19465;
-        const FIRST_EPOCH_WITH_VEHNT: usize = 19467;
-
         let first_unclaimed_epoch = std::cmp::max(
             (delegated_position.last_claimed_epoch + 1) as usize,
             FIRST_EPOCH_WITH_VEHNT,
         );
-        // the last epochs is initialized but incomplete
-        let last_reward_epoch = epochs.len() - 1 + FIRST_EPOCH;
-        let sub_dao = SubDao::try_from(delegated_position.sub_dao)?;
-
-        for i in first_unclaimed_epoch..last_reward_epoch {
-            let epoch_summary = &epochs[i - FIRST_EPOCH];
-            assert_eq!(epoch_summary.epoch, i as u64);
-            let ts = epoch_summary.epoch_start_at_ts.unwrap();
-            let delegated_vehnt_at_epoch = position.voting_power(voting_mint_config, ts)? as u128;
-
-            let (delegation_rewards_issued, vehnt_at_epoch_start) = match sub_dao {
-                SubDao::Mobile => (epoch_summary.mobile_delegation_rewards_issued as u128, {
-                    let mut mobile_vehnt = *epoch_summary.mobile_vehnt_at_epoch_start.get_decimal();
-                    mobile_vehnt.set_scale(0)?;
-                    mobile_vehnt.to_u128().unwrap()
-                }),
-                SubDao::Iot => (epoch_summary.iot_delegation_rewards_issued as u128, {
-                    let mut mobile_vehnt = *epoch_summary.iot_vehnt_at_epoch_start.get_decimal();
-                    mobile_vehnt.set_scale(0)?;
-                    mobile_vehnt.to_u128().unwrap()
-                }),
-                _ => panic!("Error: calculating earnings for undelegated position?!"),
-            };
-
-            pending_rewards += u64::try_from(
-                delegated_vehnt_at_epoch
-                    .checked_mul(delegation_rewards_issued)
-                    .unwrap()
-                    .checked_div(vehnt_at_epoch_start)
-                    .unwrap(),
-            )
-            .unwrap();
-        }
+        let pending_rewards = rewards_from_epoch(
+            &delegated_position.sub_dao,
+            first_unclaimed_epoch,
+            epochs,
+            position,
+            voting_mint_config,
+        )?;
 
         Ok(Self {
             pending_rewards,
             delegated_position_key: delegated_position_key.to_string(),
-            sub_dao,
+            sub_dao: daos.name_of(&delegated_position.sub_dao),
+            sub_dao_key: delegated_position.sub_dao.to_string(),
             last_claimed_epoch: delegated_position.last_claimed_epoch,
         })
     }
+
+    /// Adds the rewards of the epochs completed since the pending rewards
+    /// were computed with the first `computed_epochs` summaries. Only valid
+    /// if neither the delegation nor its position changed in between.
+    pub fn add_completed_epochs(
+        &mut self,
+        computed_epochs: usize,
+        epochs: &[epoch_info::EpochSummary],
+        position: &PositionV0,
+        voting_mint_config: &VotingMintConfigV0,
+    ) -> Result {
+        let first_uncomputed_epoch = std::cmp::max(
+            (self.last_claimed_epoch + 1) as usize,
+            std::cmp::max(computed_epochs - 1 + FIRST_EPOCH, FIRST_EPOCH_WITH_VEHNT),
+        );
+        self.pending_rewards += rewards_from_epoch(
+            &Pubkey::from_str(&self.sub_dao_key)?,
+            first_uncomputed_epoch,
+            epochs,
+            position,
+            voting_mint_config,
+        )?;
+        Ok(())
+    }
 }
 
-#[derive(Clone, Default, Debug, serde::Serialize, serde::Deserialize)]
+/// The delegation rewards of a position delegated to the `sub_dao` account
+/// for the completed epochs from `first_epoch` on
+fn rewards_from_epoch(
+    sub_dao: &Pubkey,
+    first_epoch: usize,
+    epochs: &[epoch_info::EpochSummary],
+    position: &PositionV0,
+    voting_mint_config: &VotingMintConfigV0,
+) -> Result<u64> {
+    let mut rewards = 0;
+    // the last epochs is initialized but incomplete
+    let last_reward_epoch = epochs.len() - 1 + FIRST_EPOCH;
+
+    for i in first_epoch..last_reward_epoch {
+        let epoch_summary = &epochs[i - FIRST_EPOCH];
+        assert_eq!(epoch_summary.epoch, i as u64);
+        // nothing could be delegated to a subDAO before its first epoch
+        let Some(sub_dao_summary) = epoch_summary.sub_daos.get(sub_dao) else {
+            continue;
+        };
+        let ts = epoch_summary.epoch_start_at_ts.unwrap();
+        let delegated_vehnt_at_epoch = position.voting_power(voting_mint_config, ts)? as u128;
+
+        let delegation_rewards_issued = sub_dao_summary.delegation_rewards_issued as u128;
+        let vehnt_at_epoch_start = {
+            let mut vehnt = *sub_dao_summary.vehnt_at_epoch_start.get_decimal();
+            vehnt.set_scale(0)?;
+            vehnt.to_u128().unwrap()
+        };
+
+        rewards += u64::try_from(
+            delegated_vehnt_at_epoch
+                .checked_mul(delegation_rewards_issued)
+                .unwrap()
+                .checked_div(vehnt_at_epoch_start)
+                .unwrap(),
+        )
+        .unwrap();
+    }
+    Ok(rewards)
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LockupType {
     Cliff,
@@ -770,6 +1046,16 @@ pub enum LockupType {
     Unlocked,
 }
 
+impl From<LockupKind> for LockupType {
+    fn from(kind: LockupKind) -> Self {
+        match kind {
+            LockupKind::Constant => LockupType::Constant,
+            LockupKind::Cliff => LockupType::Cliff,
+            LockupKind::None => LockupType::Unlocked,
+        }
+    }
+}
+
 impl Position {
     pub async fn try_from_positionv0(
         owner: Pubkey,
@@ -793,11 +1079,9 @@ impl Position {
             voting_weight: vehnt,
             vehnt_info: vehnt_info.into(),
             delegated: None,
-            lockup_type: match position.lockup.kind {
-                LockupKind::Constant => LockupType::Constant,
-                LockupKind::Cliff => LockupType::Cliff,
-                LockupKind::None => LockupType::Unlocked,
-            },
+            lockup_type: position.lockup.kind.into(),
+            // resolved along with the owners of the other positions
+            owner_kind: rpc::OwnerKind::default(),
         })
     }
 }
@@ -839,3 +1123,336 @@ pub struct VotingMintConfig {
     pub lockup_saturation_secs: u64,
     pub digit_shift: i8,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anchor_lang::AccountSerialize;
+    use helium_anchor_gen::voter_stake_registry::{Lockup, Registrar};
+    use tokio::test;
+
+    const EPOCH_SECS: i64 = 24 * 60 * 60;
+
+    fn voting_mint_config() -> VotingMintConfigV0 {
+        VotingMintConfigV0 {
+            mint: Pubkey::from_str(HNT_MINT).unwrap(),
+            baseline_vote_weight_scaled_factor: 1_000_000_000,
+            max_extra_lockup_vote_weight_scaled_factor: 0,
+            genesis_vote_power_multiplier: 0,
+            genesis_vote_power_multiplier_expiration_ts: 0,
+            lockup_saturation_secs: 1,
+            digit_shift: 0,
+        }
+    }
+
+    /// Summaries of the epochs from the first one on, of which the last is
+    /// incomplete, for `sub_dao` and another subDAO
+    fn epochs(sub_dao: &Pubkey, count: usize) -> Vec<epoch_info::EpochSummary> {
+        let other_sub_dao = Pubkey::new_unique();
+        (FIRST_EPOCH..FIRST_EPOCH + count)
+            .map(|epoch| {
+                let mut summary = epoch_info::EpochSummary::from_partial_data(
+                    epoch as u64,
+                    [
+                        (*sub_dao, 10_000_000 + epoch as u128),
+                        (other_sub_dao, 20_000_000),
+                    ],
+                    epoch as i64 * EPOCH_SECS,
+                )
+                .unwrap();
+                for (key, sub_dao_summary) in summary.sub_daos.iter_mut() {
+                    sub_dao_summary.delegation_rewards_issued = if key == sub_dao {
+                        1_000 + epoch as u64
+                    } else {
+                        3_000
+                    };
+                }
+                summary
+            })
+            .collect()
+    }
+
+    fn position(registrar: Pubkey) -> PositionV0 {
+        PositionV0 {
+            registrar,
+            mint: Pubkey::new_unique(),
+            lockup: Lockup {
+                start_ts: 0,
+                end_ts: 4_000_000_000,
+                kind: LockupKind::Constant,
+            },
+            amount_deposited_native: 1_000_000,
+            ..Default::default()
+        }
+    }
+
+    struct Keys {
+        registrar: Pubkey,
+        position: Pubkey,
+        delegated_position: Pubkey,
+    }
+
+    fn account<T: AccountSerialize>(owner: &str, account: &T) -> rpc::Account {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        rpc::Account {
+            lamports: 1,
+            owner: Pubkey::from_str(owner).unwrap(),
+            data,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn fixture(
+        keys: &Keys,
+        position: &PositionV0,
+        delegated_position: &DelegatedPositionV0,
+    ) -> rpc::FixtureTransport {
+        let registrar = Registrar {
+            voting_mints: vec![voting_mint_config()],
+            ..Default::default()
+        };
+        rpc::FixtureTransport::new()
+            .with_account(keys.registrar, &account(HELIUM_VSR_ID, &registrar))
+            .with_account(keys.position, &account(HELIUM_VSR_ID, position))
+            .with_account(
+                keys.delegated_position,
+                &account(HELIUM_DAO_ID, delegated_position),
+            )
+    }
+
+    /// Reads the positions and delegations of `fixture` like `get_data`,
+    /// refreshing the pending rewards of the delegation with `cache`
+    async fn refresh(
+        fixture: rpc::FixtureTransport,
+        cache: &mut RefreshCache,
+        epochs: &[epoch_info::EpochSummary],
+    ) -> DelegatedPosition {
+        let rpc_client = rpc::Client::new_with_transport(fixture);
+        let positions_data = locked::get_data_since(&rpc_client, &cache.positions)
+            .await
+            .unwrap();
+        let (pubkey, data_hash, delegated_position) = get_delegated_positions(&rpc_client)
+            .await
+            .unwrap()
+            .value
+            .pop()
+            .unwrap();
+        let (position_key, position) = positions_data.positions[0];
+        let mint = positions_data.registrar_to_mint[&position.registrar];
+        let cached = CachedDelegation::refresh(
+            cache.delegations.remove(&pubkey),
+            (pubkey, data_hash, delegated_position),
+            &position,
+            positions_data.changed.contains(&position_key),
+            &Daos::default(),
+            epochs,
+            &positions_data.mint_configs[&mint],
+        )
+        .unwrap();
+        let delegated = cached.delegated.clone();
+        cache.positions = positions_data.decoded;
+        cache.delegations.insert(pubkey, cached);
+        delegated
+    }
+
+    /// The pending rewards computed from scratch
+    fn recompute(
+        keys: &Keys,
+        position: &PositionV0,
+        delegated_position: &DelegatedPositionV0,
+        epochs: &[epoch_info::EpochSummary],
+    ) -> u64 {
+        DelegatedPosition::try_from_delegated_position_v0(
+            keys.delegated_position,
+            *delegated_position,
+            &Daos::default(),
+            epochs,
+            position,
+            &voting_mint_config(),
+        )
+        .unwrap()
+        .pending_rewards
+    }
+
+    #[test]
+    async fn test_refresh_cache() {
+        let keys = Keys {
+            registrar: Pubkey::new_unique(),
+            position: Pubkey::new_unique(),
+            delegated_position: Pubkey::new_unique(),
+        };
+        let mut position = position(keys.registrar);
+        let mut delegated_position = DelegatedPositionV0 {
+            position: keys.position,
+            sub_dao: Pubkey::new_unique(),
+            last_claimed_epoch: FIRST_EPOCH as u64,
+            ..Default::default()
+        };
+        let epochs = epochs(&delegated_position.sub_dao, 10);
+        let mut cache = RefreshCache::default();
+
+        // as epochs complete, only the new ones are added to the cached rewards
+        for count in 2..=6 {
+            let fixture = fixture(&keys, &position, &delegated_position);
+            let delegated = refresh(fixture, &mut cache, &epochs[..count]).await;
+            assert_eq!(
+                delegated.pending_rewards,
+                recompute(&keys, &position, &delegated_position, &epochs[..count])
+            );
+        }
+        assert!(
+            cache.delegations[&keys.delegated_position]
+                .delegated
+                .pending_rewards
+                > 0
+        );
+
+        // a changed position invalidates the cached rewards, which were
+        // computed with its previous voting power
+        position.amount_deposited_native *= 2;
+        let mut stale = cache.delegations[&keys.delegated_position]
+            .delegated
+            .clone();
+        stale
+            .add_completed_epochs(6, &epochs[..8], &position, &voting_mint_config())
+            .unwrap();
+        let fixture = fixture(&keys, &position, &delegated_position);
+        let delegated = refresh(fixture, &mut cache, &epochs[..8]).await;
+        let expected = recompute(&keys, &position, &delegated_position, &epochs[..8]);
+        assert_eq!(delegated.pending_rewards, expected);
+        assert_ne!(stale.pending_rewards, expected);
+
+        // so does a changed delegation, such as claimed rewards
+        delegated_position.last_claimed_epoch = FIRST_EPOCH as u64 + 5;
+        let fixture = fixture(&keys, &position, &delegated_position);
+        let delegated = refresh(fixture, &mut cache, &epochs[..8]).await;
+        let expected = recompute(&keys, &position, &delegated_position, &epochs[..8]);
+        assert_eq!(delegated.pending_rewards, expected);
+        assert_eq!(delegated.last_claimed_epoch, FIRST_EPOCH as u64 + 5);
+
+        // and unchanged accounts carry the rewards over again
+        let fixture = fixture(&keys, &position, &delegated_position);
+        let delegated = refresh(fixture, &mut cache, &epochs).await;
+        assert_eq!(
+            delegated.pending_rewards,
+            recompute(&keys, &position, &delegated_position, &epochs)
+        );
+    }
+
+    #[test]
+    async fn test_rewards_of_new_sub_dao() {
+        let sub_dao = Pubkey::new_unique();
+        let position = position(Pubkey::new_unique());
+        let rewards = |epochs: &[epoch_info::EpochSummary], first_epoch| {
+            rewards_from_epoch(
+                &sub_dao,
+                first_epoch,
+                epochs,
+                &position,
+                &voting_mint_config(),
+            )
+            .unwrap()
+        };
+        let mut epochs = epochs(&sub_dao, 8);
+        let all = rewards(&epochs, FIRST_EPOCH_WITH_VEHNT);
+        let since_created = rewards(&epochs, FIRST_EPOCH + 4);
+        assert!(0 < since_created && since_created < all);
+
+        // a subDAO created later only earns rewards from its first epoch on
+        for summary in &mut epochs[..4] {
+            summary.sub_daos.remove(&sub_dao);
+        }
+        assert_eq!(rewards(&epochs, FIRST_EPOCH_WITH_VEHNT), since_created);
+        assert_eq!(rewards(&epochs[..4], FIRST_EPOCH_WITH_VEHNT), 0);
+        assert_eq!(rewards(&epochs, FIRST_EPOCH + 4), since_created);
+    }
+
+    fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> rpc::Account {
+        use spl_token_2022::{
+            solana_program::program_pack::Pack,
+            state::{Account as TokenAccount, AccountState},
+        };
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount {
+            mint,
+            owner,
+            amount,
+            state: AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        rpc::Account {
+            lamports: 1,
+            owner: spl_token_2022::id(),
+            data,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    async fn test_reconcile_owners() {
+        let positions: Vec<(Pubkey, PositionV0)> = (0..4)
+            .map(|_| {
+                let position = PositionV0 {
+                    mint: Pubkey::new_unique(),
+                    ..Default::default()
+                };
+                (Pubkey::new_unique(), position)
+            })
+            .collect();
+        let [unchanged, transferred, closed, unresolved] =
+            [0, 1, 2, 3].map(|i| (positions[i].0, positions[i].1.mint));
+        let holding = |owner| rpc::Holding {
+            token_account: Pubkey::new_unique(),
+            owner,
+        };
+        let (owner, new_owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // none of them held by an associated token account
+        let holdings = [
+            holding(owner),
+            holding(owner),
+            holding(owner),
+            holding(owner),
+        ];
+        let (moved_to, reopened) = (holding(new_owner), holding(owner));
+        let forgotten = Pubkey::new_unique();
+
+        let mut owners = PositionOwners::default();
+        let map = owners.owners.entry("vehnt".to_string()).or_default();
+        for ((position, _), holding) in positions.iter().zip(holdings) {
+            map.insert(*position, holding);
+        }
+        map.insert(forgotten, holding(owner));
+
+        let fixture = rpc::FixtureTransport::new()
+            .with_account(
+                holdings[0].token_account,
+                &token_account(unchanged.1, owner, 1),
+            )
+            // the largest account would name another owner, so it must not
+            // be looked up for an NFT that did not move
+            .with_token_account(unchanged.1, Pubkey::new_unique(), Pubkey::new_unique())
+            .with_account(
+                holdings[1].token_account,
+                &token_account(transferred.1, owner, 0),
+            )
+            .with_token_account(transferred.1, moved_to.token_account, new_owner)
+            // moved to another token account of the same owner
+            .with_token_account(closed.1, reopened.token_account, owner);
+        let rpc_client = rpc::Client::new_with_transport(fixture);
+        let transfers = owners.reconcile(&rpc_client, &positions).await.unwrap();
+        assert_eq!(transfers, 1);
+
+        let map = &owners.owners["vehnt"];
+        assert_eq!(map.len(), 4);
+        assert_eq!(map[&unchanged.0], holdings[0]);
+        assert_eq!(map[&transferred.0], moved_to);
+        assert_eq!(map[&closed.0], reopened);
+        // left for the next reconciliation
+        assert_eq!(map[&unresolved.0], holdings[3]);
+        assert!(!owners.reconciliation_due());
+    }
+}
@@ -0,0 +1,273 @@
+use super::*;
+use anchor_lang::{
+    solana_program::pubkey::Pubkey, AccountDeserialize, AnchorDeserialize, Discriminator,
+};
+use helium_anchor_gen::helium_sub_daos::{DaoV0, SubDaoV0};
+use spl_token_2022::{extension::StateWithExtensions, state::Mint};
+
+const TOKEN_METADATA_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+/// The Helium DAO or one of its subDAOs, whose token is locked in positions
+/// of the voter stake registry
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Dao {
+    /// The lowercase symbol of the token, such as `hnt` or `iot`
+    pub symbol: String,
+    /// The `DaoV0` or `SubDaoV0` account
+    pub key: Pubkey,
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub registrar: Pubkey,
+    pub is_sub_dao: bool,
+}
+
+impl Dao {
+    /// The name of the voting power of the DAO's positions, such as `vehnt`,
+    /// under which they are served
+    pub fn ve_name(&self) -> String {
+        format!("ve{}", self.symbol)
+    }
+}
+
+/// The Helium DAO followed by its subDAOs, in the order of their symbols
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Daos(Vec<Dao>);
+
+impl Daos {
+    /// Reads the DAO whose token is HNT and its subDAOs from the
+    /// helium_sub_daos program. Each is named after the symbol in the
+    /// metadata of its token; subDAOs whose token has no metadata are
+    /// skipped.
+    pub async fn discover(rpc_client: &rpc::Client) -> Result<Self> {
+        let helium_dao_id = Pubkey::from_str(HELIUM_DAO_ID)?;
+        let hnt_mint = Pubkey::from_str(HNT_MINT)?;
+        let (dao_key, dao) = rpc_client
+            .get_program_accounts_decoded(
+                &helium_dao_id,
+                vec![rpc::GetProgramAccountsFilter::discriminator(
+                    &DaoV0::DISCRIMINATOR,
+                )],
+                None,
+                |pubkey, account| {
+                    Ok(DaoV0::try_deserialize(&mut account.data.as_slice())
+                        .ok()
+                        .filter(|dao| dao.hnt_mint == hnt_mint)
+                        .map(|dao| (pubkey, dao)))
+                },
+            )
+            .await?
+            .value
+            .pop()
+            .ok_or(Error::Custom("Helium DAO not found"))?;
+        let sub_daos = rpc_client
+            .get_program_accounts_decoded(
+                &helium_dao_id,
+                vec![rpc::GetProgramAccountsFilter::discriminator(
+                    &SubDaoV0::DISCRIMINATOR,
+                )],
+                None,
+                |pubkey, account| {
+                    Ok(SubDaoV0::try_deserialize(&mut account.data.as_slice())
+                        .ok()
+                        .filter(|sub_dao| sub_dao.dao == dao_key)
+                        .map(|sub_dao| (pubkey, sub_dao)))
+                },
+            )
+            .await?
+            .value;
+
+        let mut daos = vec![(dao_key, dao.hnt_mint, dao.registrar, false)];
+        daos.extend(
+            sub_daos
+                .into_iter()
+                .map(|(key, sub_dao)| (key, sub_dao.dnt_mint, sub_dao.registrar, true)),
+        );
+        let tokens = token_symbols_and_decimals(
+            rpc_client,
+            &daos
+                .iter()
+                .map(|(_, mint, ..)| mint)
+                .collect::<Vec<&Pubkey>>(),
+        )
+        .await?;
+        let mut daos: Vec<Dao> = daos
+            .into_iter()
+            .zip(tokens)
+            .filter_map(|((key, mint, registrar, is_sub_dao), token)| match token {
+                Some((symbol, decimals)) => Some(Dao {
+                    symbol,
+                    key,
+                    mint,
+                    decimals,
+                    registrar,
+                    is_sub_dao,
+                }),
+                None => {
+                    println!("Warning: no token metadata for the mint {mint} of DAO {key}");
+                    None
+                }
+            })
+            .collect();
+        if !matches!(daos.first(), Some(dao) if !dao.is_sub_dao) {
+            return Err(Error::Custom("Helium DAO token has no metadata"));
+        }
+        Ok(Self::new(daos))
+    }
+
+    /// `daos` must start with the Helium DAO, its subDAOs are sorted
+    pub fn new(mut daos: Vec<Dao>) -> Self {
+        if let Some(sub_daos) = daos.get_mut(1..) {
+            sub_daos.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        }
+        Self(daos)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Dao> {
+        self.0.iter()
+    }
+
+    /// The Helium DAO, whose positions are the ones delegated to subDAOs
+    pub fn dao(&self) -> Option<&Dao> {
+        self.0.first()
+    }
+
+    pub fn sub_daos(&self) -> impl Iterator<Item = &Dao> {
+        self.0.iter().skip(1)
+    }
+
+    /// By the name of the voting power, such as `veiot`, or else by the
+    /// symbol, so that a symbol starting with `ve` is never mistaken for
+    /// another DAO's voting power
+    pub fn by_name(&self, name: &str) -> Option<&Dao> {
+        let name = name.to_lowercase();
+        self.0
+            .iter()
+            .find(|dao| dao.ve_name() == name)
+            .or_else(|| self.0.iter().find(|dao| dao.symbol == name))
+    }
+
+    pub fn by_mint(&self, mint: &Pubkey) -> Option<&Dao> {
+        self.0.iter().find(|dao| dao.mint == *mint)
+    }
+
+    /// By its `DaoV0` or `SubDaoV0` account, such as the subDAO a position
+    /// is delegated to
+    pub fn by_key(&self, key: &Pubkey) -> Option<&Dao> {
+        self.0.iter().find(|dao| dao.key == *key)
+    }
+
+    /// The symbol of the DAO of a `DaoV0` or `SubDaoV0` account, or the
+    /// account itself if that DAO was not discovered
+    pub fn name_of(&self, key: &Pubkey) -> String {
+        self.by_key(key)
+            .map_or_else(|| key.to_string(), |dao| dao.symbol.clone())
+    }
+}
+
+/// The symbols of tokens from their Metaplex metadata, lowercased, along with
+/// their decimals. Neither changes in practice, so both are read once.
+async fn token_symbols_and_decimals(
+    rpc_client: &rpc::Client,
+    mints: &[&Pubkey],
+) -> Result<Vec<Option<(String, u8)>>> {
+    let metadata_id = Pubkey::from_str(TOKEN_METADATA_ID)?;
+    let metadata_keys: Vec<Pubkey> = mints
+        .iter()
+        .map(|mint| {
+            Pubkey::find_program_address(
+                &[b"metadata", metadata_id.as_ref(), mint.as_ref()],
+                &metadata_id,
+            )
+            .0
+        })
+        .collect();
+    let mut keys: Vec<&Pubkey> = mints.to_vec();
+    keys.extend(metadata_keys.iter());
+    let mut accounts = rpc_client
+        .get_multiple_accounts_cached(&keys, Some(rpc::CachePolicy::Immutable))
+        .await?
+        .value;
+    let metadata = accounts.split_off(mints.len());
+    Ok(accounts
+        .into_iter()
+        .zip(metadata)
+        .map(|(mint, metadata)| {
+            let decimals = StateWithExtensions::<Mint>::unpack(&mint?.data)
+                .ok()?
+                .base
+                .decimals;
+            Some((metadata_symbol(&metadata?.data)?, decimals))
+        })
+        .collect())
+}
+
+/// Metadata accounts start with a key byte, the update authority and the
+/// mint, followed by the name and the symbol as strings padded with zeros
+fn metadata_symbol(data: &[u8]) -> Option<String> {
+    let mut data = data.get(1 + 32 + 32..)?;
+    let _name = <String as AnchorDeserialize>::deserialize(&mut data).ok()?;
+    let symbol = <String as AnchorDeserialize>::deserialize(&mut data).ok()?;
+    let symbol = symbol.trim_end_matches('\0').trim().to_lowercase();
+    (!symbol.is_empty()).then_some(symbol)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dao(symbol: &str, is_sub_dao: bool) -> Dao {
+        Dao {
+            symbol: symbol.to_string(),
+            key: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            decimals: 6,
+            registrar: Pubkey::new_unique(),
+            is_sub_dao,
+        }
+    }
+
+    #[test]
+    fn test_metadata_symbol() {
+        let mut data = vec![4];
+        data.extend([0; 64]);
+        for (string, padded_len) in [("Helium Network Token", 32), ("HNT", 10)] {
+            let mut padded = string.as_bytes().to_vec();
+            padded.resize(padded_len, 0);
+            data.extend((padded_len as u32).to_le_bytes());
+            data.extend(padded);
+        }
+        assert_eq!(metadata_symbol(&data), Some("hnt".to_string()));
+        assert_eq!(metadata_symbol(&data[..70]), None);
+    }
+
+    #[test]
+    fn test_by_name() {
+        let daos = Daos(vec![
+            dao("hnt", false),
+            dao("iot", true),
+            dao("mobile", true),
+        ]);
+        assert_eq!(daos.by_name("vehnt").unwrap().symbol, "hnt");
+        assert_eq!(daos.by_name("IOT").unwrap().symbol, "iot");
+        assert_eq!(daos.by_name("vemobile").unwrap().ve_name(), "vemobile");
+        assert!(daos.by_name("vedc").is_none());
+        assert_eq!(daos.dao().unwrap().symbol, "hnt");
+        assert_eq!(daos.sub_daos().count(), 2);
+        let mint = daos.iter().nth(1).unwrap().mint;
+        assert_eq!(daos.by_mint(&mint).unwrap().symbol, "iot");
+        let key = daos.iter().nth(2).unwrap().key;
+        assert_eq!(daos.by_key(&key).unwrap().symbol, "mobile");
+        assert!(daos.by_key(&mint).is_none());
+        assert_eq!(daos.name_of(&key), "mobile");
+        assert_eq!(daos.name_of(&mint), mint.to_string());
+    }
+
+    #[test]
+    fn test_by_name_prefers_voting_power() {
+        let daos = Daos(vec![dao("hnt", false), dao("vex", true), dao("x", true)]);
+        assert_eq!(daos.by_name("vex").unwrap().symbol, "x");
+        assert_eq!(daos.by_name("vevex").unwrap().symbol, "vex");
+        assert_eq!(daos.by_name("x").unwrap().symbol, "x");
+        assert!(daos.by_name("vevevex").is_none());
+    }
+}
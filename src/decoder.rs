@@ -0,0 +1,433 @@
+use super::*;
+use crate::cli::positions::LockupType;
+use anchor_lang::{solana_program::pubkey::Pubkey, AnchorDeserialize, Discriminator};
+use helium_anchor_gen::{
+    helium_sub_daos::instruction as dao, voter_stake_registry::instruction as vsr,
+};
+use rpc::{ParsedInstruction, Transaction};
+use serde::Serialize;
+
+/// The accounts of each instruction, in the order of the programs' IDLs.
+/// Accounts past the end of the list are named `remaining`.
+mod account_names {
+    pub const INITIALIZE_POSITION: &[&str] = &[
+        "payer",
+        "collection",
+        "collection_metadata",
+        "collection_master_edition",
+        "registrar",
+        "mint",
+        "metadata",
+        "master_edition",
+        "position",
+        "position_token_account",
+        "recipient",
+        "vault",
+        "deposit_mint",
+        "system_program",
+        "token_program",
+        "associated_token_program",
+        "token_metadata_program",
+        "rent",
+    ];
+    pub const DEPOSIT: &[&str] = &[
+        "registrar",
+        "position",
+        "vault",
+        "mint",
+        "deposit_token",
+        "deposit_authority",
+        "token_program",
+        "associated_token_program",
+        "system_program",
+    ];
+    pub const WITHDRAW: &[&str] = &[
+        "registrar",
+        "position",
+        "mint",
+        "position_token_account",
+        "position_authority",
+        "vault",
+        "deposit_mint",
+        "destination",
+        "token_program",
+        "associated_token_program",
+        "system_program",
+    ];
+    pub const CLOSE_POSITION: &[&str] = &[
+        "sol_destination",
+        "position",
+        "registrar",
+        "mint",
+        "position_token_account",
+        "position_authority",
+        "token_program",
+    ];
+    pub const RESET_LOCKUP: &[&str] = &[
+        "registrar",
+        "position_authority",
+        "position",
+        "mint",
+        "position_token_account",
+    ];
+    pub const TRANSFER: &[&str] = &[
+        "registrar",
+        "position_authority",
+        "source_position",
+        "mint",
+        "position_token_account",
+        "target_position",
+        "deposit_mint",
+        "source_vault",
+        "target_vault",
+        "token_program",
+        "associated_token_program",
+        "system_program",
+    ];
+    pub const DELEGATE: &[&str] = &[
+        "payer",
+        "position",
+        "mint",
+        "position_token_account",
+        "position_authority",
+        "registrar",
+        "dao",
+        "sub_dao",
+        "sub_dao_epoch_info",
+        "closing_time_sub_dao_epoch_info",
+        "genesis_end_sub_dao_epoch_info",
+        "delegated_position",
+        "vsr_program",
+        "system_program",
+    ];
+    pub const CLOSE_DELEGATION: &[&str] = &[
+        "payer",
+        "position",
+        "mint",
+        "position_token_account",
+        "position_authority",
+        "registrar",
+        "dao",
+        "sub_dao",
+        "delegated_position",
+        "sub_dao_epoch_info",
+        "closing_time_sub_dao_epoch_info",
+        "genesis_end_sub_dao_epoch_info",
+        "vsr_program",
+        "system_program",
+    ];
+    pub const CLAIM_REWARDS: &[&str] = &[
+        "position",
+        "mint",
+        "position_token_account",
+        "position_authority",
+        "registrar",
+        "dao",
+        "sub_dao",
+        "delegated_position",
+        "dnt_mint",
+        "sub_dao_epoch_info",
+        "delegator_pool",
+        "delegator_ata",
+        "delegator_pool_circuit_breaker",
+        "vsr_program",
+        "system_program",
+        "circuit_breaker_program",
+        "associated_token_program",
+        "token_program",
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeliumProgram {
+    HeliumSubDaos,
+    VoterStakeRegistry,
+}
+
+/// What a decoded instruction does, with its arguments
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    CreatePosition {
+        lockup_type: LockupType,
+        periods: u32,
+    },
+    Deposit {
+        amount: u64,
+    },
+    Withdraw {
+        amount: u64,
+    },
+    ClosePosition,
+    /// Extends or changes the lockup of a position
+    ResetLockup {
+        lockup_type: LockupType,
+        periods: u32,
+    },
+    /// Moves tokens from one position to another
+    Transfer {
+        amount: u64,
+    },
+    Delegate,
+    Undelegate,
+    ClaimRewards {
+        epoch: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedInstruction {
+    pub program: HeliumProgram,
+    #[serde(flatten)]
+    pub action: Action,
+    /// The instruction's accounts, named by their role
+    pub accounts: Vec<(&'static str, String)>,
+    /// The index of the top-level instruction, or of the one that invoked
+    /// this instruction when `inner`
+    pub index: usize,
+    pub inner: bool,
+}
+
+impl DecodedInstruction {
+    /// The first account with the given role
+    pub fn account(&self, name: &str) -> Option<Pubkey> {
+        self.accounts
+            .iter()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, key)| Pubkey::from_str(key).ok())
+    }
+}
+
+/// Decodes every instruction of a transaction, including the ones invoked
+/// by other programs, that creates, changes or delegates a position or
+/// claims its rewards. Other instructions are skipped. Only transactions
+/// fetched with `TransactionEncoding::JsonParsed` can be decoded.
+pub fn decode_transaction(transaction: &Transaction) -> Result<Vec<DecodedInstruction>> {
+    let rpc::EncodedTransaction::Json(parsed) = &transaction.transaction else {
+        return Err(Error::Custom("only jsonParsed transactions can be decoded"));
+    };
+    let inner = transaction
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.inner_instructions.as_ref());
+    let mut decoded = Vec::new();
+    for (index, instruction) in parsed.message.instructions.iter().enumerate() {
+        if let Some(mut instruction) = decode_instruction(instruction)? {
+            instruction.index = index;
+            decoded.push(instruction);
+        }
+        let invoked = inner
+            .into_iter()
+            .flatten()
+            .filter(|inner| inner.index as usize == index)
+            .flat_map(|inner| inner.instructions.iter());
+        for instruction in invoked {
+            // instructions compiled to account indexes are not decoded
+            let Ok(instruction) = serde_json::from_value::<ParsedInstruction>(instruction.clone())
+            else {
+                continue;
+            };
+            if let Some(mut instruction) = decode_instruction(&instruction)? {
+                instruction.index = index;
+                instruction.inner = true;
+                decoded.push(instruction);
+            }
+        }
+    }
+    Ok(decoded)
+}
+
+/// Decodes an instruction to `helium_sub_daos` or `voter_stake_registry`,
+/// returning `None` for other programs and instructions of theirs that are
+/// not decoded. The index is left for `decode_transaction` to set.
+pub fn decode_instruction(instruction: &ParsedInstruction) -> Result<Option<DecodedInstruction>> {
+    let ParsedInstruction::Raw {
+        program_id,
+        accounts,
+        data,
+    } = instruction
+    else {
+        return Ok(None);
+    };
+    let program = if *program_id == Pubkey::from_str(HELIUM_DAO_ID)? {
+        HeliumProgram::HeliumSubDaos
+    } else if *program_id == Pubkey::from_str(HELIUM_VSR_ID)? {
+        HeliumProgram::VoterStakeRegistry
+    } else {
+        return Ok(None);
+    };
+    let data = bs58::decode(data)
+        .into_vec()
+        .map_err(|_| Error::Custom("instruction data is not base58"))?;
+    if data.len() < 8 {
+        return Ok(None);
+    }
+    let (discriminator, mut args) = data.split_at(8);
+    let decoded = match program {
+        HeliumProgram::VoterStakeRegistry => decode_vsr(discriminator, &mut args)?,
+        HeliumProgram::HeliumSubDaos => decode_dao(discriminator, &mut args)?,
+    };
+    Ok(decoded.map(|(action, names)| DecodedInstruction {
+        program,
+        action,
+        accounts: accounts
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (names.get(i).copied().unwrap_or("remaining"), key.clone()))
+            .collect(),
+        index: 0,
+        inner: false,
+    }))
+}
+
+fn is<T: Discriminator>(discriminator: &[u8]) -> bool {
+    discriminator == T::DISCRIMINATOR
+}
+
+fn decode_vsr(
+    discriminator: &[u8],
+    args: &mut &[u8],
+) -> Result<Option<(Action, &'static [&'static str])>> {
+    Ok(Some(if is::<vsr::InitializePositionV0>(discriminator) {
+        let args = vsr::InitializePositionV0::deserialize(args)?.args;
+        (
+            Action::CreatePosition {
+                lockup_type: args.kind.into(),
+                periods: args.periods,
+            },
+            account_names::INITIALIZE_POSITION,
+        )
+    } else if is::<vsr::DepositV0>(discriminator) {
+        let args = vsr::DepositV0::deserialize(args)?.args;
+        (
+            Action::Deposit {
+                amount: args.amount,
+            },
+            account_names::DEPOSIT,
+        )
+    } else if is::<vsr::WithdrawV0>(discriminator) {
+        let args = vsr::WithdrawV0::deserialize(args)?.args;
+        (
+            Action::Withdraw {
+                amount: args.amount,
+            },
+            account_names::WITHDRAW,
+        )
+    } else if is::<vsr::ClosePositionV0>(discriminator) {
+        (Action::ClosePosition, account_names::CLOSE_POSITION)
+    } else if is::<vsr::ResetLockupV0>(discriminator) {
+        let args = vsr::ResetLockupV0::deserialize(args)?.args;
+        (
+            Action::ResetLockup {
+                lockup_type: args.kind.into(),
+                periods: args.periods,
+            },
+            account_names::RESET_LOCKUP,
+        )
+    } else if is::<vsr::TransferV0>(discriminator) {
+        let args = vsr::TransferV0::deserialize(args)?.args;
+        (
+            Action::Transfer {
+                amount: args.amount,
+            },
+            account_names::TRANSFER,
+        )
+    } else {
+        return Ok(None);
+    }))
+}
+
+fn decode_dao(
+    discriminator: &[u8],
+    args: &mut &[u8],
+) -> Result<Option<(Action, &'static [&'static str])>> {
+    Ok(Some(if is::<dao::DelegateV0>(discriminator) {
+        (Action::Delegate, account_names::DELEGATE)
+    } else if is::<dao::CloseDelegationV0>(discriminator) {
+        (Action::Undelegate, account_names::CLOSE_DELEGATION)
+    } else if is::<dao::ClaimRewardsV0>(discriminator) {
+        let args = dao::ClaimRewardsV0::deserialize(args)?.args;
+        (
+            Action::ClaimRewards { epoch: args.epoch },
+            account_names::CLAIM_REWARDS,
+        )
+    } else {
+        return Ok(None);
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anchor_lang::InstructionData;
+    use helium_anchor_gen::voter_stake_registry::{DepositArgsV0, LockupKind, ResetLockupArgsV0};
+
+    fn raw(program_id: &str, data: Vec<u8>, accounts: usize) -> ParsedInstruction {
+        ParsedInstruction::Raw {
+            program_id: Pubkey::from_str(program_id).unwrap(),
+            accounts: (0..accounts)
+                .map(|_| Pubkey::new_unique().to_string())
+                .collect(),
+            data: bs58::encode(data).into_string(),
+        }
+    }
+
+    #[test]
+    fn test_decode_vsr_instructions() {
+        let deposit = raw(
+            HELIUM_VSR_ID,
+            vsr::DepositV0 {
+                args: DepositArgsV0 { amount: 42 },
+            }
+            .data(),
+            account_names::DEPOSIT.len(),
+        );
+        let decoded = decode_instruction(&deposit).unwrap().unwrap();
+        assert_eq!(decoded.program, HeliumProgram::VoterStakeRegistry);
+        assert_eq!(decoded.action, Action::Deposit { amount: 42 });
+        let ParsedInstruction::Raw { accounts, .. } = &deposit else {
+            unreachable!()
+        };
+        assert_eq!(
+            decoded.account("position").unwrap().to_string(),
+            accounts[1]
+        );
+
+        let reset = raw(
+            HELIUM_VSR_ID,
+            vsr::ResetLockupV0 {
+                args: ResetLockupArgsV0 {
+                    kind: LockupKind::Cliff,
+                    periods: 365,
+                },
+            }
+            .data(),
+            account_names::RESET_LOCKUP.len() + 1,
+        );
+        let decoded = decode_instruction(&reset).unwrap().unwrap();
+        assert_eq!(
+            decoded.action,
+            Action::ResetLockup {
+                lockup_type: LockupType::Cliff,
+                periods: 365
+            }
+        );
+        assert_eq!(decoded.accounts.last().unwrap().0, "remaining");
+    }
+
+    #[test]
+    fn test_decode_skips_other_programs() {
+        let delegate = dao::DelegateV0 {}.data();
+        assert!(decode_instruction(&raw(HNT_MINT, delegate.clone(), 1))
+            .unwrap()
+            .is_none());
+        let decoded = decode_instruction(&raw(HELIUM_DAO_ID, delegate, 2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.action, Action::Delegate);
+        // the claim discriminator with missing args
+        let claim = dao::ClaimRewardsV0::DISCRIMINATOR.to_vec();
+        assert!(decode_instruction(&raw(HELIUM_DAO_ID, claim, 0)).is_err());
+    }
+}
@@ -12,8 +12,6 @@ pub enum Error {
     AnchorLang(Box<anchor_lang::error::Error>),
     #[error("base64 decode error: {0}")]
     Base64Decode(#[from] base64::DecodeError),
-    #[error("invalid subdao: {0}")]
-    InvalidSubDao(Pubkey),
     #[error("reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
     #[error("parse int error: {0}")]
@@ -37,7 +35,7 @@ pub enum Error {
     #[error("Expected to find position {position} but none found!")]
     MissingPosition { position: Pubkey },
     #[error("No registrar for mint {0}")]
-    NoRegistrarForMint(&'static str),
+    NoRegistrarForMint(Pubkey),
     #[error("SolanaProgramError: {0}")]
     SolanaProgram(#[from] anchor_lang::prelude::ProgramError),
 }
@@ -2,6 +2,8 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use std::str::FromStr;
 
 pub mod cli;
+pub mod daos;
+pub mod decoder;
 pub mod error;
 pub mod rpc;
 pub mod server;
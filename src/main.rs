@@ -4,6 +4,6 @@ use hnt_explorer::*;
 async fn main() -> Result {
     use clap::Parser;
     let cli = cli::Cli::parse();
-    let rpc_client = rpc::Client::default();
+    let rpc_client = cli.rpc_client()?;
     cli.run(rpc_client).await
 }
@@ -0,0 +1,272 @@
+use super::*;
+use std::path::PathBuf;
+
+pub const ENDPOINT_ENV: &str = "SOL_RPC_ENDPOINT";
+pub const ENDPOINT_WEIGHTS_ENV: &str = "SOL_RPC_ENDPOINT_WEIGHTS";
+pub const DEFAULT_ENDPOINT: &str = "https://api.mainnet-beta.solana.com";
+
+/// The default timeout for establishing a connection to a node
+pub const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+
+/// How long to wait for the response to a call, by the class of its method.
+/// A program scan returns far more data than an account fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub account: Duration,
+    pub scan: Duration,
+    pub das: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            account: Duration::from_secs(30),
+            scan: Duration::from_secs(DEFAULT_TIMEOUT),
+            das: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn get(&self, class: MethodClass) -> Duration {
+        match class {
+            MethodClass::Account => self.account,
+            MethodClass::Scan => self.scan,
+            MethodClass::Das => self.das,
+        }
+    }
+
+    fn max(&self) -> Duration {
+        self.account.max(self.scan).max(self.das)
+    }
+}
+
+/// Builds a `Client` talking to one or more HTTP endpoints, with the headers,
+/// timeouts, proxy and retry limits a provider requires.
+#[derive(Debug)]
+pub struct ClientBuilder {
+    endpoints: Vec<(String, u32)>,
+    headers: Vec<(String, String)>,
+    user_agent: Option<String>,
+    connect_timeout: Duration,
+    timeouts: Timeouts,
+    proxy: Option<String>,
+    retry: RetryPolicy,
+    limiter: Option<RateLimiter>,
+    cassette: Option<(PathBuf, CassetteMode)>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            endpoints: vec![(DEFAULT_ENDPOINT.to_string(), 1)],
+            headers: Vec::new(),
+            user_agent: None,
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
+            timeouts: Timeouts::default(),
+            proxy: None,
+            retry: RetryPolicy::default(),
+            limiter: None,
+            cassette: None,
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the endpoints, rate limits and cassette settings from
+    /// `SOL_RPC_ENDPOINT`, `SOL_RPC_ENDPOINT_WEIGHTS`, `SOL_RPC_RATE_LIMIT`,
+    /// `SOL_RPC_MAX_IN_FLIGHT`, `SOL_RPC_CASSETTE` and
+    /// `SOL_RPC_CASSETTE_MODE`
+    pub fn from_env() -> Result<Self> {
+        let mut builder = Self::new().rate_limiter(RateLimiter::from_env()?);
+        if let Ok(urls) = std::env::var(ENDPOINT_ENV) {
+            let weights = std::env::var(ENDPOINT_WEIGHTS_ENV).ok();
+            builder = builder.endpoints_from_urls(&urls, weights.as_deref())?;
+        }
+        if let Ok(dir) = std::env::var(CASSETTE_ENV) {
+            let mode = match std::env::var(CASSETTE_MODE_ENV) {
+                Ok(mode) => CassetteMode::from_str(&mode)?,
+                Err(_) => CassetteMode::Record,
+            };
+            builder = builder.cassette(dir.into(), mode);
+        }
+        Ok(builder)
+    }
+
+    /// Replaces the endpoints with a single URL
+    pub fn endpoint(mut self, url: String) -> Self {
+        self.endpoints = vec![(url, 1)];
+        self
+    }
+
+    /// Replaces the endpoints with URLs and their round-robin weights
+    pub fn endpoints(mut self, endpoints: Vec<(String, u32)>) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Replaces the endpoints with a comma separated list of URLs and an
+    /// optional comma separated list of weights in the same order
+    pub fn endpoints_from_urls(mut self, urls: &str, weights: Option<&str>) -> Result<Self> {
+        self.endpoints = endpoints::parse_urls(urls, weights)?;
+        Ok(self)
+    }
+
+    /// Adds a header sent with every request, such as an API key
+    pub fn header(mut self, name: String, value: String) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: String) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Sends every request through an HTTP(S) proxy
+    pub fn proxy(mut self, url: String) -> Self {
+        self.proxy = Some(url);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.retry.max_retries = max_retries;
+        self
+    }
+
+    pub fn rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Records traffic to or replays it from `dir`, see
+    /// `Client::new_with_cassette`
+    pub fn cassette(mut self, dir: PathBuf, mode: CassetteMode) -> Self {
+        self.cassette = Some((dir, mode));
+        self
+    }
+
+    fn http_client(&self) -> Result<reqwest::Client> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                reqwest::header::HeaderName::from_str(name)
+                    .map_err(|e| Error::Config(format!("header name {name}: {e}")))?,
+                reqwest::header::HeaderValue::from_str(value)
+                    .map_err(|e| Error::Config(format!("header value for {name}: {e}")))?,
+            );
+        }
+        let mut builder = reqwest::Client::builder()
+            .gzip(true)
+            .default_headers(headers)
+            .connect_timeout(self.connect_timeout)
+            // the client enforces the timeout of each call's class
+            .timeout(self.timeouts.max());
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        Ok(builder.build()?)
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let http = self.http_client()?;
+        let endpoints = EndpointPool::new(
+            self.endpoints
+                .iter()
+                .map(|(url, weight)| Endpoint::http_with_client(url.clone(), *weight, http.clone()))
+                .collect(),
+        )?;
+        let client = match self.cassette {
+            None => Client::new_with_transport(endpoints),
+            Some((dir, mode)) => Client::new_with_cassette(endpoints, dir, mode)?,
+        };
+        let client = client
+            .with_retry_policy(self.retry)
+            .with_timeouts(self.timeouts);
+        Ok(match self.limiter {
+            Some(limiter) => client.with_rate_limiter(limiter),
+            None => client,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::test;
+
+    #[derive(Debug)]
+    struct SlowTransport(Duration);
+
+    #[async_trait::async_trait]
+    impl Transport for SlowTransport {
+        async fn send(&self, body: String) -> Result<String> {
+            tokio::time::sleep(self.0).await;
+            FixtureTransport::new().send(body).await
+        }
+    }
+
+    #[test]
+    async fn test_timeouts_by_method_class() {
+        let timeouts = Timeouts {
+            account: Duration::from_millis(10),
+            scan: Duration::from_secs(5),
+            das: Duration::from_secs(5),
+        };
+        let client = Client::new_with_transport(SlowTransport(Duration::from_millis(50)))
+            .with_timeouts(timeouts)
+            .with_retry_policy(RetryPolicy::none());
+        let err = client.get_account(&Pubkey::new_unique()).await.unwrap_err();
+        assert!(matches!(err, Error::Timeout(_)));
+        assert!(err.is_retriable());
+        let accounts = client
+            .get_program_accounts_with_filter(&Pubkey::new_unique(), vec![], None)
+            .await
+            .unwrap();
+        assert!(accounts.is_empty());
+    }
+
+    #[test]
+    async fn test_client_builder() {
+        let client = ClientBuilder::new()
+            .endpoint("http://127.0.0.1:1".to_string())
+            .header("x-api-key".to_string(), "secret".to_string())
+            .user_agent("hnt-explorer".to_string())
+            .max_retries(1)
+            .build()
+            .unwrap();
+        assert_eq!(client.retry_policy().max_retries, 1);
+        assert_eq!(client.timeouts(), &Timeouts::default());
+
+        let err = ClientBuilder::new()
+            .header("bad header".to_string(), "value".to_string())
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+        assert!(ClientBuilder::new()
+            .endpoints_from_urls("http://a, http://b", Some("1"))
+            .is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use super::*;
+use std::{collections::HashMap, sync::Mutex, time::Instant};
+
+/// How long an account stays in an `AccountCache`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// The account never changes, such as a registrar or the epoch info of
+    /// a closed epoch, and is kept for the life of the client
+    Immutable,
+    /// The account changes slowly and may be served for this long
+    Ttl(Duration),
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    account: Account,
+    slot: u64,
+    expires_at: Option<Instant>,
+}
+
+/// Accounts that are served without a request to a node, shared by a
+/// `Client` and its clones so that refresh loops re-use them
+#[derive(Debug, Default)]
+pub struct AccountCache {
+    entries: Mutex<HashMap<Pubkey, CacheEntry>>,
+}
+
+impl AccountCache {
+    /// The cached account along with the slot it was read at, unless it
+    /// has expired
+    pub fn get(&self, pubkey: &Pubkey) -> Option<WithContext<Account>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(pubkey) {
+            Some(entry) if entry.expires_at.is_none_or(|at| at > Instant::now()) => {
+                Some(WithContext {
+                    slots: SlotRange::new(entry.slot),
+                    value: entry.account.clone(),
+                })
+            }
+            Some(_) => {
+                entries.remove(pubkey);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, pubkey: Pubkey, account: WithContext<Account>, policy: CachePolicy) {
+        let expires_at = match policy {
+            CachePolicy::Immutable => None,
+            CachePolicy::Ttl(ttl) => Some(Instant::now() + ttl),
+        };
+        self.entries.lock().unwrap().insert(
+            pubkey,
+            CacheEntry {
+                account: account.value,
+                slot: account.slots.max,
+                expires_at,
+            },
+        );
+    }
+
+    pub fn invalidate(&self, pubkey: &Pubkey) {
+        self.entries.lock().unwrap().remove(pubkey);
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Client {
+    /// Like `get_multiple_accounts_with_context`, serving accounts from the
+    /// client's cache when present and fetching only the others. Fetched
+    /// accounts are cached under `policy`, if any. The slots cover the
+    /// fetched accounts only, since cached accounts are expected not to have
+    /// changed since they were read.
+    pub async fn get_multiple_accounts_cached(
+        &self,
+        pubkeys: &[&Pubkey],
+        policy: Option<CachePolicy>,
+    ) -> Result<WithContext<Vec<Option<Account>>>> {
+        let mut accounts: Vec<Option<Account>> = pubkeys
+            .iter()
+            .map(|pubkey| self.account_cache().get(pubkey).map(|a| a.value))
+            .collect();
+        let missing: Vec<usize> = (0..pubkeys.len())
+            .filter(|i| accounts[*i].is_none())
+            .collect();
+        let fetched = self
+            .get_multiple_accounts_with_context(
+                &missing.iter().map(|i| pubkeys[*i]).collect::<Vec<_>>(),
+            )
+            .await?;
+        for (i, account) in missing.into_iter().zip(fetched.value) {
+            if let (Some(policy), Some(account)) = (policy, &account) {
+                let account = WithContext {
+                    slots: fetched.slots,
+                    value: account.clone(),
+                };
+                self.account_cache().insert(*pubkeys[i], account, policy);
+            }
+            accounts[i] = account;
+        }
+        Ok(WithContext {
+            slots: fetched.slots,
+            value: accounts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::test_support::fixture_account;
+    use tokio::test;
+
+    #[test]
+    async fn test_fixture_account_cache() {
+        let program_id = Pubkey::new_unique();
+        let immutable = Pubkey::new_unique();
+        let expiring = Pubkey::new_unique();
+        let missing = Pubkey::new_unique();
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_slot(7)
+                .with_account(immutable, &fixture_account(program_id, vec![1]))
+                .with_account(expiring, &fixture_account(program_id, vec![2])),
+        );
+
+        let accounts = client
+            .get_multiple_accounts_cached(&[&immutable, &missing], Some(CachePolicy::Immutable))
+            .await
+            .unwrap();
+        assert_eq!(accounts.slots, SlotRange::new(7));
+        assert_eq!(accounts.value[0].as_ref().unwrap().data, vec![1]);
+        assert!(accounts.value[1].is_none());
+        assert_eq!(client.account_cache().len(), 1);
+
+        client
+            .get_multiple_accounts_cached(&[&expiring], Some(CachePolicy::Ttl(Duration::ZERO)))
+            .await
+            .unwrap();
+        assert!(client.account_cache().get(&expiring).is_none());
+
+        // clones share the cache, so only the uncached account is fetched
+        let calls = |client: &Client| -> u64 {
+            client
+                .metrics()
+                .snapshot()
+                .iter()
+                .map(|m| m.successes)
+                .sum()
+        };
+        let before = calls(&client);
+        let accounts = client
+            .clone()
+            .get_multiple_accounts_cached(&[&immutable, &expiring], None)
+            .await
+            .unwrap();
+        assert_eq!(calls(&client), before + 1);
+        assert_eq!(accounts.value[0].as_ref().unwrap().data, vec![1]);
+        assert_eq!(accounts.value[1].as_ref().unwrap().data, vec![2]);
+        assert_eq!(
+            client.account_cache().get(&immutable).unwrap().slots,
+            SlotRange::new(7)
+        );
+    }
+}
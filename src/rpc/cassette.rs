@@ -0,0 +1,202 @@
+use super::*;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// Env var naming the directory that RPC traffic is recorded to or replayed from
+pub const CASSETTE_ENV: &str = "SOL_RPC_CASSETTE";
+/// Env var selecting `record` (default) or `replay` for `CASSETTE_ENV`
+pub const CASSETTE_MODE_ENV: &str = "SOL_RPC_CASSETTE_MODE";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+impl FromStr for CassetteMode {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "record" => Ok(Self::Record),
+            "replay" => Ok(Self::Replay),
+            _ => Err(Error::Cassette(format!("unknown cassette mode \"{s}\""))),
+        }
+    }
+}
+
+/// A single request/response pair as stored on disk
+#[derive(Debug, Serialize, Deserialize)]
+struct Interaction {
+    request: Value,
+    response: Value,
+}
+
+/// Transport that passes every request through to `inner` and writes the
+/// request/response pair to its own file in `dir`. Files are numbered so that
+/// replay serves repeated identical requests in their original order.
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    dir: PathBuf,
+    next: AtomicU64,
+}
+
+impl RecordingTransport {
+    pub fn new<T: Transport + 'static>(inner: T, dir: PathBuf) -> Result<Self> {
+        std::fs::create_dir_all(&dir).map_err(|e| Error::Cassette(e.to_string()))?;
+        // continue numbering after any interactions already in the directory
+        let next = std::fs::read_dir(&dir)
+            .map_err(|e| Error::Cassette(e.to_string()))?
+            .count() as u64;
+        Ok(Self {
+            inner: Arc::new(inner),
+            dir,
+            next: AtomicU64::new(next),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for RecordingTransport {
+    async fn send(&self, body: String) -> Result<String> {
+        let response = self.inner.send(body.clone()).await?;
+        let interaction = Interaction {
+            request: serde_json::from_str(&body)
+                .map_err(|e| Error::json_deser(e, body.clone(), body.clone()))?,
+            response: serde_json::from_str(&response)
+                .map_err(|e| Error::json_deser(e, response.clone(), body.clone()))?,
+        };
+        let n = self.next.fetch_add(1, Ordering::SeqCst);
+        let path = self.dir.join(format!("{n:08}.json"));
+        tokio::fs::write(path, serde_json::to_vec(&interaction).unwrap())
+            .await
+            .map_err(|e| Error::Cassette(e.to_string()))?;
+        Ok(response)
+    }
+}
+
+/// Transport that answers requests from a directory written by
+/// `RecordingTransport`, without touching the network. Requests are matched
+/// on everything but their `id`, which is rewritten to match the caller's.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    interactions: Mutex<HashMap<String, VecDeque<Interaction>>>,
+}
+
+impl ReplayTransport {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        let mut paths = std::fs::read_dir(&dir)
+            .map_err(|e| Error::Cassette(format!("{}: {e}", dir.display())))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()
+            .map_err(|e| Error::Cassette(e.to_string()))?;
+        paths.sort();
+
+        let mut interactions: HashMap<String, VecDeque<Interaction>> = HashMap::new();
+        for path in paths {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| Error::Cassette(format!("{}: {e}", path.display())))?;
+            let interaction: Interaction = serde_json::from_str(&text)
+                .map_err(|e| Error::json_deser(e, text.clone(), path.display().to_string()))?;
+            interactions
+                .entry(request_key(&interaction.request))
+                .or_default()
+                .push_back(interaction);
+        }
+        Ok(Self {
+            interactions: Mutex::new(interactions),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for ReplayTransport {
+    async fn send(&self, body: String) -> Result<String> {
+        let request: Value = serde_json::from_str(&body)
+            .map_err(|e| Error::json_deser(e, body.clone(), body.clone()))?;
+        let interaction = self
+            .interactions
+            .lock()
+            .unwrap()
+            .get_mut(&request_key(&request))
+            .and_then(|queue| queue.pop_front())
+            .ok_or_else(|| Error::Cassette(format!("no recorded response for {body}")))?;
+
+        // map recorded ids onto the ids of this request
+        let ids: HashMap<String, Value> = match (&interaction.request, &request) {
+            (Value::Array(recorded), Value::Array(current)) => recorded
+                .iter()
+                .zip(current)
+                .map(|(r, c)| (r["id"].to_string(), c["id"].clone()))
+                .collect(),
+            (recorded, current) => {
+                HashMap::from([(recorded["id"].to_string(), current["id"].clone())])
+            }
+        };
+        let mut response = interaction.response;
+        let rewrite = |r: &mut Value| {
+            if let Some(id) = ids.get(&r["id"].to_string()) {
+                r["id"] = id.clone();
+            }
+        };
+        match &mut response {
+            Value::Array(responses) => responses.iter_mut().for_each(rewrite),
+            response => rewrite(response),
+        }
+        Ok(response.to_string())
+    }
+}
+
+/// Requests are keyed by their JSON with the `id` removed
+fn request_key(request: &Value) -> String {
+    fn strip_id(request: &Value) -> Value {
+        let mut request = request.clone();
+        if let Some(object) = request.as_object_mut() {
+            object.remove("id");
+        }
+        request
+    }
+    match request {
+        Value::Array(requests) => Value::Array(requests.iter().map(strip_id).collect()),
+        request => strip_id(request),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::test_support::fixture_account;
+    use tokio::test;
+
+    #[test]
+    async fn test_cassette_record_and_replay() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("hnt-explorer-cassette-{nanos}"));
+        let pubkey = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+
+        let fixture =
+            FixtureTransport::new().with_account(pubkey, &fixture_account(owner, vec![4, 2]));
+        let recording =
+            Client::new_with_transport(RecordingTransport::new(fixture, dir.clone()).unwrap());
+        let recorded = recording.get_account(&pubkey).await.unwrap();
+        assert!(recording.get_account(&Pubkey::new_unique()).await.is_err());
+
+        let replay = Client::new_with_transport(ReplayTransport::new(dir.clone()).unwrap());
+        let replayed = replay.get_account(&pubkey).await.unwrap();
+        assert_eq!(replayed.owner, recorded.owner);
+        assert_eq!(replayed.data, recorded.data);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
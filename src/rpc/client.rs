@@ -1,21 +1,19 @@
 use super::*;
+use std::{collections::HashMap, path::PathBuf};
+use stream::BodyParser;
 
 /// The default timeout for API requests
 pub const DEFAULT_TIMEOUT: u64 = 120;
 
 #[derive(Clone, Debug)]
 pub struct Client {
-    base_url: String,
-    client: reqwest::Client,
-    max_retries: u64,
-}
-
-impl Default for Client {
-    fn default() -> Self {
-        let url = std::env::var("SOL_RPC_ENDPOINT")
-            .unwrap_or("https://api.mainnet-beta.solana.com".to_string());
-        Self::new_with_base_url(url)
-    }
+    transport: Arc<dyn Transport>,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+    config: RpcConfig,
+    metrics: Arc<Metrics>,
+    timeouts: Timeouts,
+    cache: Arc<AccountCache>,
 }
 
 impl Client {
@@ -30,92 +28,393 @@ impl Client {
     /// timeout value.  The library will use absoluate paths based on
     /// the given base_url.
     pub fn new_with_timeout(base_url: String, timeout: u64) -> Self {
-        let client = reqwest::Client::builder()
-            .gzip(true)
-            .timeout(Duration::from_secs(timeout))
-            .build()
-            .unwrap();
-        Self {
-            base_url,
-            client,
-            max_retries: 5,
-        }
+        Self::new_with_transport(HttpTransport::new(base_url, timeout))
     }
 
-    pub(crate) async fn post<T: DeserializeOwned, D: Serialize>(&self, data: &D) -> Result<T> {
-        let mut result = self.post_attempt(data).await;
-        let mut retries = 0;
-        while result.is_err() {
-            retries += 1;
-            if retries > self.max_retries {
-                return result;
+    /// Create a new client using several endpoints with the given
+    /// weights, failing over between them when one is unhealthy.
+    pub fn new_with_endpoints(urls: Vec<(String, u32)>) -> Result<Self> {
+        let endpoints = urls
+            .into_iter()
+            .map(|(url, weight)| Endpoint::http(url, weight, DEFAULT_TIMEOUT))
+            .collect();
+        Ok(Self::new_with_transport(EndpointPool::new(endpoints)?))
+    }
+
+    /// Create a new client that records all traffic sent through
+    /// `transport` into `dir`, or that replays a previous recording from
+    /// `dir` without any network access.
+    pub fn new_with_cassette<T: Transport + 'static>(
+        transport: T,
+        dir: PathBuf,
+        mode: CassetteMode,
+    ) -> Result<Self> {
+        Ok(match mode {
+            CassetteMode::Record => {
+                Self::new_with_transport(RecordingTransport::new(transport, dir)?)
             }
-            tokio::time::sleep(Duration::from_secs(retries)).await;
-            result = self.post_attempt(data).await;
+            CassetteMode::Replay => Self::new_with_transport(ReplayTransport::new(dir)?),
+        })
+    }
+
+    /// Create a new client that sends its requests through the given
+    /// transport, such as a `FixtureTransport` for offline use.
+    pub fn new_with_transport<T: Transport + 'static>(transport: T) -> Self {
+        Self {
+            transport: Arc::new(transport),
+            limiter: Arc::new(RateLimiter::default()),
+            retry: RetryPolicy::default(),
+            config: RpcConfig::default(),
+            metrics: Arc::new(Metrics::default()),
+            timeouts: Timeouts::default(),
+            cache: Arc::new(AccountCache::default()),
         }
+    }
+
+    /// Sets the commitment level for account queries
+    pub fn with_commitment(mut self, commitment: Commitment) -> Self {
+        self.config.commitment = Some(commitment);
+        self
+    }
+
+    /// Requires nodes to have reached `slot` before answering account
+    /// queries. Cloning a client and setting this keeps every later read of
+    /// a snapshot at or after the slot of its first read.
+    pub fn with_min_context_slot(mut self, slot: u64) -> Self {
+        self.config.min_context_slot = Some(slot);
+        self
+    }
+
+    /// Sets the encoding nodes use for account data. Compressed data is
+    /// decompressed transparently.
+    pub fn with_encoding(mut self, encoding: EncodingType) -> Self {
+        self.config.encoding = encoding;
+        self
+    }
+
+    pub fn commitment(&self) -> Option<Commitment> {
+        self.config.commitment
+    }
+
+    pub fn min_context_slot(&self) -> Option<u64> {
+        self.config.min_context_slot
+    }
+
+    pub fn encoding(&self) -> EncodingType {
+        self.config.encoding
+    }
+
+    pub(crate) fn config(&self) -> RpcConfig {
+        self.config
+    }
+
+    /// Replaces the policy used to retry failed requests
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Replaces how long calls of each class may wait for a response
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    /// Replaces the rate limiter. Clones of the returned client share it.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Arc::new(limiter);
+        self
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    /// Accounts served without a request by `get_multiple_accounts_cached`.
+    /// Clones of the client share it.
+    pub fn account_cache(&self) -> &AccountCache {
+        &self.cache
+    }
+
+    /// Call counts, latencies and bytes received per method. Clones of the
+    /// client share them.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub(crate) async fn post<T: DeserializeOwned>(&self, data: &RpcCall<'_>) -> Result<T> {
+        self.post_with(data, parse_response).await
+    }
+
+    /// Like `post`, with `parse` turning the response body into the result.
+    /// `parse` is called again for every retried attempt.
+    pub(crate) async fn post_with<T, P>(&self, data: &RpcCall<'_>, parse: P) -> Result<T>
+    where
+        P: Fn(String, String) -> Result<T>,
+    {
+        let mut attempts = 0;
+        self.retry
+            .run(|| {
+                if attempts > 0 {
+                    self.metrics.record_retry(data.method());
+                }
+                attempts += 1;
+                self.post_attempt(data, &parse)
+            })
+            .await
+    }
+
+    async fn post_attempt<T, P>(&self, data: &RpcCall<'_>, parse: &P) -> Result<T>
+    where
+        P: Fn(String, String) -> Result<T>,
+    {
+        let result = self
+            .limiter
+            .run(&[data.method()], async {
+                let request_json = serde_json::to_string(&data).unwrap();
+                let body = self
+                    .send(&[data.method()], data.class(), request_json.clone())
+                    .await?;
+                parse(body, request_json)
+            })
+            .await;
+        self.metrics.record_result(data.method(), result.is_ok());
         result
     }
 
-    pub(crate) async fn post_attempt<T: DeserializeOwned, D: Serialize>(
+    /// Like `post_with`, with the response body fed to a parser in chunks as
+    /// it arrives rather than buffered whole. `parser` makes a new parser
+    /// for every attempt, given the request.
+    pub(crate) async fn post_streaming<P, M>(
         &self,
-        data: &D,
-    ) -> Result<T> {
-        #[derive(Clone, Serialize, Deserialize, Debug)]
-        #[serde(untagged)]
-        enum AllResponse<T> {
-            Ok(FullResponse<T>),
-            Err { error: String },
-        }
+        data: &RpcCall<'_>,
+        parser: M,
+    ) -> Result<P::Output>
+    where
+        M: Fn(String) -> P,
+        P: BodyParser + Send,
+    {
+        let mut attempts = 0;
+        self.retry
+            .run(|| {
+                if attempts > 0 {
+                    self.metrics.record_retry(data.method());
+                }
+                attempts += 1;
+                let request_json = serde_json::to_string(&data).unwrap();
+                self.post_streaming_attempt(data, request_json.clone(), parser(request_json))
+            })
+            .await
+    }
 
-        #[derive(Clone, Serialize, Deserialize, Debug)]
-        struct FullResponse<T> {
-            jsonrpc: String,
-            #[serde(flatten)]
-            response: Response<T>,
-            id: Option<String>,
-        }
-        #[derive(Clone, Serialize, Deserialize, Debug)]
+    async fn post_streaming_attempt<P: BodyParser + Send>(
+        &self,
+        data: &RpcCall<'_>,
+        request_json: String,
+        mut parser: P,
+    ) -> Result<P::Output> {
+        let methods = [data.method()];
+        let result = self
+            .limiter
+            .run(&methods, async {
+                let start = std::time::Instant::now();
+                let timeout = self.timeouts.get(data.class());
+                let sent = tokio::time::timeout(
+                    timeout,
+                    self.transport
+                        .send_streaming(request_json, &mut |chunk: &[u8]| parser.feed(chunk)),
+                )
+                .await
+                .unwrap_or(Err(Error::Timeout(timeout)));
+                let bytes = *sent.as_ref().unwrap_or(&0);
+                self.metrics
+                    .record_request(&methods, start.elapsed(), bytes);
+                sent?;
+                parser.finish()
+            })
+            .await;
+        self.metrics.record_result(data.method(), result.is_ok());
+        result
+    }
+
+    /// Sends a request carrying a call for each entry in `methods`, timing it
+    /// and giving up after the timeout of `class`
+    async fn send(
+        &self,
+        methods: &[&'static str],
+        class: MethodClass,
+        request_json: String,
+    ) -> Result<String> {
+        let start = std::time::Instant::now();
+        let timeout = self.timeouts.get(class);
+        let body = tokio::time::timeout(timeout, self.transport.send(request_json))
+            .await
+            .unwrap_or(Err(Error::Timeout(timeout)));
+        let bytes = body.as_ref().map_or(0, String::len);
+        self.metrics.record_request(methods, start.elapsed(), bytes);
+        body
+    }
+
+    /// Sends all calls in a single JSON-RPC batch and returns one result per
+    /// call, in the order of `calls`. Responses are correlated by `id`, so
+    /// a failed call does not fail the other calls in the batch.
+    pub(crate) async fn post_batch<T: DeserializeOwned>(
+        &self,
+        calls: &[RpcCall<'_>],
+    ) -> Result<Vec<Result<T>>> {
+        let mut attempts = 0;
+        self.retry
+            .run(|| {
+                if attempts > 0 {
+                    for call in calls {
+                        self.metrics.record_retry(call.method());
+                    }
+                }
+                attempts += 1;
+                self.post_batch_attempt(calls)
+            })
+            .await
+    }
+
+    async fn post_batch_attempt<T: DeserializeOwned>(
+        &self,
+        calls: &[RpcCall<'_>],
+    ) -> Result<Vec<Result<T>>> {
+        #[derive(Deserialize, Debug)]
         #[serde(untagged)]
-        #[serde(rename_all = "lowercase")]
-        enum Response<T> {
-            Result { result: T },
-            Error { error: ErrorResponse },
-        }
-        #[derive(Clone, Serialize, Deserialize, Debug)]
-        struct ErrorResponse {
-            code: isize,
-            message: String,
-        }
-        #[derive(Serialize, Debug)]
-        pub struct AssetsByAuthorityResponse {
-            pub items: Vec<Item>,
+        enum BatchResponse {
+            Ok(Vec<FullResponse<serde_json::Value>>),
+            // nodes reply with a single error when rejecting the batch as a whole
+            Err(AllResponse<serde_json::Value>),
         }
-        #[derive(Serialize, Debug)]
-        pub struct Item {
-            pub id: String,
+
+        if calls.is_empty() {
+            return Ok(Vec::new());
         }
+        let request_json = serde_json::to_string(calls).unwrap();
+        let methods: Vec<&'static str> = calls.iter().map(RpcCall::method).collect();
+        // the batch may take as long as its slowest class of call
+        let batch_class = calls
+            .iter()
+            .map(RpcCall::class)
+            .max_by_key(|class| self.timeouts.get(*class))
+            .unwrap_or(MethodClass::Account);
+        let responses = self
+            .limiter
+            .run(&methods, async {
+                let body = self
+                    .send(&methods, batch_class, request_json.clone())
+                    .await?;
+                match serde_json::from_str(&body)
+                    .map_err(|e| Error::json_deser(e, body, request_json.clone()))?
+                {
+                    BatchResponse::Ok(responses) => Ok(responses),
+                    BatchResponse::Err(response) => {
+                        response.into_result(request_json.clone())?;
+                        Err(Error::Custom("node returned a result to a batch request"))
+                    }
+                }
+            })
+            .await;
+        let responses = match responses {
+            Ok(responses) => responses,
+            Err(e) => {
+                for method in &methods {
+                    self.metrics.record_result(method, false);
+                }
+                return Err(e);
+            }
+        };
+
+        let mut responses: HashMap<String, Response<serde_json::Value>> = responses
+            .into_iter()
+            .filter_map(|r| r.id.map(|id| (id, r.response)))
+            .collect();
+        Ok(calls
+            .iter()
+            .map(|call| {
+                let call_json = || serde_json::to_string(call).unwrap();
+                let result = match responses.remove(call.id()) {
+                    None => Err(Error::MissingBatchResponse(call_json())),
+                    Some(response) => response.into_result(call_json()).and_then(|value| {
+                        serde_json::from_value(value.clone())
+                            .map_err(|e| Error::json_deser(e, value.to_string(), call_json()))
+                    }),
+                };
+                self.metrics.record_result(call.method(), result.is_ok());
+                result
+            })
+            .collect())
+    }
+}
+
+/// Parses the body of a response to a single call
+fn parse_response<T: DeserializeOwned>(body: String, request_json: String) -> Result<T> {
+    let response: AllResponse<T> = serde_json::from_str(&body)
+        .map_err(|e| Error::json_deser(e, body, request_json.clone()))?;
+    response.into_result(request_json)
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+enum AllResponse<T> {
+    Ok(FullResponse<T>),
+    Err { error: String },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct FullResponse<T> {
+    jsonrpc: String,
+    #[serde(flatten)]
+    response: Response<T>,
+    id: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+#[serde(rename_all = "lowercase")]
+enum Response<T> {
+    Result { result: T },
+    Error { error: ErrorResponse },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub(super) struct ErrorResponse {
+    pub code: isize,
+    pub message: String,
+}
 
-        let request = self.client.post(&self.base_url).json(&data);
-        let response = request.send().await?;
-        let body = response.text().await?;
-        let response: AllResponse<T> = serde_json::from_str(&body)
-            .map_err(|e| Error::json_deser(e, body, serde_json::to_string(&data).unwrap()))?;
-
-        match response {
-            AllResponse::Ok(response) => match response.response {
-                Response::Result { result, .. } => Ok(result),
-                Response::Error {
-                    error: ErrorResponse { code, message },
-                } => Err(Error::NodeError {
-                    code,
-                    msg: message,
-                    request_json: serde_json::to_string(&data).unwrap(),
-                }),
-            },
+impl<T> AllResponse<T> {
+    fn into_result(self, request_json: String) -> Result<T> {
+        match self {
+            AllResponse::Ok(response) => response.response.into_result(request_json),
             AllResponse::Err { error } => Err(Error::NodeError {
                 code: -1,
                 msg: error,
-                request_json: serde_json::to_string(&data).unwrap(),
+                request_json,
+            }),
+        }
+    }
+}
+
+impl<T> Response<T> {
+    fn into_result(self, request_json: String) -> Result<T> {
+        match self {
+            Response::Result { result, .. } => Ok(result),
+            Response::Error {
+                error: ErrorResponse { code, message },
+            } => Err(Error::NodeError {
+                code,
+                msg: message,
+                request_json,
             }),
         }
     }
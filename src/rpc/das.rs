@@ -0,0 +1,353 @@
+use super::*;
+use std::future::Future;
+
+/// Largest page size DAS providers accept
+pub const DAS_MAX_LIMIT: usize = 1000;
+
+/// A page of a DAS listing. Pages start at 1.
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct Page {
+    pub page: usize,
+    pub limit: usize,
+}
+
+impl Page {
+    pub fn new(page: usize, limit: usize) -> Self {
+        Self { page, limit }
+    }
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self::new(1, DAS_MAX_LIMIT)
+    }
+}
+
+/// Criteria for `searchAssets`. Unset fields are not filtered on.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchAssets {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_pubkey"
+    )]
+    pub owner_address: Option<Pubkey>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_pubkey"
+    )]
+    pub authority_address: Option<Pubkey>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_pubkey"
+    )]
+    pub creator_address: Option<Pubkey>,
+    /// `(group_key, group_value)`, such as `("collection", <collection mint>)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grouping: Option<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burnt: Option<bool>,
+}
+
+/// A page of assets as returned by the DAS listing methods
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AssetList {
+    #[serde(default)]
+    pub total: usize,
+    #[serde(default)]
+    pub limit: usize,
+    #[serde(default)]
+    pub page: Option<usize>,
+    pub items: Vec<Asset>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Asset {
+    #[serde(
+        deserialize_with = "deserialize_pubkey",
+        serialize_with = "serialize_pubkey"
+    )]
+    pub id: Pubkey,
+    #[serde(default)]
+    pub interface: String,
+    #[serde(default)]
+    pub content: Option<Content>,
+    #[serde(default)]
+    pub authorities: Vec<Authority>,
+    #[serde(default)]
+    pub compression: Option<Compression>,
+    #[serde(default)]
+    pub grouping: Vec<Grouping>,
+    #[serde(default)]
+    pub ownership: Option<Ownership>,
+    #[serde(default)]
+    pub mutable: bool,
+    #[serde(default)]
+    pub burnt: bool,
+}
+
+impl Asset {
+    pub fn owner(&self) -> Option<Pubkey> {
+        self.ownership.as_ref().and_then(|o| o.owner)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.content.as_ref()?.metadata.name.as_deref()
+    }
+
+    /// The value of the asset's group of `key`, such as its collection
+    pub fn group(&self, key: &str) -> Option<&str> {
+        self.grouping
+            .iter()
+            .find(|g| g.group_key == key)
+            .map(|g| g.group_value.as_str())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Content {
+    #[serde(default)]
+    pub json_uri: String,
+    #[serde(default)]
+    pub metadata: ContentMetadata,
+    #[serde(default)]
+    pub files: Vec<File>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ContentMetadata {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Attribute {
+    pub trait_type: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct File {
+    #[serde(default)]
+    pub uri: String,
+    #[serde(default)]
+    pub mime: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Authority {
+    #[serde(
+        deserialize_with = "deserialize_pubkey",
+        serialize_with = "serialize_pubkey"
+    )]
+    pub address: Pubkey,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Compression {
+    pub eligible: bool,
+    pub compressed: bool,
+    #[serde(default)]
+    pub data_hash: String,
+    #[serde(default)]
+    pub creator_hash: String,
+    #[serde(default)]
+    pub asset_hash: String,
+    /// Merkle tree of a compressed asset; empty otherwise
+    #[serde(default)]
+    pub tree: String,
+    #[serde(default)]
+    pub seq: u64,
+    #[serde(default)]
+    pub leaf_id: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Grouping {
+    pub group_key: String,
+    pub group_value: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Ownership {
+    #[serde(default)]
+    pub frozen: bool,
+    #[serde(default)]
+    pub delegated: bool,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_pubkey",
+        serialize_with = "serialize_optional_pubkey"
+    )]
+    pub delegate: Option<Pubkey>,
+    #[serde(default)]
+    pub ownership_model: String,
+    /// Burnt assets have no owner
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_pubkey",
+        serialize_with = "serialize_optional_pubkey"
+    )]
+    pub owner: Option<Pubkey>,
+}
+
+impl Client {
+    pub async fn get_asset(&self, id: &Pubkey) -> Result<Asset> {
+        let json = RpcCall::get_asset(id);
+        self.post(&json).await
+    }
+
+    pub async fn get_assets_by_owner_page(&self, owner: &Pubkey, page: Page) -> Result<AssetList> {
+        let json = RpcCall::get_assets_by_owner(owner, page);
+        self.post(&json).await
+    }
+
+    /// Fetches every page of assets held by `owner`
+    pub async fn get_assets_by_owner(&self, owner: &Pubkey) -> Result<Vec<Asset>> {
+        paginate(|page| self.get_assets_by_owner_page(owner, page)).await
+    }
+
+    pub async fn get_assets_by_authority_page(
+        &self,
+        authority: &Pubkey,
+        page: Page,
+    ) -> Result<AssetList> {
+        let json = RpcCall::get_assets_by_authority(authority, page);
+        self.post(&json).await
+    }
+
+    /// Fetches every page of assets with update authority `authority`
+    pub async fn get_assets_by_authority(&self, authority: &Pubkey) -> Result<Vec<Asset>> {
+        paginate(|page| self.get_assets_by_authority_page(authority, page)).await
+    }
+
+    pub async fn search_assets_page(&self, query: &SearchAssets, page: Page) -> Result<AssetList> {
+        let json = RpcCall::search_assets(query, page);
+        self.post(&json).await
+    }
+
+    /// Fetches every page of assets matching `query`
+    pub async fn search_assets(&self, query: &SearchAssets) -> Result<Vec<Asset>> {
+        paginate(|page| self.search_assets_page(query, page)).await
+    }
+}
+
+/// Requests pages of `DAS_MAX_LIMIT` assets until a page comes back short
+async fn paginate<F, Fut>(mut fetch: F) -> Result<Vec<Asset>>
+where
+    F: FnMut(Page) -> Fut,
+    Fut: Future<Output = Result<AssetList>>,
+{
+    let mut assets = Vec::new();
+    let mut page = Page::default();
+    loop {
+        let list = fetch(page).await?;
+        let last_page = list.items.len() < page.limit;
+        assets.extend(list.items);
+        if last_page {
+            return Ok(assets);
+        }
+        page.page += 1;
+    }
+}
+
+fn deserialize_optional_pubkey<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Pubkey>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(str) if !str.is_empty() => Pubkey::from_str(&str)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+fn serialize_optional_pubkey<S: Serializer>(
+    pubkey: &Option<Pubkey>,
+    s: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match pubkey {
+        Some(pubkey) => s.serialize_str(&pubkey.to_string()),
+        None => s.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::test_support::das_asset;
+    use tokio::test;
+
+    #[test]
+    async fn test_fixture_das_get_assets_paginated() {
+        let owner = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let mut fixture = FixtureTransport::new();
+        for i in 0..2500 {
+            let collection = if i % 2 == 0 { "even" } else { "odd" };
+            fixture = fixture.with_asset(das_asset(
+                Pubkey::new_unique(),
+                owner,
+                authority,
+                collection,
+            ));
+        }
+        let other = das_asset(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            authority,
+            "even",
+        );
+        let client = Client::new_with_transport(fixture.with_asset(other.clone()));
+
+        let assets = client.get_assets_by_owner(&owner).await.unwrap();
+        assert_eq!(assets.len(), 2500);
+        assert!(assets.iter().all(|a| a.owner() == Some(owner)));
+        assert_eq!(assets[0].name(), Some("Position"));
+        assert_eq!(
+            assets[0].content.as_ref().unwrap().metadata.attributes[0].trait_type,
+            "sub_dao"
+        );
+        assert_eq!(client.metrics().snapshot()[0].successes, 3);
+
+        assert_eq!(
+            client
+                .get_assets_by_authority(&authority)
+                .await
+                .unwrap()
+                .len(),
+            2501
+        );
+        let query = SearchAssets {
+            grouping: Some(("collection".to_string(), "even".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(client.search_assets(&query).await.unwrap().len(), 1251);
+        let query = SearchAssets {
+            owner_address: Some(owner),
+            grouping: Some(("collection".to_string(), "even".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(client.search_assets(&query).await.unwrap().len(), 1250);
+
+        let asset = client.get_asset(&other.id).await.unwrap();
+        assert_eq!(asset.owner(), other.owner());
+        assert_eq!(asset.group("collection"), Some("even"));
+        let err = client.get_asset(&Pubkey::new_unique()).await.unwrap_err();
+        assert!(matches!(err, Error::NodeError { code: -32000, .. }));
+        assert_eq!(err.kind(), ErrorKind::AccountNotFound);
+    }
+}
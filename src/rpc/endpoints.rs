@@ -0,0 +1,337 @@
+use super::*;
+use std::{sync::Mutex, time::Instant};
+
+/// How long an endpoint is skipped after its first failure. The cooldown
+/// doubles with every consecutive failure up to `MAX_COOLDOWN`.
+const BASE_COOLDOWN: Duration = Duration::from_secs(1);
+const MAX_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// A single RPC provider in an `EndpointPool`
+#[derive(Debug)]
+pub struct Endpoint {
+    name: String,
+    weight: u32,
+    transport: Arc<dyn Transport>,
+}
+
+impl Endpoint {
+    pub fn new<T: Transport + 'static>(name: String, weight: u32, transport: T) -> Self {
+        Self {
+            name,
+            weight: weight.max(1),
+            transport: Arc::new(transport),
+        }
+    }
+
+    pub fn http(url: String, weight: u32, timeout: u64) -> Self {
+        Self::new(redact(&url), weight, HttpTransport::new(url, timeout))
+    }
+
+    /// An HTTP endpoint sending requests through an already configured client
+    pub fn http_with_client(url: String, weight: u32, client: reqwest::Client) -> Self {
+        Self::new(
+            redact(&url),
+            weight,
+            HttpTransport::with_client(url, client),
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct EndpointState {
+    current_weight: i64,
+    consecutive_failures: u32,
+    unhealthy_until: Option<Instant>,
+    requests: u64,
+    failures: u64,
+}
+
+impl EndpointState {
+    fn is_healthy(&self, now: Instant) -> bool {
+        match self.unhealthy_until {
+            Some(until) => until <= now,
+            None => true,
+        }
+    }
+}
+
+/// Point-in-time view of an endpoint's health
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointHealth {
+    pub name: String,
+    pub weight: u32,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub requests: u64,
+    pub failures: u64,
+}
+
+/// Transport that spreads requests over several endpoints using smooth
+/// weighted round-robin. An endpoint that is rate limiting (429), erroring
+/// (5xx) or unreachable is put in a cooldown and the request fails over to
+/// the next endpoint. When every endpoint is cooling down, the one that
+/// recovers first is used anyway.
+#[derive(Debug)]
+pub struct EndpointPool {
+    endpoints: Vec<Endpoint>,
+    state: Mutex<Vec<EndpointState>>,
+}
+
+impl EndpointPool {
+    pub fn new(endpoints: Vec<Endpoint>) -> Result<Self> {
+        if endpoints.is_empty() {
+            return Err(Error::NoEndpoints);
+        }
+        Ok(Self {
+            state: Mutex::new(vec![EndpointState::default(); endpoints.len()]),
+            endpoints,
+        })
+    }
+
+    /// Builds a pool of HTTP endpoints from a comma separated list of URLs
+    /// and an optional comma separated list of weights in the same order.
+    pub fn from_urls(urls: &str, weights: Option<&str>, timeout: u64) -> Result<Self> {
+        Self::new(
+            parse_urls(urls, weights)?
+                .into_iter()
+                .map(|(url, weight)| Endpoint::http(url, weight, timeout))
+                .collect(),
+        )
+    }
+
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        let state = self.state.lock().unwrap();
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .zip(state.iter())
+            .map(|(endpoint, state)| EndpointHealth {
+                name: endpoint.name.clone(),
+                weight: endpoint.weight,
+                healthy: state.is_healthy(now),
+                consecutive_failures: state.consecutive_failures,
+                requests: state.requests,
+                failures: state.failures,
+            })
+            .collect()
+    }
+
+    /// Picks the next endpoint, skipping those in `tried`
+    fn pick(&self, tried: &[usize]) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let untried: Vec<usize> = (0..self.endpoints.len())
+            .filter(|i| !tried.contains(i))
+            .collect();
+        let mut candidates: Vec<usize> = untried
+            .iter()
+            .copied()
+            .filter(|i| state[*i].is_healthy(now))
+            .collect();
+        if candidates.is_empty() {
+            candidates = untried
+                .into_iter()
+                .min_by_key(|i| state[*i].unhealthy_until)
+                .into_iter()
+                .collect();
+        }
+
+        let total: i64 = candidates
+            .iter()
+            .map(|i| self.endpoints[*i].weight as i64)
+            .sum();
+        for i in &candidates {
+            state[*i].current_weight += self.endpoints[*i].weight as i64;
+        }
+        let best = candidates
+            .into_iter()
+            .max_by_key(|i| (state[*i].current_weight, std::cmp::Reverse(*i)))?;
+        state[best].current_weight -= total;
+        state[best].requests += 1;
+        Some(best)
+    }
+
+    fn mark_success(&self, i: usize) {
+        let mut state = self.state.lock().unwrap();
+        state[i].consecutive_failures = 0;
+        state[i].unhealthy_until = None;
+    }
+
+    fn mark_failure(&self, i: usize) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut state[i];
+        state.failures += 1;
+        state.consecutive_failures += 1;
+        let cooldown = BASE_COOLDOWN
+            .saturating_mul(1 << (state.consecutive_failures - 1).min(16))
+            .min(MAX_COOLDOWN);
+        state.unhealthy_until = Some(Instant::now() + cooldown);
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for EndpointPool {
+    async fn send(&self, body: String) -> Result<String> {
+        let mut tried = Vec::with_capacity(self.endpoints.len());
+        let mut last_error = None;
+        while let Some(i) = self.pick(&tried) {
+            tried.push(i);
+            let endpoint = &self.endpoints[i];
+            match endpoint.transport.send(body.clone()).await {
+                Ok(response) => {
+                    self.mark_success(i);
+                    return Ok(response);
+                }
+                Err(e) if e.is_endpoint_failure() => {
+                    println!("Warning: RPC endpoint {} failed: {e}", endpoint.name);
+                    self.mark_failure(i);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or(Error::NoEndpoints))
+    }
+
+    /// Fails over like `send` as long as no part of the body has reached
+    /// `sink`, after which the request cannot be restarted elsewhere
+    async fn send_streaming(
+        &self,
+        body: String,
+        sink: &mut (dyn FnMut(&[u8]) -> Result<()> + Send),
+    ) -> Result<usize> {
+        let mut tried = Vec::with_capacity(self.endpoints.len());
+        let mut last_error = None;
+        while let Some(i) = self.pick(&tried) {
+            tried.push(i);
+            let endpoint = &self.endpoints[i];
+            let mut delivered = false;
+            let result = endpoint
+                .transport
+                .send_streaming(body.clone(), &mut |chunk: &[u8]| {
+                    delivered = true;
+                    sink(chunk)
+                })
+                .await;
+            match result {
+                Ok(len) => {
+                    self.mark_success(i);
+                    return Ok(len);
+                }
+                Err(e) if e.is_endpoint_failure() => {
+                    println!("Warning: RPC endpoint {} failed: {e}", endpoint.name);
+                    self.mark_failure(i);
+                    if delivered {
+                        return Err(e);
+                    }
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or(Error::NoEndpoints))
+    }
+}
+
+/// Parses a comma separated list of URLs and an optional comma separated
+/// list of weights in the same order
+pub(crate) fn parse_urls(urls: &str, weights: Option<&str>) -> Result<Vec<(String, u32)>> {
+    let urls: Vec<String> = urls
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(str::to_string)
+        .collect();
+    let weights = match weights {
+        None => vec![1; urls.len()],
+        Some(weights) => weights
+            .split(',')
+            .map(|w| w.trim().parse::<u32>())
+            .collect::<std::result::Result<Vec<u32>, _>>()?,
+    };
+    if weights.len() != urls.len() {
+        return Err(Error::Custom(
+            "number of endpoint weights must match endpoints",
+        ));
+    }
+    Ok(urls.into_iter().zip(weights).collect())
+}
+
+/// Strips the query string, which often carries an API key, for logging
+fn redact(url: &str) -> String {
+    match url.split_once('?') {
+        Some((base, _)) => format!("{base}?..."),
+        None => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::test_support::*;
+    use tokio::test;
+
+    #[test]
+    async fn test_endpoint_pool_failover() {
+        let pubkey = Pubkey::new_unique();
+        let fixture = FixtureTransport::new()
+            .with_account(pubkey, &fixture_account(Pubkey::new_unique(), vec![1]));
+        let pool = EndpointPool::new(vec![
+            Endpoint::new("down".to_string(), 10, FailingTransport),
+            Endpoint::new("up".to_string(), 1, fixture),
+        ])
+        .unwrap();
+        let request =
+            serde_json::to_string(&RpcCall::get_account_info(&pubkey, RpcConfig::default()))
+                .unwrap();
+        assert!(pool.send(request.clone()).await.is_ok());
+
+        let health = pool.health();
+        assert!(!health[0].healthy);
+        assert_eq!(health[0].failures, 1);
+        assert!(health[1].healthy);
+
+        // the failed endpoint is skipped while it cools down
+        pool.send(request).await.unwrap();
+        assert_eq!(pool.health()[0].requests, 1);
+        assert_eq!(pool.health()[1].requests, 2);
+    }
+
+    #[test]
+    async fn test_endpoint_pool_weighted_round_robin() {
+        let pubkey = Pubkey::new_unique();
+        let endpoint = |name: &str, weight| {
+            Endpoint::new(
+                name.to_string(),
+                weight,
+                FixtureTransport::new()
+                    .with_account(pubkey, &fixture_account(Pubkey::new_unique(), vec![1])),
+            )
+        };
+        let pool = EndpointPool::new(vec![endpoint("a", 3), endpoint("b", 1)]).unwrap();
+        let request =
+            serde_json::to_string(&RpcCall::get_account_info(&pubkey, RpcConfig::default()))
+                .unwrap();
+        for _ in 0..8 {
+            pool.send(request.clone()).await.unwrap();
+        }
+        let health = pool.health();
+        assert_eq!(health[0].requests, 6);
+        assert_eq!(health[1].requests, 2);
+    }
+
+    #[test]
+    async fn test_endpoint_pool_from_urls() {
+        let pool = EndpointPool::from_urls(
+            "https://a.example.com, https://b.example.com/?api-key=secret",
+            Some("2,1"),
+            1,
+        )
+        .unwrap();
+        let health = pool.health();
+        assert_eq!(health.len(), 2);
+        assert_eq!(health[0].weight, 2);
+        assert_eq!(health[1].name, "https://b.example.com/?...");
+        assert!(EndpointPool::from_urls("https://a.example.com", Some("1,2"), 1).is_err());
+    }
+}
@@ -14,6 +14,10 @@ pub enum Error {
     JsonDeserialization(serde_json::Error, String, String),
     #[error("base64 decode error: {0}")]
     B64Decode(#[from] base64::DecodeError),
+    #[error("zstd decode error: {0}")]
+    Zstd(std::io::Error),
+    #[error("error decoding account {0}: {1}")]
+    AccountDecode(anchor_lang::solana_program::pubkey::Pubkey, String),
     #[error("solana parse pubkey error: {0}")]
     SolanaParsePubkey(#[from] anchor_lang::solana_program::pubkey::ParsePubkeyError),
     #[error("join error: {0} ")]
@@ -26,10 +30,140 @@ pub enum Error {
     AccountNotFound,
     #[error("try from slice error: {0}")]
     TryFromSlice(#[from] std::array::TryFromSliceError),
+    #[error("fixture error: {0}")]
+    Fixture(String),
+    #[error("cassette error: {0}")]
+    Cassette(String),
+    #[error("no response in batch to request: {0}")]
+    MissingBatchResponse(String),
+    #[error("{0}")]
+    Custom(&'static str),
+    #[error("http status {status} from node: {body}")]
+    HttpStatus { status: u16, body: String },
+    #[error("no RPC endpoint available")]
+    NoEndpoints,
+    #[error("no response from node after {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("invalid client configuration: {0}")]
+    Config(String),
+    #[error("token account {0} does not hold the NFT")]
+    NftNotHeld(anchor_lang::solana_program::pubkey::Pubkey),
+    #[error("websocket error: {0}")]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    #[error("websocket connection closed")]
+    SubscriptionClosed,
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        Error::WebSocket(Box::new(e))
+    }
+}
+
+/// Broad classes of RPC errors, used to decide whether a request is worth
+/// retrying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The endpoint is throttling us
+    RateLimited,
+    /// Network trouble or a node-side failure that may succeed on retry
+    Transient,
+    /// The node rejected the request itself (bad params, unknown method)
+    InvalidRequest,
+    /// The requested account or asset does not exist
+    AccountNotFound,
+    /// The response could not be decoded
+    Deserialization,
+    /// Anything else, such as local configuration errors
+    Other,
+}
+
+impl ErrorKind {
+    pub fn is_retriable(&self) -> bool {
+        matches!(self, ErrorKind::RateLimited | ErrorKind::Transient)
+    }
 }
 
 impl Error {
     pub fn json_deser(erro: serde_json::Error, full_text: String, request: String) -> Self {
         Error::JsonDeserialization(erro, full_text, request)
     }
+
+    /// Whether the error indicates a problem with the endpoint itself
+    /// (unreachable, timing out, rate limiting or failing), in which case
+    /// another endpoint may succeed.
+    pub fn is_endpoint_failure(&self) -> bool {
+        matches!(self, Error::Reqwest(_) | Error::HttpStatus { .. })
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::HttpStatus { status: 429, .. } => ErrorKind::RateLimited,
+            Error::HttpStatus { .. }
+            | Error::Reqwest(_)
+            | Error::MissingBatchResponse(_)
+            | Error::Timeout(_)
+            | Error::WebSocket(_)
+            | Error::SubscriptionClosed => ErrorKind::Transient,
+            Error::NodeError { code, msg, .. } => match code {
+                // some providers report throttling as a JSON-RPC error
+                429 | -32429 => ErrorKind::RateLimited,
+                // parse error, invalid request, method not found, invalid params
+                -32700 | -32600 | -32601 | -32602 => ErrorKind::InvalidRequest,
+                // DAS reports missing assets as a generic server error
+                _ if msg.to_lowercase().contains("not found") => ErrorKind::AccountNotFound,
+                _ => ErrorKind::Transient,
+            },
+            Error::AccountNotFound | Error::NoAssetByAuthority(_) | Error::NftNotHeld(_) => {
+                ErrorKind::AccountNotFound
+            }
+            Error::JsonDeserialization(..)
+            | Error::B64Decode(_)
+            | Error::Zstd(_)
+            | Error::AccountDecode(..)
+            | Error::SolanaParsePubkey(_)
+            | Error::ParseInt(_)
+            | Error::TryFromSlice(_) => ErrorKind::Deserialization,
+            Error::Join(_)
+            | Error::Fixture(_)
+            | Error::Cassette(_)
+            | Error::Custom(_)
+            | Error::NoEndpoints
+            | Error::Config(_) => ErrorKind::Other,
+        }
+    }
+
+    pub fn is_retriable(&self) -> bool {
+        self.kind().is_retriable()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        let node_error = |code| Error::NodeError {
+            code,
+            msg: String::new(),
+            request_json: String::new(),
+        };
+        assert_eq!(node_error(-32602).kind(), ErrorKind::InvalidRequest);
+        assert_eq!(node_error(-32005).kind(), ErrorKind::Transient);
+        assert_eq!(node_error(-32429).kind(), ErrorKind::RateLimited);
+        let throttled = Error::HttpStatus {
+            status: 429,
+            body: String::new(),
+        };
+        assert_eq!(throttled.kind(), ErrorKind::RateLimited);
+        assert!(throttled.is_retriable());
+        assert_eq!(Error::AccountNotFound.kind(), ErrorKind::AccountNotFound);
+        assert!(!Error::AccountNotFound.is_retriable());
+        let deser = serde_json::from_str::<u8>("x").unwrap_err();
+        assert_eq!(
+            Error::json_deser(deser, String::new(), String::new()).kind(),
+            ErrorKind::Deserialization
+        );
+    }
 }
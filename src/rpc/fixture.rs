@@ -0,0 +1,448 @@
+use super::*;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// In-memory transport that answers RPC calls from fixture data instead of a
+/// node. Accounts are served to `getAccountInfo`, `getMultipleAccounts` and
+/// `getProgramAccounts` (including `dataSize` and `memcmp` filters,
+/// `dataSlice` and `base64+zstd` encoding) at the fixture's slot, rejecting
+/// requests with a later `minContextSlot`; any other method is answered from
+/// a recorded `result` registered with `with_response`.
+/// DAS methods are answered from assets registered with `with_asset`, paged
+/// like a DAS provider, unless a recorded `result` exists for them.
+#[derive(Debug, Default)]
+pub struct FixtureTransport {
+    slot: u64,
+    accounts: BTreeMap<Pubkey, FixtureAccount>,
+    largest_accounts: HashMap<Pubkey, Pubkey>,
+    token_supply: HashMap<Pubkey, u128>,
+    assets: Vec<Asset>,
+    responses: HashMap<String, Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixtureAccount {
+    #[serde(
+        deserialize_with = "deserialize_pubkey",
+        serialize_with = "serialize_pubkey"
+    )]
+    pub owner: Pubkey,
+    #[serde(default)]
+    pub lamports: u64,
+    /// base64 encoded account data
+    pub data: String,
+    #[serde(default)]
+    pub executable: bool,
+    #[serde(default)]
+    pub rent_epoch: Epoch,
+}
+
+/// On-disk format read by `FixtureTransport::load`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fixtures {
+    #[serde(default)]
+    pub slot: u64,
+    /// account pubkey to account
+    #[serde(default)]
+    pub accounts: HashMap<String, FixtureAccount>,
+    /// mint to largest token account
+    #[serde(default)]
+    pub largest_accounts: HashMap<String, String>,
+    /// mint to supply
+    #[serde(default)]
+    pub token_supply: HashMap<String, u128>,
+    /// DAS assets
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+    /// method name to JSON-RPC `result`
+    #[serde(default)]
+    pub responses: HashMap<String, Value>,
+}
+
+impl From<&Account> for FixtureAccount {
+    fn from(account: &Account) -> Self {
+        Self {
+            owner: account.owner,
+            lamports: account.lamports,
+            data: base64::engine::general_purpose::STANDARD.encode(&account.data),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        }
+    }
+}
+
+impl FixtureTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load fixtures from a JSON file in the `Fixtures` format
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| Error::Fixture(e.to_string()))?;
+        Self::from_json(&text)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        let fixtures: Fixtures = serde_json::from_str(text)
+            .map_err(|e| Error::json_deser(e, text.to_string(), String::new()))?;
+        let mut transport = Self::new().with_slot(fixtures.slot);
+        for (pubkey, account) in fixtures.accounts {
+            transport
+                .accounts
+                .insert(Pubkey::from_str(&pubkey)?, account);
+        }
+        for (mint, token_account) in fixtures.largest_accounts {
+            transport
+                .largest_accounts
+                .insert(Pubkey::from_str(&mint)?, Pubkey::from_str(&token_account)?);
+        }
+        for (mint, supply) in fixtures.token_supply {
+            transport
+                .token_supply
+                .insert(Pubkey::from_str(&mint)?, supply);
+        }
+        transport.assets = fixtures.assets;
+        transport.responses = fixtures.responses;
+        Ok(transport)
+    }
+
+    pub fn with_slot(mut self, slot: u64) -> Self {
+        self.slot = slot;
+        self
+    }
+
+    pub fn with_account(mut self, pubkey: Pubkey, account: &Account) -> Self {
+        self.accounts.insert(pubkey, account.into());
+        self
+    }
+
+    /// Adds an initialized SPL token account holding one token of `mint` for
+    /// `owner` and registers it as the largest account of the mint.
+    pub fn with_token_account(
+        mut self,
+        mint: Pubkey,
+        token_account: Pubkey,
+        owner: Pubkey,
+    ) -> Self {
+        use spl_token_2022::{
+            solana_program::program_pack::Pack,
+            state::{Account as TokenAccount, AccountState},
+        };
+        let state = TokenAccount {
+            mint,
+            owner,
+            amount: 1,
+            state: AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; TokenAccount::LEN];
+        state.pack_into_slice(&mut data);
+        let account = Account {
+            lamports: 2_039_280,
+            owner: spl_token_2022::id(),
+            data,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.largest_accounts.insert(mint, token_account);
+        self.with_account(token_account, &account)
+    }
+
+    pub fn with_token_supply(mut self, mint: Pubkey, supply: u128) -> Self {
+        self.token_supply.insert(mint, supply);
+        self
+    }
+
+    pub fn with_asset(mut self, asset: Asset) -> Self {
+        self.assets.push(asset);
+        self
+    }
+
+    /// Registers the `result` returned for every call to `method`
+    pub fn with_response(mut self, method: &str, result: Value) -> Self {
+        self.responses.insert(method.to_string(), result);
+        self
+    }
+
+    fn answer(&self, request: &Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        match self.result(method, &params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "error": { "code": code, "message": message },
+                "id": id,
+            }),
+        }
+    }
+
+    fn result(&self, method: &str, params: &Value) -> std::result::Result<Value, (isize, String)> {
+        let context = json!({ "slot": self.slot });
+        let min_context_slot = params
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find_map(|p| p.get("minContextSlot"))
+            .and_then(Value::as_u64);
+        if let Some(min_context_slot) = min_context_slot {
+            if min_context_slot > self.slot {
+                return Err((
+                    -32016,
+                    format!("Minimum context slot has not been reached: {min_context_slot}"),
+                ));
+            }
+        }
+        match method {
+            "getAccountInfo" => {
+                let pubkey = param_pubkey(&params[0])?;
+                let value = self.account_json(&pubkey, &params[1])?;
+                Ok(json!({ "context": context, "value": value }))
+            }
+            "getMultipleAccounts" => {
+                let pubkeys = params[0]
+                    .as_array()
+                    .ok_or_else(|| invalid_params("expected an array of pubkeys"))?;
+                let value = pubkeys
+                    .iter()
+                    .map(|p| param_pubkey(p).and_then(|p| self.account_json(&p, &params[1])))
+                    .collect::<std::result::Result<Vec<Value>, _>>()?;
+                Ok(json!({ "context": context, "value": value }))
+            }
+            "getProgramAccounts" => {
+                let program_id = param_pubkey(&params[0])?;
+                let filters = params[1]["filters"].as_array().cloned().unwrap_or_default();
+                let mut accounts = Vec::new();
+                for (pubkey, account) in &self.accounts {
+                    if account.owner != program_id {
+                        continue;
+                    }
+                    let data = account_data(account)?;
+                    if filters.iter().all(|f| filter_matches(f, &data)) {
+                        let account = self.account_json(pubkey, &params[1])?;
+                        accounts.push(json!({
+                            "pubkey": pubkey.to_string(),
+                            "account": account,
+                        }));
+                    }
+                }
+                if params[1]["withContext"].as_bool() == Some(true) {
+                    Ok(json!({ "context": context, "value": accounts }))
+                } else {
+                    Ok(Value::Array(accounts))
+                }
+            }
+            "getTokenLargestAccounts" => {
+                let mint = param_pubkey(&params[0])?;
+                let value = match self.largest_accounts.get(&mint) {
+                    Some(address) => vec![json!({
+                        "address": address.to_string(),
+                        "amount": "1",
+                        "decimals": 0,
+                        "uiAmountString": "1",
+                    })],
+                    None => vec![],
+                };
+                Ok(json!({ "context": context, "value": value }))
+            }
+            "getTokenSupply" => {
+                let mint = param_pubkey(&params[0])?;
+                match self.token_supply.get(&mint) {
+                    Some(amount) => Ok(json!({
+                        "context": context,
+                        "value": { "amount": amount.to_string() },
+                    })),
+                    None => Err(invalid_params("Invalid param: not a Token mint")),
+                }
+            }
+            "getAsset" | "getAssetsByOwner" | "getAssetsByAuthority" | "searchAssets"
+                if !self.responses.contains_key(method) =>
+            {
+                self.das_result(method, params)
+            }
+            _ => self
+                .responses
+                .get(method)
+                .cloned()
+                .ok_or_else(|| (-32601, format!("Method not found: {method}"))),
+        }
+    }
+
+    fn das_result(
+        &self,
+        method: &str,
+        params: &Value,
+    ) -> std::result::Result<Value, (isize, String)> {
+        let param = |name: &str| match params.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(p) => param_pubkey(p).map(Some),
+        };
+        if method == "getAsset" {
+            let id = param("id")?.ok_or_else(|| invalid_params("missing id"))?;
+            return match self.assets.iter().find(|a| a.id == id) {
+                Some(asset) => Ok(json!(asset)),
+                None => Err((-32000, "Asset Not Found".to_string())),
+            };
+        }
+
+        let owner = param("ownerAddress")?;
+        let authority = param("authorityAddress")?;
+        let grouping = params.get("grouping").and_then(Value::as_array);
+        let compressed = params.get("compressed").and_then(Value::as_bool);
+        let burnt = params.get("burnt").and_then(Value::as_bool);
+        let matching: Vec<&Asset> = self
+            .assets
+            .iter()
+            .filter(|a| owner.is_none() || a.owner() == owner)
+            .filter(|a| match authority {
+                Some(authority) => a.authorities.iter().any(|x| x.address == authority),
+                None => true,
+            })
+            .filter(|a| match grouping {
+                Some(g) => a.group(g[0].as_str().unwrap_or("")) == g[1].as_str(),
+                None => true,
+            })
+            .filter(|a| match compressed {
+                Some(compressed) => {
+                    a.compression.as_ref().map(|c| c.compressed) == Some(compressed)
+                }
+                None => true,
+            })
+            .filter(|a| burnt.is_none() || burnt == Some(a.burnt))
+            .collect();
+
+        let page = params["page"].as_u64().unwrap_or(1).max(1) as usize;
+        let limit = params["limit"].as_u64().unwrap_or(DAS_MAX_LIMIT as u64) as usize;
+        let items: Vec<&Asset> = matching
+            .iter()
+            .skip((page - 1) * limit)
+            .take(limit)
+            .copied()
+            .collect();
+        Ok(json!({
+            "total": items.len(),
+            "limit": limit,
+            "page": page,
+            "items": items,
+        }))
+    }
+
+    /// Answers with the account in the `encoding` and `dataSlice` requested
+    /// by `config`
+    fn account_json(
+        &self,
+        pubkey: &Pubkey,
+        config: &Value,
+    ) -> std::result::Result<Value, (isize, String)> {
+        let Some(account) = self.accounts.get(pubkey) else {
+            return Ok(Value::Null);
+        };
+        let mut data = account_data(account)?;
+        if let Some(slice) = config.get("dataSlice") {
+            let offset = (slice["offset"].as_u64().unwrap_or(0) as usize).min(data.len());
+            let end = (offset + slice["length"].as_u64().unwrap_or(0) as usize).min(data.len());
+            data = data[offset..end].to_vec();
+        }
+        let encoding = config["encoding"].as_str().unwrap_or("base64");
+        let data = match encoding {
+            "base64" => data,
+            "base64+zstd" => {
+                zstd::encode_all(data.as_slice(), 0).map_err(|e| (-32603, e.to_string()))?
+            }
+            other => return Err(invalid_params(&format!("unsupported encoding {other}"))),
+        };
+        Ok(json!({
+            "lamports": account.lamports,
+            "owner": account.owner.to_string(),
+            "data": [base64::engine::general_purpose::STANDARD.encode(data), encoding],
+            "executable": account.executable,
+            "rentEpoch": account.rent_epoch,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for FixtureTransport {
+    async fn send(&self, body: String) -> Result<String> {
+        let request: Value = serde_json::from_str(&body)
+            .map_err(|e| Error::json_deser(e, body.clone(), body.clone()))?;
+        let response = match &request {
+            Value::Array(requests) => {
+                Value::Array(requests.iter().map(|r| self.answer(r)).collect())
+            }
+            request => self.answer(request),
+        };
+        Ok(response.to_string())
+    }
+}
+
+fn account_data(account: &FixtureAccount) -> std::result::Result<Vec<u8>, (isize, String)> {
+    base64::engine::general_purpose::STANDARD
+        .decode(&account.data)
+        .map_err(|e| (-32603, e.to_string()))
+}
+
+fn invalid_params(msg: &str) -> (isize, String) {
+    (-32602, msg.to_string())
+}
+
+fn param_pubkey(param: &Value) -> std::result::Result<Pubkey, (isize, String)> {
+    param
+        .as_str()
+        .and_then(|p| Pubkey::from_str(p).ok())
+        .ok_or_else(|| invalid_params("Invalid param: expected a base58 pubkey"))
+}
+
+fn filter_matches(filter: &Value, data: &[u8]) -> bool {
+    if let Some(size) = filter.get("dataSize").and_then(Value::as_u64) {
+        return data.len() as u64 == size;
+    }
+    if let Some(memcmp) = filter.get("memcmp") {
+        let offset = memcmp["offset"].as_u64().unwrap_or(0) as usize;
+        let bytes = memcmp["bytes"]
+            .as_str()
+            .and_then(|b| bs58::decode(b).into_vec().ok())
+            .unwrap_or_default();
+        return data.len() >= offset + bytes.len() && data[offset..offset + bytes.len()] == bytes;
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::test;
+
+    #[test]
+    async fn test_fixture_from_json() {
+        let client = Client::new_with_transport(
+            FixtureTransport::from_json(
+                r#"{
+                    "tokenSupply": { "hntyVP6YFm1Hg25TN9WGLqM12b8TQmcknKrdu1oxWux": 1000 },
+                    "responses": {
+                        "getAssetsByAuthority": {
+                            "items": [{ "id": "BQW1ABLREJtw8hA3WqpvfvUZppVWaG127hAoVsNnmNKS" }]
+                        }
+                    }
+                }"#,
+            )
+            .unwrap(),
+        );
+        let hnt_mint = Pubkey::from_str("hntyVP6YFm1Hg25TN9WGLqM12b8TQmcknKrdu1oxWux").unwrap();
+        assert_eq!(
+            client.get_token_supply_amount(&hnt_mint).await.unwrap(),
+            1000
+        );
+        assert_eq!(
+            client
+                .get_assets_by_authority(&Pubkey::new_unique())
+                .await
+                .unwrap()[0]
+                .id,
+            Pubkey::from_str("BQW1ABLREJtw8hA3WqpvfvUZppVWaG127hAoVsNnmNKS").unwrap()
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use super::*;
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// Upper bounds, in milliseconds, of the latency histogram buckets. Requests
+/// slower than the last bound are counted in an overflow bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 11] =
+    [10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000, 30000];
+
+/// Per-method counters and latency histograms of the calls a `Client` (and
+/// its clones) sends. Latency is measured from sending the HTTP request to
+/// receiving its body, excluding time spent waiting on the rate limiter.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    methods: Mutex<BTreeMap<&'static str, MethodMetrics>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodMetrics {
+    pub method: &'static str,
+    pub successes: u64,
+    pub failures: u64,
+    /// Attempts made after a failed attempt of the same call
+    pub retries: u64,
+    /// Size of the response bodies. A batch response is split evenly
+    /// between the calls in the batch.
+    pub bytes_received: u64,
+    pub latency: Histogram,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Histogram {
+    pub count: u64,
+    pub sum_ms: u64,
+    /// Count of samples at or below each bound of `LATENCY_BUCKETS_MS`,
+    /// followed by the count of samples above the last bound
+    pub buckets: Vec<u64>,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum_ms: 0,
+            buckets: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+        }
+    }
+}
+
+impl Histogram {
+    fn record(&mut self, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum_ms += ms;
+    }
+
+    pub fn mean_ms(&self) -> Option<u64> {
+        (self.count > 0).then(|| self.sum_ms / self.count)
+    }
+}
+
+impl Metrics {
+    fn update(&self, method: &'static str, f: impl FnOnce(&mut MethodMetrics)) {
+        let mut methods = self.methods.lock().unwrap();
+        let metrics = methods.entry(method).or_insert_with(|| MethodMetrics {
+            method,
+            successes: 0,
+            failures: 0,
+            retries: 0,
+            bytes_received: 0,
+            latency: Histogram::default(),
+        });
+        f(metrics)
+    }
+
+    /// Records one HTTP request carrying a call for each entry in `methods`
+    pub(crate) fn record_request(&self, methods: &[&'static str], latency: Duration, bytes: usize) {
+        let bytes = (bytes / methods.len().max(1)) as u64;
+        for method in methods {
+            self.update(method, |m| {
+                m.latency.record(latency);
+                m.bytes_received += bytes;
+            });
+        }
+    }
+
+    pub(crate) fn record_result(&self, method: &'static str, success: bool) {
+        self.update(method, |m| {
+            if success {
+                m.successes += 1;
+            } else {
+                m.failures += 1;
+            }
+        });
+    }
+
+    pub(crate) fn record_retry(&self, method: &'static str) {
+        self.update(method, |m| m.retries += 1);
+    }
+
+    pub fn snapshot(&self) -> Vec<MethodMetrics> {
+        self.methods.lock().unwrap().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::test_support::*;
+    use tokio::test;
+
+    #[test]
+    async fn test_metrics_count_results_retries_and_bytes() {
+        let pubkey = Pubkey::new_unique();
+        let flaky = FlakyTransport {
+            failures: 1.into(),
+            inner: FixtureTransport::new()
+                .with_account(pubkey, &fixture_account(Pubkey::new_unique(), vec![1])),
+        };
+        let client = Client::new_with_transport(flaky).with_retry_policy(fast_retries(2));
+        client.get_account(&pubkey).await.unwrap();
+        client
+            .get_token_supply_amount(&Pubkey::new_unique())
+            .await
+            .unwrap_err();
+
+        let metrics = client.metrics().snapshot();
+        assert_eq!(metrics.len(), 2);
+        let account_info = &metrics[0];
+        assert_eq!(account_info.method, "getAccountInfo");
+        assert_eq!(account_info.successes, 1);
+        assert_eq!(account_info.failures, 1);
+        assert_eq!(account_info.retries, 1);
+        assert!(account_info.bytes_received > 0);
+        assert_eq!(account_info.latency.count, 2);
+        assert_eq!(account_info.latency.buckets.iter().sum::<u64>(), 2);
+        let token_supply = &metrics[1];
+        assert_eq!(token_supply.method, "getTokenSupply");
+        assert_eq!(token_supply.successes, 0);
+        assert_eq!(token_supply.failures, 1);
+        assert_eq!(token_supply.retries, 0);
+    }
+}
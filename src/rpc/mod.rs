@@ -1,21 +1,118 @@
-use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::solana_program::{clock::Clock, pubkey::Pubkey, sysvar};
 use base64::Engine;
 use serde::{de::DeserializeOwned, Deserialize, Serialize, Serializer};
 use std::str::FromStr;
-use std::time::{Duration, SystemTime};
+use std::sync::Arc;
+use std::time::Duration;
 
+mod builder;
+mod cache;
+mod cassette;
 mod client;
+mod das;
+mod endpoints;
 mod error;
+mod fixture;
+mod metrics;
+mod owners;
+mod pubsub;
+mod rate_limit;
+mod retry;
 mod rpc_call;
-
-pub use client::Client;
-pub use error::Error;
-use rpc_call::RpcCall;
+mod stream;
+/// Fixtures shared by the tests of the RPC client
+#[cfg(test)]
+mod test_support;
+mod transactions;
+mod transport;
+
+pub use builder::{
+    ClientBuilder, Timeouts, DEFAULT_CONNECT_TIMEOUT, DEFAULT_ENDPOINT, ENDPOINT_ENV,
+    ENDPOINT_WEIGHTS_ENV,
+};
+pub use cache::{AccountCache, CachePolicy};
+pub use cassette::{
+    CassetteMode, RecordingTransport, ReplayTransport, CASSETTE_ENV, CASSETTE_MODE_ENV,
+};
+pub use client::{Client, DEFAULT_TIMEOUT};
+pub use das::{
+    Asset, AssetList, Attribute, Authority, Compression, Content, ContentMetadata, File, Grouping,
+    Ownership, Page, SearchAssets, DAS_MAX_LIMIT,
+};
+pub use endpoints::{Endpoint, EndpointHealth, EndpointPool};
+pub use error::{Error, ErrorKind};
+pub use fixture::{FixtureAccount, FixtureTransport, Fixtures};
+pub use metrics::{Histogram, MethodMetrics, Metrics, LATENCY_BUCKETS_MS};
+pub use owners::{nft_holder, Holding, OwnerKind};
+pub use pubsub::{ws_endpoint_from_env, ws_url, PubsubClient, Subscription, WS_ENDPOINT_ENV};
+pub use rate_limit::{
+    MethodStats, RateLimiter, DEFAULT_MAX_IN_FLIGHT, DEFAULT_REQUESTS_PER_SECOND,
+    MAX_IN_FLIGHT_ENV, RATE_LIMIT_ENV,
+};
+pub use retry::RetryPolicy;
+pub use rpc_call::{Commitment, DataSlice, EncodingType, MethodClass};
+use rpc_call::{RpcCall, RpcConfig};
+pub use transactions::{
+    EncodedTransaction, InnerInstructions, ParsedAccountKey, ParsedInstruction, ParsedMessage,
+    ParsedTransaction, SignatureInfo, SignaturesQuery, TokenBalance, Transaction,
+    TransactionEncoding, TransactionMeta, UiTokenAmount, SIGNATURES_MAX_LIMIT,
+};
+pub use transport::{HttpTransport, Transport};
 
 pub type Result<T = ()> = std::result::Result<T, Error>;
 pub type Epoch = u64;
 
-#[derive(Debug)]
+/// Solana RPC nodes reject `getMultipleAccounts` calls for more keys
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// A sharded scan issues one `getProgramAccounts` call per value of the
+/// shard byte
+pub const SHARD_COUNT: usize = 256;
+
+/// Range of slots the data of one or more responses was read at. A single
+/// RPC call has `min == max`; results assembled from several calls may span
+/// a few slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotRange {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl SlotRange {
+    pub fn new(slot: u64) -> Self {
+        Self {
+            min: slot,
+            max: slot,
+        }
+    }
+
+    pub fn merge(self, other: SlotRange) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+}
+
+/// A result along with the slots it was read at
+#[derive(Debug, Clone)]
+pub struct WithContext<T> {
+    pub slots: SlotRange,
+    pub value: T,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContextResponse<T> {
+    context: Context,
+    value: T,
+}
+
+#[derive(Deserialize, Debug)]
+struct Context {
+    slot: u64,
+}
+
+#[derive(Debug, Clone)]
 pub struct Account {
     /// lamports in the account
     pub lamports: u64,
@@ -36,6 +133,14 @@ pub enum GetProgramAccountsFilter<'a> {
     Memcmp(Memcmp<'a>),
 }
 
+impl GetProgramAccountsFilter<'static> {
+    /// Matches the accounts of an Anchor account type by the discriminator
+    /// their data starts with
+    pub fn discriminator(discriminator: &'static [u8]) -> Self {
+        Self::Memcmp(Memcmp::new(0, discriminator))
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct Memcmp<'a> {
     /// Data offset to begin match
@@ -57,22 +162,15 @@ impl<'a> Memcmp<'a> {
 
 impl Client {
     pub async fn get_account(&self, pubkey: &Pubkey) -> Result<Account> {
-        #[derive(Deserialize, Debug)]
-        struct Response {
-            pub value: Option<ReceivedAccount>,
-        }
+        Ok(self.get_account_with_context(pubkey).await?.value)
+    }
 
-        let json = RpcCall::get_account_info(pubkey);
-        let account_response: Response = self.post(&json).await?;
-        let account = account_response.value.ok_or(Error::AccountNotFound)?;
-        Ok(Account {
-            lamports: account.lamports,
-            owner: account.owner,
-            data: base64::engine::general_purpose::STANDARD
-                .decode(&account.data[0])
-                .map_err(Error::B64Decode)?,
-            executable: account.executable,
-            rent_epoch: account.rent_epoch,
+    pub async fn get_account_with_context(&self, pubkey: &Pubkey) -> Result<WithContext<Account>> {
+        let json = RpcCall::get_account_info(pubkey, self.config());
+        let response: ContextResponse<Option<ReceivedAccount>> = self.post(&json).await?;
+        Ok(WithContext {
+            slots: SlotRange::new(response.context.slot),
+            value: response.value.ok_or(Error::AccountNotFound)?.try_into()?,
         })
     }
 
@@ -81,42 +179,119 @@ impl Client {
         Ok(account.data)
     }
 
-    pub async fn get_multiple_accounts_data(&self, pubkeys: &[&Pubkey]) -> Result<Vec<Vec<u8>>> {
-        #[derive(Deserialize, Debug)]
-        pub struct Response {
-            pub value: Vec<Value>,
+    /// Fetches any number of accounts, in the order of `pubkeys`. The keys
+    /// are split into chunks of `MAX_MULTIPLE_ACCOUNTS`, which are fetched
+    /// concurrently under the rate limiter. Accounts that do not exist are
+    /// `None`.
+    pub async fn get_multiple_accounts(&self, pubkeys: &[&Pubkey]) -> Result<Vec<Option<Account>>> {
+        Ok(self
+            .get_multiple_accounts_with_context(pubkeys)
+            .await?
+            .value)
+    }
+
+    /// Like `get_multiple_accounts`, along with the range of slots the
+    /// chunks were read at
+    pub async fn get_multiple_accounts_with_context(
+        &self,
+        pubkeys: &[&Pubkey],
+    ) -> Result<WithContext<Vec<Option<Account>>>> {
+        let chunks = pubkeys
+            .chunks(MAX_MULTIPLE_ACCOUNTS)
+            .map(|chunk| self.get_multiple_accounts_chunk(chunk));
+        let chunks = futures::future::try_join_all(chunks).await?;
+        let mut slots: Option<SlotRange> = None;
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in chunks {
+            slots = Some(match slots {
+                Some(slots) => slots.merge(chunk.slots),
+                None => chunk.slots,
+            });
+            accounts.extend(chunk.value);
         }
-        #[derive(Deserialize, Debug)]
-        pub struct Value {
-            pub data: Vec<String>,
+        Ok(WithContext {
+            // nothing was read when there are no keys
+            slots: slots.unwrap_or(SlotRange::new(self.min_context_slot().unwrap_or_default())),
+            value: accounts,
+        })
+    }
+
+    async fn get_multiple_accounts_chunk(
+        &self,
+        pubkeys: &[&Pubkey],
+    ) -> Result<WithContext<Vec<Option<Account>>>> {
+        let json = RpcCall::get_multiple_accounts(pubkeys, self.config());
+        let response: ContextResponse<Vec<Option<ReceivedAccount>>> = self.post(&json).await?;
+        if response.value.len() != pubkeys.len() {
+            return Err(Error::Custom(
+                "getMultipleAccounts returned a different number of accounts",
+            ));
         }
+        Ok(WithContext {
+            slots: SlotRange::new(response.context.slot),
+            value: response
+                .value
+                .into_iter()
+                .map(|account| account.map(Account::try_from).transpose())
+                .collect::<Result<_>>()?,
+        })
+    }
 
-        let json = RpcCall::get_multiple_accounts(pubkeys);
-        let account_response: Response = self.post(&json).await?;
-        account_response
-            .value
+    /// Like `get_multiple_accounts`, keeping only the account data
+    pub async fn get_multiple_accounts_data(
+        &self,
+        pubkeys: &[&Pubkey],
+    ) -> Result<Vec<Option<Vec<u8>>>> {
+        Ok(self
+            .get_multiple_accounts(pubkeys)
+            .await?
             .into_iter()
-            .map(|v| {
-                base64::engine::general_purpose::STANDARD
-                    .decode(&v.data[0])
-                    .map_err(Error::B64Decode)
-            })
-            .collect()
+            .map(|account| account.map(|a| a.data))
+            .collect())
     }
 
     async fn get_token_largest_account(&self, pubkey: &Pubkey) -> Result<Pubkey> {
-        #[derive(Deserialize, Debug)]
-        struct Response {
-            pub value: Vec<Value>,
-        }
-        #[derive(Deserialize, Debug)]
-        struct Value {
-            pub address: String,
-        }
+        let json = RpcCall::get_token_largest_accounts(pubkey, self.config());
+        let response: TokenLargestAccounts = self.post(&json).await?;
+        response.largest()
+    }
 
-        let json = RpcCall::get_token_largest_accounts(pubkey);
-        let response: Response = self.post(&json).await?;
-        Ok(Pubkey::from_str(&response.value[0].address)?)
+    /// Fetches the largest token account of every mint using JSON-RPC batch
+    /// requests. Mints whose individual call failed with a retriable error are
+    /// retried in a new batch, following the client's retry policy. A mint
+    /// whose call fails for good fails on its own.
+    pub async fn get_token_largest_accounts(
+        &self,
+        mints: &[&Pubkey],
+    ) -> Result<Vec<Result<Pubkey>>> {
+        let mut token_accounts: Vec<Option<Result<Pubkey>>> = mints.iter().map(|_| None).collect();
+        let mut retries = 0;
+        loop {
+            let pending: Vec<usize> = (0..mints.len())
+                .filter(|i| token_accounts[*i].is_none())
+                .collect();
+            let calls: Vec<RpcCall> = pending
+                .iter()
+                .map(|i| RpcCall::get_token_largest_accounts(mints[*i], self.config()))
+                .collect();
+            let responses: Vec<Result<TokenLargestAccounts>> = self.post_batch(&calls).await?;
+
+            let last_attempt = retries >= self.retry_policy().max_retries;
+            for (i, response) in pending.into_iter().zip(responses) {
+                match response.and_then(|r| r.largest()) {
+                    Err(e) if e.is_retriable() && !last_attempt => (),
+                    result => token_accounts[i] = Some(result),
+                }
+            }
+            if token_accounts.iter().all(Option::is_some) {
+                return Ok(token_accounts.into_iter().flatten().collect());
+            }
+            retries += 1;
+            for _ in token_accounts.iter().filter(|a| a.is_none()) {
+                self.metrics().record_retry("getTokenLargestAccounts");
+            }
+            tokio::time::sleep(self.retry_policy().delay(retries)).await;
+        }
     }
 
     pub async fn get_token_supply_amount(&self, pubkey: &Pubkey) -> Result<u128> {
@@ -129,120 +304,105 @@ impl Client {
             pub amount: String,
         }
 
-        let json = RpcCall::get_token_supply(pubkey);
+        let json = RpcCall::get_token_supply(pubkey, self.config());
         let response: Response = self.post(&json).await?;
         Ok(response.value.amount.parse::<u128>()?)
     }
 
-    pub async fn get_assets_by_authority(&self, authority: &Pubkey) -> Result<Option<Pubkey>> {
-        #[derive(Deserialize, Debug)]
-        pub struct AssetsByAuthorityResponse {
-            pub items: Vec<Item>,
-        }
-
-        #[derive(Deserialize, Debug)]
-        pub struct Item {
-            pub id: String,
+    /// Reads the Clock sysvar. Its `unix_timestamp` is the time on-chain
+    /// programs see when executing at the slot it was read at.
+    pub async fn get_clock(&self) -> Result<WithContext<Clock>> {
+        let account = self.get_account_with_context(&sysvar::clock::ID).await?;
+        let data = account.value.data;
+        if data.len() < 40 {
+            return Err(Error::Custom("clock sysvar account is too short"));
         }
-        let json = RpcCall::get_assets_by_authority(authority);
-        let asset_response: AssetsByAuthorityResponse = self.post(&json).await?;
-        Ok(if asset_response.items.is_empty() {
-            None
-        } else {
-            Some(Pubkey::from_str(&asset_response.items[0].id)?)
+        let field = |i: usize| -> Result<[u8; 8]> { Ok(data[i * 8..(i + 1) * 8].try_into()?) };
+        Ok(WithContext {
+            slots: account.slots,
+            value: Clock {
+                slot: u64::from_le_bytes(field(0)?),
+                epoch_start_timestamp: i64::from_le_bytes(field(1)?),
+                epoch: u64::from_le_bytes(field(2)?),
+                leader_schedule_epoch: u64::from_le_bytes(field(3)?),
+                unix_timestamp: i64::from_le_bytes(field(4)?),
+            },
         })
     }
 
+    /// A position is the authority of exactly one asset, its NFT
     #[allow(unused)]
     pub async fn get_position_owner(&self, position_id: &Pubkey) -> Result<Pubkey> {
-        if let Some(mint) = self.get_assets_by_authority(position_id).await? {
-            self.get_owner_by_mint(&mint).await
-        } else {
-            Err(Error::NoAssetByAuthority(position_id.to_string()))
+        let assets = self
+            .get_assets_by_authority_page(position_id, Page::new(1, 1))
+            .await?;
+        match assets.items.first() {
+            Some(asset) => self.get_owner_by_mint(&asset.id).await,
+            None => Err(Error::NoAssetByAuthority(position_id.to_string())),
         }
     }
 
-    #[allow(unused)]
+    /// The token account holding an NFT, which is the largest token account
+    /// of its mint, and its owner
+    pub async fn get_holding_by_mint(&self, mint: &Pubkey) -> Result<Holding> {
+        let token_account = self.get_token_largest_account(mint).await?;
+        let account_data = self.get_account_data(&token_account).await?;
+        Ok(Holding {
+            token_account,
+            owner: nft_holder(&token_account, mint, &account_data)?,
+        })
+    }
+
+    /// The owner of the token account holding an NFT
     pub async fn get_owner_by_mint(&self, mint: &Pubkey) -> Result<Pubkey> {
-        let token_largest_accounts = self.get_token_largest_account(mint).await?;
-        let account_data = self.get_account_data(&token_largest_accounts).await?;
-        Ok(Pubkey::try_from(&account_data[32..64])?)
+        Ok(self.get_holding_by_mint(mint).await?.owner)
     }
 
-    pub async fn get_all_owners_by_mint(
+    /// The holdings of many NFTs, resolved `chunk_size` mints at a time. An
+    /// NFT whose holding cannot be resolved fails on its own.
+    pub async fn get_all_holdings_by_mint(
         &self,
-        position_id: &[&Pubkey],
+        mints: &[&Pubkey],
         chunk_size: usize,
-    ) -> Result<Vec<Pubkey>> {
-        use futures::future::join_all;
+    ) -> Result<Vec<Result<Holding>>> {
         use std::time::Instant;
-        const ONE_SEC: Duration = Duration::from_secs(1);
-        let mut owners = Vec::with_capacity(position_id.len());
+        let mut holdings = Vec::with_capacity(mints.len());
 
-        println!("Fetching owners of {} positions", position_id.len());
+        println!("Fetching owners of {} positions", mints.len());
         let start = Instant::now();
         let mut last_output = start;
 
-        let mut reference = Instant::now();
-        let mut num_requests = 0;
-
-        for i in position_id.chunks(chunk_size) {
-            let mut futures = Vec::with_capacity(chunk_size);
-
-            if num_requests > 150 && reference.elapsed() < ONE_SEC {
-                tokio::time::sleep(Duration::from_secs(1) - reference.elapsed()).await;
-            }
-
-            if reference.elapsed() >= ONE_SEC {
-                reference = Instant::now();
-                num_requests = 0;
+        for chunk in mints.chunks(chunk_size) {
+            // one batch resolves the token accounts of the whole chunk
+            let token_accounts = self.get_token_largest_accounts(chunk).await?;
+            let found: Vec<&Pubkey> = token_accounts
+                .iter()
+                .filter_map(|token_account| token_account.as_ref().ok())
+                .collect();
+            let mut account_data = self.get_multiple_accounts_data(&found).await?.into_iter();
+            for (token_account, mint) in token_accounts.into_iter().zip(chunk) {
+                holdings.push(token_account.and_then(|token_account| {
+                    let data = account_data
+                        .next()
+                        .flatten()
+                        .ok_or(Error::AccountNotFound)?;
+                    Ok(Holding {
+                        token_account,
+                        owner: nft_holder(&token_account, mint, &data)?,
+                    })
+                }));
             }
-            let time_left = ONE_SEC - reference.elapsed();
-
-            for j in i {
-                futures.push(async move {
-                    let mut retries = 0;
-                    let mut result = self.get_token_largest_account(j).await;
-                    while result.is_err() {
-                        if retries > 3 {
-                            break;
-                        }
-                        retries += 1;
-                        if retries == 1 {
-                            tokio::time::sleep(time_left).await;
-                        } else {
-                            tokio::time::sleep(ONE_SEC).await;
-                        }
-                        result = self.get_token_largest_account(j).await;
-                    }
-                    result
-                });
-            }
-            num_requests += chunk_size;
-
-            let token_accounts: Vec<Result<Pubkey>> = join_all(futures).await;
-            let token_accounts: Vec<Pubkey> = token_accounts
-                .into_iter()
-                .collect::<Result<Vec<Pubkey>>>()?;
-            let account_data = self
-                .get_multiple_accounts_data(&token_accounts.iter().collect::<Vec<&Pubkey>>())
-                .await?;
-            let these_owners = account_data
-                .into_iter()
-                .map(|v| Pubkey::try_from(&v[32..64]).map_err(Error::TryFromSlice))
-                .collect::<Result<Vec<Pubkey>>>()?;
-            owners.extend(&these_owners);
-            if last_output.elapsed().as_secs() > 5 || owners.len() == position_id.len() {
+            if last_output.elapsed().as_secs() > 5 || holdings.len() == mints.len() {
                 last_output = Instant::now();
                 println!(
                     "Completed {:>#5}/{} positions in {:>#3} seconds. ",
-                    owners.len(),
-                    position_id.len(),
+                    holdings.len(),
+                    mints.len(),
                     start.elapsed().as_secs()
                 );
             }
         }
-        Ok(owners)
+        Ok(holdings)
     }
 
     #[allow(unused)]
@@ -250,33 +410,64 @@ impl Client {
         &self,
         program_id: &Pubkey,
         filters: Vec<GetProgramAccountsFilter<'a>>,
+        data_slice: Option<DataSlice>,
     ) -> Result<Vec<(Pubkey, Account)>> {
-        #[derive(Deserialize, Debug)]
-        struct ReceivedAccountsAndPubkeys {
-            account: ReceivedAccount,
-            #[serde(deserialize_with = "deserialize_pubkey")]
-            pubkey: Pubkey,
-        }
+        Ok(self
+            .get_program_accounts_with_context(program_id, filters, data_slice)
+            .await?
+            .value)
+    }
 
-        let json = RpcCall::get_program_accounts_with_filters(program_id, filters);
-        let account_response: Vec<ReceivedAccountsAndPubkeys> = self.post(&json).await?;
-        account_response
-            .into_iter()
-            .map(|r| {
-                Ok((
-                    (r.pubkey),
-                    Account {
-                        lamports: r.account.lamports,
-                        owner: r.account.owner,
-                        data: base64::engine::general_purpose::STANDARD
-                            .decode(&r.account.data[0])
-                            .map_err(Error::B64Decode)?,
-                        executable: r.account.executable,
-                        rent_epoch: r.account.rent_epoch,
-                    },
-                ))
-            })
-            .collect::<Result<Vec<(Pubkey, Account)>>>()
+    pub async fn get_program_accounts_with_context<'a>(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<GetProgramAccountsFilter<'a>>,
+        data_slice: Option<DataSlice>,
+    ) -> Result<WithContext<Vec<(Pubkey, Account)>>> {
+        self.get_program_accounts_decoded(program_id, filters, data_slice, |pubkey, account| {
+            Ok(Some((pubkey, account)))
+        })
+        .await
+    }
+
+    /// Scans a program in `SHARD_COUNT` shards, one for each value of the
+    /// byte at `shard_offset`, for when a single `getProgramAccounts` call
+    /// would be too large. Every shard applies `filters` and `data_slice`;
+    /// shards are fetched concurrently under the rate limiter. The offset
+    /// should point at a byte that is evenly distributed across accounts,
+    /// such as one within a pubkey that differs for every account.
+    pub async fn get_program_accounts_sharded<'a>(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<GetProgramAccountsFilter<'a>>,
+        shard_offset: usize,
+        data_slice: Option<DataSlice>,
+    ) -> Result<WithContext<Vec<(Pubkey, Account)>>> {
+        self.get_program_accounts_sharded_decoded(
+            program_id,
+            filters,
+            shard_offset,
+            data_slice,
+            |pubkey, account| Ok(Some((pubkey, account))),
+        )
+        .await
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenLargestAccounts {
+    value: Vec<TokenAccountBalance>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenAccountBalance {
+    address: String,
+}
+
+impl TokenLargestAccounts {
+    fn largest(&self) -> Result<Pubkey> {
+        let largest = self.value.first().ok_or(Error::AccountNotFound)?;
+        Ok(Pubkey::from_str(&largest.address)?)
     }
 }
 
@@ -291,6 +482,27 @@ struct ReceivedAccount {
     pub rent_epoch: Epoch,
 }
 
+impl TryFrom<ReceivedAccount> for Account {
+    type Error = Error;
+
+    fn try_from(account: ReceivedAccount) -> Result<Self> {
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(&account.data[0])
+            .map_err(Error::B64Decode)?;
+        let data = match account.data.get(1).map(String::as_str) {
+            Some("base64+zstd") => zstd::decode_all(data.as_slice()).map_err(Error::Zstd)?,
+            _ => data,
+        };
+        Ok(Account {
+            lamports: account.lamports,
+            owner: account.owner,
+            data,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        })
+    }
+}
+
 fn deserialize_pubkey<'de, D>(deserializer: D) -> std::result::Result<Pubkey, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -299,31 +511,34 @@ where
     Pubkey::from_str(&str).map_err(serde::de::Error::custom)
 }
 
+fn serialize_pubkey<S: Serializer>(pubkey: &Pubkey, s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_str(&pubkey.to_string())
+}
+
 #[cfg(test)]
 mod test {
+    use super::test_support::*;
     use super::*;
-    use std::str::FromStr;
     use tokio::test;
 
     #[test]
     async fn test_get_assets_by_authority() {
-        let client = Client::default();
-        let asset_by_authority = client
+        let client = ClientBuilder::from_env().unwrap().build().unwrap();
+        let assets_by_authority = client
             .get_assets_by_authority(
                 &Pubkey::from_str("EvXrmwTJaqXvAL5skuyiWRV1X7MPwmZYX8Qp3DCw83RT").unwrap(),
             )
             .await
-            .unwrap()
             .unwrap();
         assert_eq!(
-            asset_by_authority,
+            assets_by_authority[0].id,
             Pubkey::from_str("BQW1ABLREJtw8hA3WqpvfvUZppVWaG127hAoVsNnmNKS").unwrap()
         )
     }
 
     #[test]
     async fn test_get_token_largest_accounts() {
-        let client = Client::default();
+        let client = ClientBuilder::from_env().unwrap().build().unwrap();
         let token_largest_accounts = client
             .get_token_largest_account(
                 &Pubkey::from_str("BLt9DipvNYvCddekZn4MuvDwxziuda6wR2Buk3hAbJwF").unwrap(),
@@ -338,7 +553,7 @@ mod test {
 
     #[test]
     async fn test_get_account_data() {
-        let client = Client::default();
+        let client = ClientBuilder::from_env().unwrap().build().unwrap();
         let data = client
             .get_account_data(
                 &Pubkey::from_str("CfGsBm5shwwb5tVpFjdj8zPbYCxJ3LhwNxBSCX7WFCCJ").unwrap(),
@@ -354,7 +569,7 @@ mod test {
 
     #[test]
     async fn test_get_multiple_accounts() {
-        let client = Client::default();
+        let client = ClientBuilder::from_env().unwrap().build().unwrap();
         let data = client
             .get_multiple_accounts_data(&[
                 &Pubkey::from_str("CfGsBm5shwwb5tVpFjdj8zPbYCxJ3LhwNxBSCX7WFCCJ").unwrap(),
@@ -362,6 +577,7 @@ mod test {
             ])
             .await
             .unwrap();
+        let data: Vec<Vec<u8>> = data.into_iter().map(Option::unwrap).collect();
         let pubkey = Pubkey::try_from(&data[0][32..64]).unwrap();
         assert_eq!(
             pubkey,
@@ -376,7 +592,7 @@ mod test {
 
     #[test]
     async fn test_get_position_owner() {
-        let client = Client::default();
+        let client = ClientBuilder::from_env().unwrap().build().unwrap();
         let pubkey = client
             .get_position_owner(
                 &Pubkey::from_str("E6ELFUZMahhCgsPCeEYXtQT51Yq24Yg4WAhmQJBsGxhg").unwrap(),
@@ -390,10 +606,10 @@ mod test {
     }
 
     #[test]
-    async fn test_get_all_owners_by_mint() {
-        let client = Client::default();
-        let pubkeys = client
-            .get_all_owners_by_mint(
+    async fn test_get_all_holdings_by_mint() {
+        let client = ClientBuilder::from_env().unwrap().build().unwrap();
+        let holdings = client
+            .get_all_holdings_by_mint(
                 &vec![
                     &Pubkey::from_str("BLt9DipvNYvCddekZn4MuvDwxziuda6wR2Buk3hAbJwF").unwrap(),
                     &Pubkey::from_str("CWyChY7verwn1w2ZgzaRGTqVUxSeKbBqTPgiJJFjJ9LH").unwrap(),
@@ -403,12 +619,251 @@ mod test {
             .await
             .unwrap();
         assert_eq!(
-            pubkeys[0],
+            holdings[0].as_ref().unwrap().owner,
             Pubkey::from_str("ADqp77vvKapHsU2ymsaoMojXpHjdhxLcfrgWWtaxYCVU").unwrap()
         );
         assert_eq!(
-            pubkeys[1],
+            holdings[1].as_ref().unwrap().owner,
             Pubkey::from_str("BKw4D8sv6Wt67LmUqVN1gLpe2XUDicifdrSBuGcYvPz2").unwrap()
         );
     }
+
+    #[test]
+    async fn test_fixture_get_account() {
+        let pubkey = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let client = Client::new_with_transport(
+            FixtureTransport::new().with_account(pubkey, &fixture_account(owner, vec![1, 2, 3])),
+        );
+        let account = client.get_account(&pubkey).await.unwrap();
+        assert_eq!(account.owner, owner);
+        assert_eq!(account.data, vec![1, 2, 3]);
+        assert!(matches!(
+            client.get_account(&Pubkey::new_unique()).await,
+            Err(Error::AccountNotFound)
+        ));
+    }
+
+    #[test]
+    async fn test_fixture_get_program_accounts_with_filter() {
+        let program_id = Pubkey::new_unique();
+        let matching = Pubkey::new_unique();
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_account(matching, &fixture_account(program_id, vec![7, 7, 1]))
+                .with_account(
+                    Pubkey::new_unique(),
+                    &fixture_account(program_id, vec![7, 8, 1]),
+                )
+                .with_account(
+                    Pubkey::new_unique(),
+                    &fixture_account(Pubkey::new_unique(), vec![7, 7, 1]),
+                ),
+        );
+        let accounts = client
+            .get_program_accounts_with_filter(
+                &program_id,
+                vec![
+                    GetProgramAccountsFilter::DataSize(3),
+                    GetProgramAccountsFilter::Memcmp(Memcmp::new(0, &[7, 7])),
+                ],
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].0, matching);
+        assert_eq!(accounts[0].1.data, vec![7, 7, 1]);
+    }
+
+    #[test]
+    async fn test_fixture_base64_zstd() {
+        let program_id = Pubkey::new_unique();
+        let pubkey = Pubkey::new_unique();
+        let data: Vec<u8> = (0..200).map(|i| (i % 4) as u8).collect();
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_account(pubkey, &fixture_account(program_id, data.clone())),
+        )
+        .with_encoding(EncodingType::Base64Zstd);
+
+        assert_eq!(client.get_account(&pubkey).await.unwrap().data, data);
+        assert_eq!(
+            client.get_multiple_accounts_data(&[&pubkey]).await.unwrap(),
+            vec![Some(data.clone())]
+        );
+        let accounts = client
+            .get_program_accounts_with_filter(&program_id, vec![], Some(DataSlice::new(4, 8)))
+            .await
+            .unwrap();
+        assert_eq!(accounts[0].1.data, data[4..12]);
+    }
+
+    #[test]
+    async fn test_fixture_get_all_holdings_by_mint() {
+        let mints = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let token_accounts = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let owners = [Pubkey::new_unique(), Pubkey::new_unique()];
+        // the third NFT has no token account
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_token_account(mints[0], token_accounts[0], owners[0])
+                .with_token_account(mints[1], token_accounts[1], owners[1]),
+        );
+        let holdings = client
+            .get_all_holdings_by_mint(&[&mints[0], &mints[2], &mints[1]], 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            holdings[0].as_ref().unwrap(),
+            &Holding {
+                token_account: token_accounts[0],
+                owner: owners[0],
+            }
+        );
+        assert_eq!(
+            holdings[1].as_ref().unwrap_err().kind(),
+            ErrorKind::AccountNotFound
+        );
+        assert_eq!(holdings[2].as_ref().unwrap().owner, owners[1]);
+        assert_eq!(
+            client.get_owner_by_mint(&mints[1]).await.unwrap(),
+            owners[1]
+        );
+    }
+
+    #[test]
+    async fn test_fixture_get_token_largest_accounts_batch() {
+        let mints = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let token_accounts = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_token_account(mints[0], token_accounts[0], Pubkey::new_unique())
+                .with_token_account(mints[1], token_accounts[1], Pubkey::new_unique()),
+        );
+        let calls = [
+            RpcCall::get_token_largest_accounts(&mints[1], RpcConfig::default()),
+            RpcCall::get_token_supply(&mints[0], RpcConfig::default()),
+            RpcCall::get_token_largest_accounts(&mints[0], RpcConfig::default()),
+        ];
+        let results: Vec<Result<TokenLargestAccounts>> = client.post_batch(&calls).await.unwrap();
+        assert_eq!(
+            results[0].as_ref().unwrap().largest().unwrap(),
+            token_accounts[1]
+        );
+        assert!(matches!(results[1], Err(Error::NodeError { .. })));
+        assert_eq!(
+            results[2].as_ref().unwrap().largest().unwrap(),
+            token_accounts[0]
+        );
+
+        let largest = client
+            .get_token_largest_accounts(&[&mints[0], &Pubkey::new_unique(), &mints[1]])
+            .await
+            .unwrap();
+        assert_eq!(largest[0].as_ref().unwrap(), &token_accounts[0]);
+        // a mint without token accounts fails on its own
+        assert!(largest[1].is_err());
+        assert_eq!(largest[2].as_ref().unwrap(), &token_accounts[1]);
+    }
+
+    #[test]
+    async fn test_fixture_get_multiple_accounts_chunked() {
+        let pubkeys: Vec<Pubkey> = (0..250).map(|_| Pubkey::new_unique()).collect();
+        let mut fixture = FixtureTransport::new();
+        // every third account does not exist
+        for (i, pubkey) in pubkeys.iter().enumerate().filter(|(i, _)| i % 3 != 0) {
+            fixture = fixture.with_account(
+                *pubkey,
+                &fixture_account(Pubkey::new_unique(), vec![i as u8]),
+            );
+        }
+        let client = Client::new_with_transport(fixture);
+        let data = client
+            .get_multiple_accounts_data(&pubkeys.iter().collect::<Vec<&Pubkey>>())
+            .await
+            .unwrap();
+        assert_eq!(data.len(), pubkeys.len());
+        for (i, data) in data.into_iter().enumerate() {
+            match i % 3 {
+                0 => assert!(data.is_none()),
+                _ => assert_eq!(data.unwrap(), vec![i as u8]),
+            }
+        }
+        assert_eq!(client.metrics().snapshot()[0].successes, 3);
+    }
+
+    #[test]
+    async fn test_fixture_slot_context() {
+        let program_id = Pubkey::new_unique();
+        let pubkey = Pubkey::new_unique();
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_slot(50)
+                .with_account(pubkey, &fixture_account(program_id, vec![1])),
+        )
+        .with_commitment(Commitment::Confirmed)
+        .with_retry_policy(RetryPolicy::none());
+
+        let accounts = client
+            .get_program_accounts_with_context(&program_id, vec![], None)
+            .await
+            .unwrap();
+        assert_eq!(accounts.slots, SlotRange::new(50));
+        assert_eq!(accounts.value[0].0, pubkey);
+
+        let pinned = client.clone().with_min_context_slot(accounts.slots.max);
+        let account = pinned.get_account_with_context(&pubkey).await.unwrap();
+        assert_eq!(account.slots, SlotRange::new(50));
+
+        // the node has not caught up with the requested slot
+        let ahead = client.clone().with_min_context_slot(51);
+        let err = ahead.get_multiple_accounts(&[&pubkey]).await.unwrap_err();
+        assert!(matches!(err, Error::NodeError { code: -32016, .. }));
+        assert!(err.is_retriable());
+
+        let json =
+            serde_json::to_value(RpcCall::get_account_info(&pubkey, ahead.config())).unwrap();
+        assert_eq!(json["params"][1]["commitment"], "confirmed");
+        assert_eq!(json["params"][1]["minContextSlot"], 51);
+    }
+
+    #[test]
+    async fn test_fixture_get_clock() {
+        let mut data = Vec::new();
+        for field in [1234u64, 1_700_000_000, 500, 501, 1_700_000_600] {
+            data.extend(field.to_le_bytes());
+        }
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_slot(1234)
+                .with_account(sysvar::clock::ID, &fixture_account(sysvar::ID, data)),
+        );
+        let clock = client.get_clock().await.unwrap();
+        assert_eq!(clock.slots, SlotRange::new(1234));
+        assert_eq!(clock.value.slot, 1234);
+        assert_eq!(clock.value.epoch, 500);
+        assert_eq!(clock.value.unix_timestamp, 1_700_000_600);
+    }
+
+    #[test]
+    async fn test_fixture_get_position_owner() {
+        let position = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_asset(das_asset(mint, owner, position, "positions"))
+                .with_token_account(mint, Pubkey::new_unique(), owner),
+        );
+        assert_eq!(client.get_position_owner(&position).await.unwrap(), owner);
+        assert!(matches!(
+            client.get_position_owner(&Pubkey::new_unique()).await,
+            Err(Error::NoAssetByAuthority(_))
+        ));
+    }
 }
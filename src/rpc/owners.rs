@@ -0,0 +1,166 @@
+use super::*;
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    solana_program::program_pack::Pack,
+    state::{Account as TokenAccount, Multisig},
+};
+
+const SPL_TOKEN_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const SQUADS_V3_ID: &str = "SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu";
+const SQUADS_V4_ID: &str = "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf";
+
+/// Owners rarely change what they are, so their accounts are cached for
+/// long
+const OWNER_ACCOUNT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What holds a position NFT
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnerKind {
+    /// Not resolved, such as when the owner's account could not be read
+    #[default]
+    Unknown,
+    /// An address with a private key
+    Wallet,
+    /// A program derived address, or an account with data owned by a
+    /// program, such as an escrow
+    Program,
+    /// An SPL token multisig or a Squads multisig account
+    Multisig,
+}
+
+/// The token account holding an NFT and the owner of that account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Holding {
+    pub token_account: Pubkey,
+    pub owner: Pubkey,
+}
+
+/// The owner of a token account holding an NFT. SPL token and token-2022
+/// accounts are decoded along with their extensions, and the account must
+/// hold exactly one token of `mint`.
+pub fn nft_holder(token_account: &Pubkey, mint: &Pubkey, data: &[u8]) -> Result<Pubkey> {
+    let account = StateWithExtensions::<TokenAccount>::unpack(data)
+        .map_err(|e| Error::AccountDecode(*token_account, e.to_string()))?;
+    if account.base.mint != *mint || account.base.amount != 1 {
+        return Err(Error::NftNotHeld(*token_account));
+    }
+    Ok(account.base.owner)
+}
+
+impl Client {
+    /// Whether each owner is a wallet, a multisig or a program. Squads vaults
+    /// are program derived addresses without data of their own, so only the
+    /// Squads multisig accounts themselves are told apart from other
+    /// programs.
+    pub async fn get_owner_kinds(&self, owners: &[&Pubkey]) -> Result<Vec<OwnerKind>> {
+        let accounts = self
+            .get_multiple_accounts_cached(owners, Some(CachePolicy::Ttl(OWNER_ACCOUNT_TTL)))
+            .await?
+            .value;
+        let token_programs = [spl_token_2022::id(), Pubkey::from_str(SPL_TOKEN_ID)?];
+        let multisig_programs = [
+            Pubkey::from_str(SQUADS_V3_ID)?,
+            Pubkey::from_str(SQUADS_V4_ID)?,
+        ];
+        Ok(owners
+            .iter()
+            .zip(accounts)
+            .map(|(owner, account)| match account {
+                Some(account)
+                    if token_programs.contains(&account.owner)
+                        && account.data.len() == Multisig::LEN =>
+                {
+                    OwnerKind::Multisig
+                }
+                Some(account) if multisig_programs.contains(&account.owner) => OwnerKind::Multisig,
+                // wallets hold no data
+                Some(account) if !account.data.is_empty() => OwnerKind::Program,
+                _ if !owner.is_on_curve() => OwnerKind::Program,
+                _ => OwnerKind::Wallet,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::test_support::fixture_account;
+    use tokio::test;
+
+    #[test]
+    async fn test_nft_holder() {
+        use spl_token_2022::{
+            solana_program::program_pack::Pack,
+            state::{Account as TokenAccount, AccountState},
+        };
+        let (token_account, mint, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut state = TokenAccount {
+            mint,
+            owner,
+            amount: 1,
+            state: AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; TokenAccount::LEN];
+        state.pack_into_slice(&mut data);
+        assert_eq!(nft_holder(&token_account, &mint, &data).unwrap(), owner);
+        assert!(matches!(
+            nft_holder(&token_account, &Pubkey::new_unique(), &data),
+            Err(Error::NftNotHeld(_))
+        ));
+
+        state.amount = 0;
+        state.pack_into_slice(&mut data);
+        let err = nft_holder(&token_account, &mint, &data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AccountNotFound);
+        assert!(matches!(
+            nft_holder(&token_account, &mint, &data[..64]),
+            Err(Error::AccountDecode(..))
+        ));
+    }
+
+    #[test]
+    async fn test_owner_kinds() {
+        // the ed25519 base point, an address a private key can sign for
+        let mut point = [0x66; 32];
+        point[0] = 0x58;
+        let wallet = Pubkey::new_from_array(point);
+        let (vault, _) = Pubkey::find_program_address(&[b"vault"], &Pubkey::new_unique());
+        let (escrow, multisig, squads) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_account(escrow, &fixture_account(Pubkey::new_unique(), vec![1; 8]))
+                .with_account(
+                    multisig,
+                    &fixture_account(spl_token_2022::id(), vec![0; Multisig::LEN]),
+                )
+                .with_account(
+                    squads,
+                    &fixture_account(Pubkey::from_str(SQUADS_V4_ID).unwrap(), vec![1; 8]),
+                ),
+        );
+        assert_eq!(
+            client
+                .get_owner_kinds(&[&wallet, &vault, &escrow, &multisig, &squads])
+                .await
+                .unwrap(),
+            [
+                OwnerKind::Wallet,
+                OwnerKind::Program,
+                OwnerKind::Program,
+                OwnerKind::Multisig,
+                OwnerKind::Multisig,
+            ]
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use super::*;
+use futures::{SinkExt, StreamExt};
+use rpc_call::SubscribeCall;
+use serde_json::Value;
+use std::collections::HashMap;
+use stream::ReceivedAccountsAndPubkeys;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+pub const WS_ENDPOINT_ENV: &str = "SOL_RPC_WS_ENDPOINT";
+
+/// The websocket URL of an HTTP endpoint, which nodes serve on the same host
+pub fn ws_url(http_url: &str) -> String {
+    if let Some(rest) = http_url.strip_prefix("https://") {
+        format!("wss://{rest}")
+    } else if let Some(rest) = http_url.strip_prefix("http://") {
+        format!("ws://{rest}")
+    } else {
+        http_url.to_string()
+    }
+}
+
+/// `SOL_RPC_WS_ENDPOINT`, or else the websocket URL of the first endpoint of
+/// `SOL_RPC_ENDPOINT` or of the default endpoint
+pub fn ws_endpoint_from_env() -> String {
+    if let Ok(url) = std::env::var(WS_ENDPOINT_ENV) {
+        return url;
+    }
+    match std::env::var(ENDPOINT_ENV) {
+        Ok(urls) => ws_url(urls.split(',').next().unwrap_or(DEFAULT_ENDPOINT).trim()),
+        Err(_) => ws_url(DEFAULT_ENDPOINT),
+    }
+}
+
+type SubscribeReply = oneshot::Sender<Result<(u64, mpsc::UnboundedReceiver<Value>)>>;
+
+enum Command {
+    Subscribe {
+        request: String,
+        id: String,
+        reply: SubscribeReply,
+    },
+    Unsubscribe {
+        request: String,
+    },
+}
+
+/// A connection to a node's websocket API. Each subscription receives its
+/// notifications until it is dropped or the connection closes, after which
+/// the caller reconnects and catches up on what it missed, such as with a
+/// scan.
+#[derive(Clone, Debug)]
+pub struct PubsubClient {
+    commands: mpsc::UnboundedSender<Command>,
+    config: RpcConfig,
+}
+
+/// Notifications of a subscription
+pub struct Subscription<T> {
+    id: u64,
+    notifications: mpsc::UnboundedReceiver<Value>,
+    commands: mpsc::UnboundedSender<Command>,
+    unsubscribe: String,
+    parse: fn(&Value) -> Result<T>,
+}
+
+impl<T> Subscription<T> {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The next notification, or `None` once the connection has closed
+    pub async fn next(&mut self) -> Option<Result<T>> {
+        let notification = self.notifications.recv().await?;
+        Some((self.parse)(&notification))
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        let request = std::mem::take(&mut self.unsubscribe);
+        // the connection may already be closed
+        let _ = self.commands.send(Command::Unsubscribe { request });
+    }
+}
+
+impl Client {
+    /// Connects to the websocket API at `url`, with the commitment and
+    /// encoding of this client
+    pub async fn pubsub(&self, url: &str) -> Result<PubsubClient> {
+        PubsubClient::connect(url, self.config()).await
+    }
+}
+
+impl PubsubClient {
+    async fn connect(url: &str, config: RpcConfig) -> Result<Self> {
+        let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(socket, receiver));
+        Ok(Self { commands, config })
+    }
+
+    /// Notifies every change of an account
+    pub async fn account_subscribe(
+        &self,
+        pubkey: &Pubkey,
+    ) -> Result<Subscription<WithContext<Account>>> {
+        let call = SubscribeCall::account_subscribe(pubkey, self.config);
+        self.subscribe(call, parse_account).await
+    }
+
+    /// Notifies every change of an account of the program that passes the
+    /// filters. Accounts that the program closes are not notified, since they
+    /// no longer belong to it.
+    pub async fn program_subscribe(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<GetProgramAccountsFilter<'_>>,
+    ) -> Result<Subscription<WithContext<(Pubkey, Account)>>> {
+        let call = SubscribeCall::program_subscribe(program_id, filters, self.config);
+        self.subscribe(call, parse_program_account).await
+    }
+
+    async fn subscribe<T>(
+        &self,
+        call: SubscribeCall<'_>,
+        parse: fn(&Value) -> Result<T>,
+    ) -> Result<Subscription<T>> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(Command::Subscribe {
+                request: serde_json::to_string(&call).unwrap(),
+                id: call.id().to_string(),
+                reply,
+            })
+            .map_err(|_| Error::SubscriptionClosed)?;
+        let (id, notifications) = response.await.map_err(|_| Error::SubscriptionClosed)??;
+        Ok(Subscription {
+            id,
+            notifications,
+            commands: self.commands.clone(),
+            unsubscribe: serde_json::to_string(&call.unsubscribe(id)).unwrap(),
+            parse,
+        })
+    }
+}
+
+fn parse_account(value: &Value) -> Result<WithContext<Account>> {
+    let response = ContextResponse::<ReceivedAccount>::deserialize(value)
+        .map_err(|e| Error::json_deser(e, value.to_string(), String::new()))?;
+    Ok(WithContext {
+        slots: SlotRange::new(response.context.slot),
+        value: response.value.try_into()?,
+    })
+}
+
+fn parse_program_account(value: &Value) -> Result<WithContext<(Pubkey, Account)>> {
+    let response = ContextResponse::<ReceivedAccountsAndPubkeys>::deserialize(value)
+        .map_err(|e| Error::json_deser(e, value.to_string(), String::new()))?;
+    Ok(WithContext {
+        slots: SlotRange::new(response.context.slot),
+        value: (response.value.pubkey, response.value.account.try_into()?),
+    })
+}
+
+/// Owns the connection, sending requests and routing notifications to their
+/// subscriptions until the connection closes or every handle is dropped
+async fn run(
+    socket: WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let mut pending: HashMap<String, SubscribeReply> = HashMap::new();
+    let mut subscriptions: HashMap<u64, mpsc::UnboundedSender<Value>> = HashMap::new();
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                None => break,
+                Some(Command::Subscribe { request, id, reply }) => {
+                    if let Err(e) = sink.send(Message::Text(request)).await {
+                        let _ = reply.send(Err(e.into()));
+                        break;
+                    }
+                    pending.insert(id, reply);
+                }
+                Some(Command::Unsubscribe { request }) => {
+                    if sink.send(Message::Text(request)).await.is_err() {
+                        break;
+                    }
+                }
+            },
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    dispatch(&text, &mut pending, &mut subscriptions);
+                }
+                // pings are answered by the websocket itself
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    // dropping the senders ends every subscription
+}
+
+fn dispatch(
+    text: &str,
+    pending: &mut HashMap<String, SubscribeReply>,
+    subscriptions: &mut HashMap<u64, mpsc::UnboundedSender<Value>>,
+) {
+    let Ok(mut message) = serde_json::from_str::<Value>(text) else {
+        return;
+    };
+    // the response to a subscribe or unsubscribe request
+    if let Some(id) = message.get("id") {
+        let id = id.as_str().map_or_else(|| id.to_string(), str::to_string);
+        if let Some(reply) = pending.remove(&id) {
+            let result = match message["result"].as_u64() {
+                Some(subscription) => {
+                    let (sender, receiver) = mpsc::unbounded_channel();
+                    subscriptions.insert(subscription, sender);
+                    Ok((subscription, receiver))
+                }
+                None => Err(Error::NodeError {
+                    code: message["error"]["code"].as_i64().unwrap_or(-1) as isize,
+                    msg: message["error"]["message"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    request_json: String::new(),
+                }),
+            };
+            let _ = reply.send(result);
+        }
+        return;
+    }
+    let params = &mut message["params"];
+    if let Some(subscription) = params["subscription"].as_u64() {
+        if let Some(sender) = subscriptions.get(&subscription) {
+            if sender.send(params["result"].take()).is_err() {
+                subscriptions.remove(&subscription);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ws_url() {
+        assert_eq!(
+            ws_url("https://mainnet.helius-rpc.com/?api-key=abc"),
+            "wss://mainnet.helius-rpc.com/?api-key=abc"
+        );
+        assert_eq!(ws_url("http://localhost:8899"), "ws://localhost:8899");
+        assert_eq!(ws_url("wss://example.com"), "wss://example.com");
+    }
+
+    #[test]
+    fn test_program_subscribe_call() {
+        let program_id = Pubkey::new_unique();
+        let discriminator = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let call = rpc_call::SubscribeCall::program_subscribe(
+            &program_id,
+            vec![GetProgramAccountsFilter::Memcmp(Memcmp::new(
+                0,
+                &discriminator,
+            ))],
+            RpcConfig::default(),
+        );
+        let request = serde_json::to_value(&call).unwrap();
+        assert_eq!(request["method"], "programSubscribe");
+        assert_eq!(request["params"][0], program_id.to_string());
+        assert!(request["params"][1]["filters"][0]["memcmp"].is_object());
+        assert!(request["params"][1].get("minContextSlot").is_none());
+
+        let unsubscribe = serde_json::to_value(call.unsubscribe(42)).unwrap();
+        assert_eq!(unsubscribe["method"], "programUnsubscribe");
+        assert_eq!(unsubscribe["params"][0], 42);
+    }
+}
@@ -0,0 +1,213 @@
+use super::*;
+use std::{collections::BTreeMap, sync::Mutex, time::Instant};
+use tokio::sync::Semaphore;
+
+/// Requests per second allowed by default, which stays under the limits of
+/// the common paid RPC plans
+pub const DEFAULT_REQUESTS_PER_SECOND: u32 = 150;
+/// Requests allowed to be awaiting a response at the same time by default
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 50;
+
+pub const RATE_LIMIT_ENV: &str = "SOL_RPC_RATE_LIMIT";
+pub const MAX_IN_FLIGHT_ENV: &str = "SOL_RPC_MAX_IN_FLIGHT";
+
+/// Token bucket shared by every request a `Client` (and its clones) sends.
+/// Each JSON-RPC call costs one token, so a batch of 100 calls is throttled
+/// like 100 requests, which is how providers count them. Independently of
+/// the rate, at most `max_in_flight` HTTP requests are outstanding at once.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: u32,
+    max_in_flight: usize,
+    in_flight: Semaphore,
+    bucket: Mutex<Bucket>,
+    stats: Mutex<BTreeMap<&'static str, MethodStats>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Per-method accounting of the calls a `RateLimiter` held back. Call and
+/// error counts are kept by `Metrics`.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct MethodStats {
+    pub method: &'static str,
+    /// Calls that had to wait for tokens
+    pub throttled_calls: u64,
+    /// Total time calls spent waiting on the rate limiter
+    pub throttled_ms: u64,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_REQUESTS_PER_SECOND, DEFAULT_MAX_IN_FLIGHT)
+    }
+}
+
+impl RateLimiter {
+    /// A `requests_per_second` of 0 disables the rate limit, leaving only
+    /// the cap on in-flight requests.
+    pub fn new(requests_per_second: u32, max_in_flight: usize) -> Self {
+        let max_in_flight = max_in_flight.max(1);
+        Self {
+            requests_per_second,
+            max_in_flight,
+            in_flight: Semaphore::new(max_in_flight),
+            bucket: Mutex::new(Bucket {
+                tokens: requests_per_second as f64,
+                last_refill: Instant::now(),
+            }),
+            stats: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Reads `SOL_RPC_RATE_LIMIT` and `SOL_RPC_MAX_IN_FLIGHT`, falling back
+    /// to the defaults for any that are unset
+    pub fn from_env() -> Result<Self> {
+        let requests_per_second = match std::env::var(RATE_LIMIT_ENV) {
+            Ok(rate) => rate.trim().parse()?,
+            Err(_) => DEFAULT_REQUESTS_PER_SECOND,
+        };
+        let max_in_flight = match std::env::var(MAX_IN_FLIGHT_ENV) {
+            Ok(max) => max.trim().parse()?,
+            Err(_) => DEFAULT_MAX_IN_FLIGHT,
+        };
+        Ok(Self::new(requests_per_second, max_in_flight))
+    }
+
+    pub fn requests_per_second(&self) -> u32 {
+        self.requests_per_second
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// Waits until `methods.len()` tokens and an in-flight slot are
+    /// available, then runs `request`. Each entry in `methods` is one call.
+    pub(crate) async fn run<T, F>(&self, methods: &[&'static str], request: F) -> Result<T>
+    where
+        F: std::future::Future<Output = Result<T>>,
+    {
+        let wait = self.reserve(methods.len());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        let result = {
+            let _permit = self
+                .in_flight
+                .acquire()
+                .await
+                .map_err(|_| Error::Custom("rate limiter closed"))?;
+            request.await
+        };
+
+        if !wait.is_zero() {
+            let mut stats = self.stats.lock().unwrap();
+            for method in methods {
+                let stats = stats.entry(method).or_insert_with(|| MethodStats {
+                    method,
+                    ..Default::default()
+                });
+                stats.throttled_calls += 1;
+                stats.throttled_ms += wait.as_millis() as u64;
+            }
+        }
+        result
+    }
+
+    /// Takes `cost` tokens from the bucket and returns how long the caller
+    /// must wait for them. The bucket may go negative, which queues callers
+    /// in order instead of letting them race for refilled tokens.
+    fn reserve(&self, cost: usize) -> Duration {
+        if self.requests_per_second == 0 {
+            return Duration::ZERO;
+        }
+        let rate = self.requests_per_second as f64;
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.last_refill).as_secs_f64() * rate;
+        bucket.tokens = (bucket.tokens + refill).min(rate);
+        bucket.last_refill = now;
+        bucket.tokens -= cost as f64;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        }
+    }
+
+    pub fn stats(&self) -> Vec<MethodStats> {
+        self.stats.lock().unwrap().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::test_support::fixture_account;
+    use tokio::test;
+
+    #[test]
+    async fn test_rate_limiter_throttles() {
+        let pubkey = Pubkey::new_unique();
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_account(pubkey, &fixture_account(Pubkey::new_unique(), vec![1])),
+        )
+        .with_rate_limiter(RateLimiter::new(20, 4));
+
+        // the bucket starts full, so the second 20 calls wait for a refill
+        let start = std::time::Instant::now();
+        let calls = (0..40).map(|_| client.get_account(&pubkey));
+        for result in futures::future::join_all(calls).await {
+            result.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(900));
+        client.get_account(&Pubkey::new_unique()).await.unwrap_err();
+
+        let stats = client.rate_limiter().stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].method, "getAccountInfo");
+        assert!(stats[0].throttled_calls >= 20 && stats[0].throttled_calls <= 21);
+        assert!(stats[0].throttled_ms > 0);
+        assert_eq!(client.metrics().snapshot()[0].successes, 41);
+    }
+
+    #[test]
+    async fn test_rate_limiter_throttles_batch_calls() {
+        let mint = Pubkey::new_unique();
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_token_account(mint, Pubkey::new_unique(), Pubkey::new_unique())
+                .with_token_supply(mint, 1),
+        )
+        .with_rate_limiter(RateLimiter::new(2, 1));
+        let unknown = Pubkey::new_unique();
+        let calls = vec![
+            RpcCall::get_token_supply(&mint, RpcConfig::default()),
+            RpcCall::get_token_supply(&unknown, RpcConfig::default()),
+            RpcCall::get_token_largest_accounts(&mint, RpcConfig::default()),
+        ];
+        client
+            .post_batch::<serde_json::Value>(&calls)
+            .await
+            .unwrap();
+
+        // the batch costs three tokens out of a bucket of two
+        let stats = client.rate_limiter().stats();
+        assert_eq!(stats[0].method, "getTokenLargestAccounts");
+        assert_eq!(stats[0].throttled_calls, 1);
+        assert_eq!(stats[1].method, "getTokenSupply");
+        assert_eq!(stats[1].throttled_calls, 2);
+        assert!(stats[1].throttled_ms >= 2 * 400);
+
+        let metrics = client.metrics().snapshot();
+        assert_eq!(metrics[1].method, "getTokenSupply");
+        assert_eq!(metrics[1].successes, 1);
+        assert_eq!(metrics[1].failures, 1);
+    }
+}
@@ -0,0 +1,131 @@
+use super::*;
+use rand::Rng;
+use std::future::Future;
+
+/// How `Client` retries failed requests. Only errors whose `ErrorKind` is
+/// retriable are retried, with exponential backoff starting at `base_delay`
+/// and capped at `max_delay`. With `jitter`, each delay is picked at random
+/// between half and all of the backoff so that concurrent callers spread out.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// The delay before retry number `retry`, starting at 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        if self.jitter && !backoff.is_zero() {
+            rand::thread_rng().gen_range(backoff / 2..=backoff)
+        } else {
+            backoff
+        }
+    }
+
+    /// Runs `attempt` until it succeeds, fails with an error that is not
+    /// retriable or runs out of retries
+    pub(crate) async fn run<T, F, Fut>(&self, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut retries = 0;
+        loop {
+            match attempt().await {
+                Err(e) if e.is_retriable() && retries < self.max_retries => {
+                    retries += 1;
+                    tokio::time::sleep(self.delay(retries)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::test_support::*;
+    use tokio::test;
+
+    #[test]
+    async fn test_retry_policy_retries_transient_errors() {
+        let pubkey = Pubkey::new_unique();
+        let flaky = FlakyTransport {
+            failures: 2.into(),
+            inner: FixtureTransport::new()
+                .with_account(pubkey, &fixture_account(Pubkey::new_unique(), vec![1])),
+        };
+        let client = Client::new_with_transport(flaky).with_retry_policy(fast_retries(2));
+        client.get_account(&pubkey).await.unwrap();
+        let metrics = client.metrics().snapshot();
+        assert_eq!(metrics[0].successes, 1);
+        assert_eq!(metrics[0].failures, 2);
+
+        let flaky = FlakyTransport {
+            failures: 3.into(),
+            inner: FixtureTransport::new(),
+        };
+        let client = Client::new_with_transport(flaky).with_retry_policy(fast_retries(2));
+        assert_eq!(
+            client.get_account(&pubkey).await.unwrap_err().kind(),
+            ErrorKind::Transient
+        );
+    }
+
+    #[test]
+    async fn test_retry_policy_skips_invalid_requests() {
+        let client =
+            Client::new_with_transport(FixtureTransport::new()).with_retry_policy(fast_retries(5));
+        let err = client
+            .get_token_supply_amount(&Pubkey::new_unique())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidRequest);
+        assert_eq!(client.metrics().snapshot()[0].failures, 1);
+    }
+
+    #[test]
+    async fn test_retry_policy_delay() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            jitter: false,
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(8), Duration::from_millis(1000));
+        let jittered = RetryPolicy {
+            jitter: true,
+            ..policy
+        }
+        .delay(3);
+        assert!(jittered >= Duration::from_millis(200) && jittered <= Duration::from_millis(400));
+    }
+}
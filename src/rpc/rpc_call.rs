@@ -1,5 +1,6 @@
 use super::*;
 use serde::Serializer;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// JSON RPC version
 pub const JSON_RPC: &str = "2.0";
@@ -45,17 +46,32 @@ enum Method<'se> {
         params: Vec<GetAccountInfoParam<'se>>,
     },
     GetTokenLargestAccounts {
-        params: Vec<InnerPubkey<'se>>,
+        params: Vec<MintParam<'se>>,
     },
     GetTokenSupply {
-        params: Vec<InnerPubkey<'se>>,
+        params: Vec<MintParam<'se>>,
+    },
+    GetAsset {
+        params: GetAssetParams<'se>,
+    },
+    GetAssetsByOwner {
+        params: GetAssetsByOwnerParams<'se>,
     },
     GetAssetsByAuthority {
         params: GetAssetsByAuthorityParams<'se>,
     },
+    SearchAssets {
+        params: SearchAssetsParams<'se>,
+    },
     GetProgramAccounts {
         params: Vec<GetProgramAccountsParams<'se>>,
     },
+    GetSignaturesForAddress {
+        params: Vec<SignaturesParam<'se>>,
+    },
+    GetTransaction {
+        params: Vec<TransactionParam<'se>>,
+    },
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -73,88 +89,252 @@ enum GetAccountInfoParam<'se> {
     Encoding(Encoding),
 }
 
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+enum MintParam<'se> {
+    Pubkey(InnerPubkey<'se>),
+    Commitment(CommitmentConfig),
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct CommitmentConfig {
+    commitment: Commitment,
+}
+
 #[derive(Clone, Debug, Serialize)]
 struct Encoding {
     encoding: EncodingType,
+    #[serde(flatten)]
+    config: RpcConfig,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Default)]
+/// Level of finality a node must have reached for the state it answers with
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
-enum EncodingType {
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+/// Commitment and minimum context slot sent along with account queries. Nodes
+/// that have not yet reached `min_context_slot` reject the request, which
+/// keeps every part of a snapshot at or after a given slot.
+#[derive(Copy, Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RpcConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<Commitment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<u64>,
+    /// Sent as part of `Encoding`, only by calls that return account data
+    #[serde(skip)]
+    pub encoding: EncodingType,
+}
+
+impl<'se> MintParam<'se> {
+    /// `getTokenLargestAccounts` and `getTokenSupply` only take a commitment
+    fn with_config(pubkey: &'se Pubkey, config: RpcConfig) -> Vec<Self> {
+        let mut params = vec![MintParam::Pubkey(pubkey.into())];
+        if let Some(commitment) = config.commitment {
+            params.push(MintParam::Commitment(CommitmentConfig { commitment }));
+        }
+        params
+    }
+}
+
+/// Encoding of the account data returned by the node
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum EncodingType {
     #[default]
+    #[serde(rename = "base64")]
     Base64,
+    /// zstd compressed before base64 encoding, which greatly reduces the
+    /// size of large scans
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+}
+
+impl Encoding {
+    fn new(config: RpcConfig) -> Self {
+        Self {
+            encoding: config.encoding,
+            config,
+        }
+    }
+}
+
+/// Methods grouped by how long a node may take to answer them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MethodClass {
+    /// Reads of a few accounts, token balances or transactions
+    Account,
+    /// `getProgramAccounts`
+    Scan,
+    /// Calls to the Digital Asset Standard API
+    Das,
 }
 
 impl<'se> RpcCall<'se> {
     fn new(request: Method) -> RpcCall {
         RpcCall {
             jsonrpc: JSON_RPC.to_string(),
-            id: now_millis(),
+            id: next_id(),
             method: request,
         }
     }
 
-    pub(crate) fn get_multiple_accounts(array: &[&'se Pubkey]) -> Self {
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The JSON-RPC method name, used for per-method accounting
+    pub(crate) fn method(&self) -> &'static str {
+        match self.method {
+            Method::GetMultipleAccounts { .. } => "getMultipleAccounts",
+            Method::GetAccountInfo { .. } => "getAccountInfo",
+            Method::GetTokenLargestAccounts { .. } => "getTokenLargestAccounts",
+            Method::GetTokenSupply { .. } => "getTokenSupply",
+            Method::GetAsset { .. } => "getAsset",
+            Method::GetAssetsByOwner { .. } => "getAssetsByOwner",
+            Method::GetAssetsByAuthority { .. } => "getAssetsByAuthority",
+            Method::SearchAssets { .. } => "searchAssets",
+            Method::GetProgramAccounts { .. } => "getProgramAccounts",
+            Method::GetSignaturesForAddress { .. } => "getSignaturesForAddress",
+            Method::GetTransaction { .. } => "getTransaction",
+        }
+    }
+
+    pub(crate) fn class(&self) -> MethodClass {
+        match self.method {
+            Method::GetProgramAccounts { .. } => MethodClass::Scan,
+            Method::GetAsset { .. }
+            | Method::GetAssetsByOwner { .. }
+            | Method::GetAssetsByAuthority { .. }
+            | Method::SearchAssets { .. } => MethodClass::Das,
+            Method::GetMultipleAccounts { .. }
+            | Method::GetAccountInfo { .. }
+            | Method::GetTokenLargestAccounts { .. }
+            | Method::GetTokenSupply { .. }
+            | Method::GetSignaturesForAddress { .. }
+            | Method::GetTransaction { .. } => MethodClass::Account,
+        }
+    }
+
+    pub(crate) fn get_multiple_accounts(array: &[&'se Pubkey], config: RpcConfig) -> Self {
         Self::new(Method::GetMultipleAccounts {
             params: vec![
                 GetAccountInfoParam::Pubkeys(array.iter().map(|p| (*p).into()).collect()),
-                GetAccountInfoParam::Encoding(Encoding {
-                    encoding: EncodingType::Base64,
-                }),
+                GetAccountInfoParam::Encoding(Encoding::new(config)),
             ],
         })
     }
 
-    pub(crate) fn get_account_info(address: &'se Pubkey) -> Self {
+    pub(crate) fn get_account_info(address: &'se Pubkey, config: RpcConfig) -> Self {
         Self::new(Method::GetAccountInfo {
             params: vec![
                 GetAccountInfoParam::Pubkey(address.into()),
-                GetAccountInfoParam::Encoding(Encoding {
-                    encoding: EncodingType::Base64,
-                }),
+                GetAccountInfoParam::Encoding(Encoding::new(config)),
             ],
         })
     }
 
-    pub(crate) fn get_token_largest_accounts(pubkey: &'se Pubkey) -> Self {
+    pub(crate) fn get_token_largest_accounts(pubkey: &'se Pubkey, config: RpcConfig) -> Self {
         Self::new(Method::GetTokenLargestAccounts {
-            params: vec![pubkey.into()],
+            params: MintParam::with_config(pubkey, config),
         })
     }
 
-    pub(crate) fn get_token_supply(pubkey: &'se Pubkey) -> Self {
+    pub(crate) fn get_token_supply(pubkey: &'se Pubkey, config: RpcConfig) -> Self {
         Self::new(Method::GetTokenSupply {
-            params: vec![pubkey.into()],
+            params: MintParam::with_config(pubkey, config),
+        })
+    }
+
+    pub(crate) fn get_asset(id: &'se Pubkey) -> Self {
+        Self::new(Method::GetAsset {
+            params: GetAssetParams { id: id.into() },
+        })
+    }
+
+    pub(crate) fn get_assets_by_owner(owner_address: &'se Pubkey, page: Page) -> Self {
+        Self::new(Method::GetAssetsByOwner {
+            params: GetAssetsByOwnerParams {
+                owner_address: owner_address.into(),
+                page,
+            },
         })
     }
 
-    pub(crate) fn get_assets_by_authority(authority_address: &'se Pubkey) -> Self {
+    pub(crate) fn get_assets_by_authority(authority_address: &'se Pubkey, page: Page) -> Self {
         Self::new(Method::GetAssetsByAuthority {
             params: GetAssetsByAuthorityParams {
                 authority_address: authority_address.into(),
-                page: 1,
-                limit: 100,
+                page,
             },
         })
     }
 
+    pub(crate) fn search_assets(query: &'se SearchAssets, page: Page) -> Self {
+        Self::new(Method::SearchAssets {
+            params: SearchAssetsParams { query, page },
+        })
+    }
+
+    pub(crate) fn get_signatures_for_address(
+        address: &'se Pubkey,
+        query: &'se SignaturesQuery,
+        config: RpcConfig,
+    ) -> Self {
+        Self::new(Method::GetSignaturesForAddress {
+            params: vec![
+                SignaturesParam::Pubkey(address.into()),
+                SignaturesParam::Config(SignaturesConfig { query, config }),
+            ],
+        })
+    }
+
+    /// Nodes do not serve transactions at the `processed` commitment, so it
+    /// is left to the node's default
+    pub(crate) fn get_transaction(
+        signature: &'se str,
+        encoding: TransactionEncoding,
+        config: RpcConfig,
+    ) -> Self {
+        Self::new(Method::GetTransaction {
+            params: vec![
+                TransactionParam::Signature(signature),
+                TransactionParam::Config(TransactionConfig {
+                    encoding,
+                    commitment: config
+                        .commitment
+                        .filter(|commitment| *commitment != Commitment::Processed),
+                    max_supported_transaction_version: 0,
+                }),
+            ],
+        })
+    }
+
     #[allow(unused)]
-    pub(crate) fn get_program_accounts(program_id: &'se Pubkey) -> Self {
-        Self::get_program_accounts_with_filters(program_id, vec![])
+    pub(crate) fn get_program_accounts(program_id: &'se Pubkey, config: RpcConfig) -> Self {
+        Self::get_program_accounts_with_filters(program_id, vec![], None, config)
     }
 
+    /// Always requests the context so that the slot of the scan is known
     pub(crate) fn get_program_accounts_with_filters(
         program_id: &'se Pubkey,
         filters: Vec<GetProgramAccountsFilter<'se>>,
+        data_slice: Option<DataSlice>,
+        config: RpcConfig,
     ) -> Self {
         Self::new(Method::GetProgramAccounts {
             params: vec![
                 GetProgramAccountsParams::Pubkey(program_id.into()),
                 GetProgramAccountsParams::Object(GetProgramAccountsObject {
                     filters,
-                    encoding: Encoding {
-                        encoding: EncodingType::Base64,
-                    },
+                    encoding: Encoding::new(config),
+                    with_context: true,
+                    data_slice,
                 }),
             ],
         })
@@ -162,8 +342,16 @@ impl<'se> RpcCall<'se> {
 }
 
 #[derive(Clone, Debug, Serialize)]
-struct GetAssetParams {
-    id: String,
+struct GetAssetParams<'se> {
+    id: InnerPubkey<'se>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GetAssetsByOwnerParams<'se> {
+    owner_address: InnerPubkey<'se>,
+    #[serde(flatten)]
+    page: Page,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -175,8 +363,16 @@ struct GetAccountInfoParams {
 #[serde(rename_all = "camelCase")]
 struct GetAssetsByAuthorityParams<'se> {
     authority_address: InnerPubkey<'se>,
-    page: usize,
-    limit: usize,
+    #[serde(flatten)]
+    page: Page,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SearchAssetsParams<'se> {
+    #[serde(flatten)]
+    query: &'se SearchAssets,
+    #[serde(flatten)]
+    page: Page,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -187,22 +383,158 @@ enum GetProgramAccountsParams<'se> {
     Object(GetProgramAccountsObject<'se>),
 }
 #[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct GetProgramAccountsObject<'se> {
     filters: Vec<GetProgramAccountsFilter<'se>>,
     #[serde(flatten)]
     encoding: Encoding,
+    with_context: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_slice: Option<DataSlice>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+enum SignaturesParam<'se> {
+    Pubkey(InnerPubkey<'se>),
+    Config(SignaturesConfig<'se>),
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SignaturesConfig<'se> {
+    #[serde(flatten)]
+    query: &'se SignaturesQuery,
+    #[serde(flatten)]
+    config: RpcConfig,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+enum TransactionParam<'se> {
+    Signature(&'se str),
+    Config(TransactionConfig),
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionConfig {
+    encoding: TransactionEncoding,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commitment: Option<Commitment>,
+    max_supported_transaction_version: u8,
+}
+
+/// A request sent over a websocket rather than through a `Transport`
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct SubscribeCall<'se> {
+    jsonrpc: String,
+    id: String,
+    #[serde(flatten)]
+    method: SubscribeMethod<'se>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "method")]
+#[serde(rename_all = "camelCase")]
+enum SubscribeMethod<'se> {
+    AccountSubscribe {
+        params: Vec<GetAccountInfoParam<'se>>,
+    },
+    ProgramSubscribe {
+        params: Vec<GetProgramAccountsParams<'se>>,
+    },
 }
 
+/// Cancels a subscription
 #[derive(Clone, Debug, Serialize)]
+pub(crate) struct UnsubscribeCall {
+    jsonrpc: String,
+    id: String,
+    method: &'static str,
+    params: [u64; 1],
+}
+
+impl<'se> SubscribeCall<'se> {
+    fn new(method: SubscribeMethod) -> SubscribeCall {
+        SubscribeCall {
+            jsonrpc: JSON_RPC.to_string(),
+            id: next_id(),
+            method,
+        }
+    }
+
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Cancels the subscription this call created
+    pub(crate) fn unsubscribe(&self, subscription: u64) -> UnsubscribeCall {
+        UnsubscribeCall {
+            jsonrpc: JSON_RPC.to_string(),
+            id: next_id(),
+            method: match self.method {
+                SubscribeMethod::AccountSubscribe { .. } => "accountUnsubscribe",
+                SubscribeMethod::ProgramSubscribe { .. } => "programUnsubscribe",
+            },
+            params: [subscription],
+        }
+    }
+
+    /// Notifications are always sent with their context, so the minimum
+    /// context slot does not apply
+    pub(crate) fn account_subscribe(pubkey: &'se Pubkey, config: RpcConfig) -> Self {
+        let config = RpcConfig {
+            min_context_slot: None,
+            ..config
+        };
+        Self::new(SubscribeMethod::AccountSubscribe {
+            params: vec![
+                GetAccountInfoParam::Pubkey(pubkey.into()),
+                GetAccountInfoParam::Encoding(Encoding::new(config)),
+            ],
+        })
+    }
+
+    pub(crate) fn program_subscribe(
+        program_id: &'se Pubkey,
+        filters: Vec<GetProgramAccountsFilter<'se>>,
+        config: RpcConfig,
+    ) -> Self {
+        let config = RpcConfig {
+            min_context_slot: None,
+            ..config
+        };
+        Self::new(SubscribeMethod::ProgramSubscribe {
+            params: vec![
+                GetProgramAccountsParams::Pubkey(program_id.into()),
+                GetProgramAccountsParams::Object(GetProgramAccountsObject {
+                    filters,
+                    encoding: Encoding::new(config),
+                    with_context: true,
+                    data_slice: None,
+                }),
+            ],
+        })
+    }
+}
+
+/// Restricts the account data returned to `length` bytes from `offset`
+#[derive(Copy, Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DataSlice {
     pub offset: usize,
     pub length: usize,
 }
 
-fn now_millis() -> String {
-    let ms = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap();
-    ms.as_millis().to_string()
+impl DataSlice {
+    pub fn new(offset: usize, length: usize) -> Self {
+        Self { offset, length }
+    }
+}
+
+/// Request ids only need to be unique per client process so that responses to
+/// a batch can be correlated with their requests.
+fn next_id() -> String {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed).to_string()
 }
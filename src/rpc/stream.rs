@@ -0,0 +1,513 @@
+use super::*;
+use client::ErrorResponse;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use std::{fmt, marker::PhantomData, sync::Mutex};
+
+impl Client {
+    /// Scans a program like `get_program_accounts_with_context`, handing
+    /// each account to `decode` as soon as it is parsed from the response.
+    /// Only what `decode` returns is kept, so a scan never holds the raw
+    /// accounts of the whole response at once; accounts for which `decode`
+    /// returns `None` are skipped. The response is parsed as it arrives, so
+    /// its body is not held as a whole either.
+    pub async fn get_program_accounts_decoded<'a, T, F>(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<GetProgramAccountsFilter<'a>>,
+        data_slice: Option<DataSlice>,
+        decode: F,
+    ) -> Result<WithContext<Vec<T>>>
+    where
+        F: FnMut(Pubkey, Account) -> Result<Option<T>> + Send,
+        T: Send,
+    {
+        let json = RpcCall::get_program_accounts_with_filters(
+            program_id,
+            filters,
+            data_slice,
+            self.config(),
+        );
+        let decode = Mutex::new(decode);
+        self.post_streaming(&json, |request_json| {
+            AccountsParser::new(&decode, request_json)
+        })
+        .await
+    }
+
+    /// Like `get_program_accounts_sharded`, decoding each account of every
+    /// shard as in `get_program_accounts_decoded`
+    pub async fn get_program_accounts_sharded_decoded<'a, T, F>(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<GetProgramAccountsFilter<'a>>,
+        shard_offset: usize,
+        data_slice: Option<DataSlice>,
+        decode: F,
+    ) -> Result<WithContext<Vec<T>>>
+    where
+        F: Fn(Pubkey, Account) -> Result<Option<T>> + Sync,
+        T: Send,
+    {
+        let shard_bytes: Vec<[u8; 1]> = (0..SHARD_COUNT).map(|b| [b as u8]).collect();
+        let shards = shard_bytes.iter().map(|byte| {
+            let mut filters = filters.clone();
+            filters.push(GetProgramAccountsFilter::Memcmp(Memcmp::new(
+                shard_offset,
+                byte,
+            )));
+            self.get_program_accounts_decoded(program_id, filters, data_slice, &decode)
+        });
+        futures::future::try_join_all(shards)
+            .await?
+            .into_iter()
+            .reduce(|mut accounts, shard| {
+                accounts.slots = accounts.slots.merge(shard.slots);
+                accounts.value.extend(shard.value);
+                accounts
+            })
+            .ok_or(Error::Custom("sharded scan has no shards"))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub(super) struct ReceivedAccountsAndPubkeys {
+    pub account: ReceivedAccount,
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub pubkey: Pubkey,
+}
+
+/// Parses the body of a response chunk by chunk, as it arrives
+pub(super) trait BodyParser {
+    type Output;
+
+    fn feed(&mut self, chunk: &[u8]) -> Result<()>;
+
+    /// Called once the whole body has been fed
+    fn finish(self) -> Result<Self::Output>;
+}
+
+/// A JSON object or array the parser is within
+enum Frame {
+    /// The last key read, and whether its value is being read
+    Object {
+        key: Option<Vec<u8>>,
+        in_value: bool,
+    },
+    /// Whether this is the array of accounts at `result.value`
+    Array { accounts: bool },
+}
+
+/// Parses a `getProgramAccounts` response with context, decoding each
+/// account of `result.value` as soon as all of its bytes have arrived. The
+/// bytes of an account, and the comma before it, are dropped once it is
+/// decoded, leaving the rest of the response with an empty array of
+/// accounts to be read by `finish`. An error returned by `decode` is
+/// returned as is rather than as a deserialization error.
+struct AccountsParser<'a, T, F> {
+    decode: &'a Mutex<F>,
+    request_json: String,
+    decoded: Vec<T>,
+    /// The bytes received and not dropped yet
+    buf: Vec<u8>,
+    /// How much of `buf` has been scanned
+    pos: usize,
+    stack: Vec<Frame>,
+    /// Start of the string being scanned
+    string: Option<usize>,
+    escaped: bool,
+    /// Start of the account being scanned
+    account: Option<usize>,
+}
+
+impl<'a, T, F> AccountsParser<'a, T, F>
+where
+    F: FnMut(Pubkey, Account) -> Result<Option<T>>,
+{
+    fn new(decode: &'a Mutex<F>, request_json: String) -> Self {
+        Self {
+            decode,
+            request_json,
+            decoded: Vec::new(),
+            buf: Vec::new(),
+            pos: 0,
+            stack: Vec::new(),
+            string: None,
+            escaped: false,
+            account: None,
+        }
+    }
+
+    fn in_accounts(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Array { accounts: true }))
+    }
+
+    /// Whether an array starting here is the one at `result.value`
+    fn at_accounts(&self) -> bool {
+        match self.stack.as_slice() {
+            [Frame::Object {
+                key: Some(result),
+                in_value: true,
+            }, Frame::Object {
+                key: Some(value),
+                in_value: true,
+            }] => result == b"result" && value == b"value",
+            _ => false,
+        }
+    }
+
+    /// Decodes the account in `buf[start..end]` and drops its bytes
+    fn decode_account(&mut self, start: usize, end: usize) -> Result<()> {
+        let received: ReceivedAccountsAndPubkeys = serde_json::from_slice(&self.buf[start..end])
+            .map_err(|e| {
+                let account = String::from_utf8_lossy(&self.buf[start..end]).into_owned();
+                Error::json_deser(e, account, self.request_json.clone())
+            })?;
+        let account = Account::try_from(received.account)?;
+        let mut decode = self.decode.lock().unwrap();
+        if let Some(value) = (*decode)(received.pubkey, account)? {
+            self.decoded.push(value);
+        }
+        self.buf.drain(start..end);
+        self.pos = start;
+        Ok(())
+    }
+}
+
+impl<'a, T, F> BodyParser for AccountsParser<'a, T, F>
+where
+    F: FnMut(Pubkey, Account) -> Result<Option<T>>,
+{
+    type Output = WithContext<Vec<T>>;
+
+    fn feed(&mut self, chunk: &[u8]) -> Result<()> {
+        self.buf.extend_from_slice(chunk);
+        while self.pos < self.buf.len() {
+            let byte = self.buf[self.pos];
+            if let Some(start) = self.string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.string = None;
+                    if let Some(Frame::Object {
+                        key,
+                        in_value: false,
+                    }) = self.stack.last_mut()
+                    {
+                        *key = Some(self.buf[start + 1..self.pos].to_vec());
+                    }
+                }
+                self.pos += 1;
+                continue;
+            }
+            match byte {
+                b'"' => self.string = Some(self.pos),
+                b'{' => {
+                    if self.in_accounts() {
+                        self.account = Some(self.pos);
+                    }
+                    self.stack.push(Frame::Object {
+                        key: None,
+                        in_value: false,
+                    });
+                }
+                b'[' => {
+                    let accounts = self.at_accounts();
+                    self.stack.push(Frame::Array { accounts });
+                }
+                b'}' | b']' => {
+                    self.stack.pop();
+                    if self.in_accounts() {
+                        if let Some(start) = self.account.take() {
+                            self.decode_account(start, self.pos + 1)?;
+                            continue;
+                        }
+                    }
+                }
+                b':' => {
+                    if let Some(Frame::Object { in_value, .. }) = self.stack.last_mut() {
+                        *in_value = true;
+                    }
+                }
+                b',' if self.in_accounts() => {
+                    self.buf.remove(self.pos);
+                    continue;
+                }
+                b',' => {
+                    if let Some(Frame::Object { in_value, .. }) = self.stack.last_mut() {
+                        *in_value = false;
+                    }
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Output> {
+        let mut deserializer = serde_json::Deserializer::from_slice(&self.buf);
+        let response = ResponseSeed(ContextSeed(PhantomData::<Vec<IgnoredAny>>))
+            .deserialize(&mut deserializer)
+            .and_then(|response| deserializer.end().map(|_| response));
+        match response {
+            Err(e) => Err(Error::json_deser(
+                e,
+                String::from_utf8_lossy(&self.buf).into_owned(),
+                self.request_json,
+            )),
+            Ok(Err(error)) => Err(Error::NodeError {
+                code: error.code,
+                msg: error.message,
+                request_json: self.request_json,
+            }),
+            Ok(Ok(response)) => Ok(WithContext {
+                slots: response.slots,
+                value: self.decoded,
+            }),
+        }
+    }
+}
+
+/// The JSON-RPC envelope, yielding the node's error if there is no result
+struct ResponseSeed<S>(S);
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for ResponseSeed<S> {
+    type Value = std::result::Result<S::Value, ErrorResponse>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> Visitor<'de> for ResponseSeed<S> {
+    type Value = std::result::Result<S::Value, ErrorResponse>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON-RPC response")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum NodeError {
+            Object(ErrorResponse),
+            Text(String),
+        }
+
+        let mut seed = Some(self.0);
+        let mut response = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "result" => {
+                    let seed = seed
+                        .take()
+                        .ok_or_else(|| de::Error::duplicate_field("result"))?;
+                    response = Some(Ok(map.next_value_seed(seed)?));
+                }
+                "error" => {
+                    response = Some(Err(match map.next_value()? {
+                        NodeError::Object(error) => error,
+                        NodeError::Text(message) => ErrorResponse { code: -1, message },
+                    }));
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        response.ok_or_else(|| de::Error::missing_field("result"))
+    }
+}
+
+/// A result with context, such as `{"context": {"slot": 1}, "value": ...}`
+struct ContextSeed<S>(S);
+
+impl<'de, S: DeserializeSeed<'de, Value = Vec<T>>, T> DeserializeSeed<'de> for ContextSeed<S> {
+    type Value = WithContext<Vec<T>>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de, Value = Vec<T>>, T> Visitor<'de> for ContextSeed<S> {
+    type Value = WithContext<Vec<T>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a result with context")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let mut seed = Some(self.0);
+        let mut context: Option<Context> = None;
+        let mut value = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "context" => context = Some(map.next_value()?),
+                "value" => {
+                    let seed = seed
+                        .take()
+                        .ok_or_else(|| de::Error::duplicate_field("value"))?;
+                    value = Some(map.next_value_seed(seed)?);
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(WithContext {
+            slots: SlotRange::new(
+                context
+                    .ok_or_else(|| de::Error::missing_field("context"))?
+                    .slot,
+            ),
+            value: value.ok_or_else(|| de::Error::missing_field("value"))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::test_support::fixture_account;
+    use tokio::test;
+
+    #[test]
+    async fn test_fixture_get_program_accounts_decoded() {
+        let program_id = Pubkey::new_unique();
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_slot(3)
+                .with_account(
+                    Pubkey::new_unique(),
+                    &fixture_account(program_id, vec![1, 5]),
+                )
+                .with_account(
+                    Pubkey::new_unique(),
+                    &fixture_account(program_id, vec![2, 6]),
+                )
+                .with_account(Pubkey::new_unique(), &fixture_account(program_id, vec![3])),
+        )
+        .with_retry_policy(RetryPolicy::none());
+
+        let mut seen = 0;
+        let decoded = client
+            .get_program_accounts_decoded(&program_id, vec![], None, |_, account| {
+                seen += 1;
+                Ok(account.data.get(1).copied())
+            })
+            .await
+            .unwrap();
+        assert_eq!(seen, 3);
+        assert_eq!(decoded.slots, SlotRange::new(3));
+        let mut values = decoded.value;
+        values.sort();
+        assert_eq!(values, vec![5, 6]);
+
+        let bad = Pubkey::new_unique();
+        let err = client
+            .get_program_accounts_decoded(&program_id, vec![], None, |_, _| {
+                Err::<Option<u8>, _>(Error::AccountDecode(bad, "bad".to_string()))
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::AccountDecode(pubkey, _) if pubkey == bad));
+
+        let err = client
+            .clone()
+            .with_min_context_slot(4)
+            .get_program_accounts_decoded(&program_id, vec![], None, |p, a| Ok(Some((p, a))))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::NodeError { code: -32016, .. }));
+    }
+
+    #[test]
+    async fn test_accounts_parser_chunks() {
+        let accounts: Vec<(Pubkey, Vec<u8>)> = (0..5u8)
+            .map(|i| (Pubkey::new_unique(), vec![i; 3]))
+            .collect();
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "context": { "slot": 12, "apiVersion": "a \"quoted\" {value} [1, 2]" },
+                "value": accounts
+                    .iter()
+                    .map(|(pubkey, data)| serde_json::json!({
+                        "pubkey": pubkey.to_string(),
+                        "account": {
+                            "lamports": 1,
+                            "owner": Pubkey::new_unique().to_string(),
+                            "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+                            "executable": false,
+                            "rentEpoch": 0
+                        }
+                    }))
+                    .collect::<Vec<_>>()
+            },
+            "id": "0"
+        })
+        .to_string();
+
+        for chunk_size in [1, 7, body.len()] {
+            let decode = Mutex::new(
+                |pubkey: Pubkey, account: Account| -> Result<Option<(Pubkey, Vec<u8>)>> {
+                    Ok(Some((pubkey, account.data)))
+                },
+            );
+            let mut parser = AccountsParser::new(&decode, String::new());
+            for chunk in body.as_bytes().chunks(chunk_size) {
+                parser.feed(chunk).unwrap();
+            }
+            // only the envelope and the context are left once the accounts
+            // are decoded
+            assert!(parser.buf.len() < 150);
+            let decoded = parser.finish().unwrap();
+            assert_eq!(decoded.slots, SlotRange::new(12));
+            assert_eq!(decoded.value, accounts);
+        }
+    }
+
+    #[test]
+    async fn test_fixture_get_program_accounts_sharded() {
+        let program_id = Pubkey::new_unique();
+        let mut transport = FixtureTransport::new().with_slot(9).with_account(
+            Pubkey::new_unique(),
+            &fixture_account(program_id, vec![8, 0, 1]),
+        );
+        for shard in [0, 17, 255] {
+            transport = transport.with_account(
+                Pubkey::new_unique(),
+                &fixture_account(program_id, vec![7, shard, 1, 2]),
+            );
+        }
+        let client = Client::new_with_transport(transport);
+        let accounts = client
+            .get_program_accounts_sharded(
+                &program_id,
+                vec![GetProgramAccountsFilter::Memcmp(Memcmp::new(0, &[7]))],
+                1,
+                Some(DataSlice::new(1, 2)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(accounts.slots, SlotRange::new(9));
+        let mut data: Vec<Vec<u8>> = accounts.value.into_iter().map(|(_, a)| a.data).collect();
+        data.sort();
+        assert_eq!(data, vec![vec![0, 1], vec![17, 1], vec![255, 1]]);
+    }
+}
@@ -0,0 +1,98 @@
+use super::*;
+
+pub(super) fn fixture_account(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1,
+        owner,
+        data,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct FailingTransport;
+
+#[async_trait::async_trait]
+impl Transport for FailingTransport {
+    async fn send(&self, _body: String) -> Result<String> {
+        Err(Error::HttpStatus {
+            status: 503,
+            body: String::new(),
+        })
+    }
+}
+
+/// Fails the first `failures` requests with a 503, then defers to `inner`
+#[derive(Debug)]
+pub(super) struct FlakyTransport {
+    pub(super) failures: std::sync::atomic::AtomicUsize,
+    pub(super) inner: FixtureTransport,
+}
+
+#[async_trait::async_trait]
+impl Transport for FlakyTransport {
+    async fn send(&self, body: String) -> Result<String> {
+        use std::sync::atomic::Ordering;
+        if self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+        {
+            return FailingTransport.send(body).await;
+        }
+        self.inner.send(body).await
+    }
+}
+
+pub(super) fn fast_retries(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(10),
+        jitter: true,
+    }
+}
+
+pub(super) fn das_asset(id: Pubkey, owner: Pubkey, authority: Pubkey, collection: &str) -> Asset {
+    serde_json::from_value(serde_json::json!({
+        "interface": "V1_NFT",
+        "id": id.to_string(),
+        "content": {
+            "$schema": "https://schema.metaplex.com/nft1.0.json",
+            "json_uri": "https://example.com/asset.json",
+            "files": [],
+            "metadata": {
+                "attributes": [{ "value": "IOT", "trait_type": "sub_dao" }],
+                "name": "Position",
+                "symbol": "POS"
+            },
+            "links": {}
+        },
+        "authorities": [{ "address": authority.to_string(), "scopes": ["full"] }],
+        "compression": {
+            "eligible": false,
+            "compressed": false,
+            "data_hash": "",
+            "creator_hash": "",
+            "asset_hash": "",
+            "tree": "",
+            "seq": 0,
+            "leaf_id": 0
+        },
+        "grouping": [{ "group_key": "collection", "group_value": collection }],
+        "royalty": {},
+        "creators": [],
+        "ownership": {
+            "frozen": false,
+            "delegated": false,
+            "delegate": null,
+            "ownership_model": "single",
+            "owner": owner.to_string()
+        },
+        "supply": null,
+        "mutable": true,
+        "burnt": false
+    }))
+    .unwrap()
+}
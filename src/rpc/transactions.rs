@@ -0,0 +1,328 @@
+use super::*;
+use serde_json::Value;
+
+/// Largest number of signatures `getSignaturesForAddress` returns at once
+pub const SIGNATURES_MAX_LIMIT: usize = 1000;
+
+/// Pagination of `getSignaturesForAddress`. Signatures are listed newest
+/// first, starting before `before` and stopping at `until`, both exclusive.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SignaturesQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+}
+
+/// A transaction that referenced an address
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+    /// The transaction error, if it failed
+    pub err: Option<Value>,
+    pub memo: Option<String>,
+    pub block_time: Option<i64>,
+    pub confirmation_status: Option<Commitment>,
+}
+
+/// How `getTransaction` returns the transaction itself
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum TransactionEncoding {
+    /// Instructions of known programs are parsed by the node
+    #[serde(rename = "jsonParsed")]
+    JsonParsed,
+    /// The serialized transaction, as sent to the network
+    #[serde(rename = "base64")]
+    Base64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub meta: Option<TransactionMeta>,
+    pub transaction: EncodedTransaction,
+    /// `legacy` or the version number
+    pub version: Option<Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EncodedTransaction {
+    /// The data and its encoding
+    Binary(String, String),
+    Json(ParsedTransaction),
+}
+
+impl EncodedTransaction {
+    /// The serialized transaction of a `base64` encoded response
+    pub fn decode(&self) -> Result<Option<Vec<u8>>> {
+        match self {
+            EncodedTransaction::Binary(data, encoding) if encoding == "base64" => Ok(Some(
+                base64::engine::general_purpose::STANDARD.decode(data)?,
+            )),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedTransaction {
+    pub signatures: Vec<String>,
+    pub message: ParsedMessage,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedMessage {
+    pub account_keys: Vec<ParsedAccountKey>,
+    pub recent_blockhash: String,
+    pub instructions: Vec<ParsedInstruction>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedAccountKey {
+    #[serde(
+        deserialize_with = "deserialize_pubkey",
+        serialize_with = "serialize_pubkey"
+    )]
+    pub pubkey: Pubkey,
+    pub signer: bool,
+    pub writable: bool,
+}
+
+/// An instruction as returned with `jsonParsed`. The node only parses the
+/// instructions of programs it knows, such as the token programs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParsedInstruction {
+    #[serde(rename_all = "camelCase")]
+    Parsed {
+        program: String,
+        #[serde(
+            deserialize_with = "deserialize_pubkey",
+            serialize_with = "serialize_pubkey"
+        )]
+        program_id: Pubkey,
+        parsed: Value,
+    },
+    #[serde(rename_all = "camelCase")]
+    Raw {
+        #[serde(
+            deserialize_with = "deserialize_pubkey",
+            serialize_with = "serialize_pubkey"
+        )]
+        program_id: Pubkey,
+        accounts: Vec<String>,
+        /// base58 encoded instruction data
+        data: String,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionMeta {
+    /// The transaction error, if it failed
+    pub err: Option<Value>,
+    pub fee: u64,
+    pub pre_balances: Vec<u64>,
+    pub post_balances: Vec<u64>,
+    #[serde(default)]
+    pub inner_instructions: Option<Vec<InnerInstructions>>,
+    #[serde(default)]
+    pub log_messages: Option<Vec<String>>,
+    #[serde(default)]
+    pub pre_token_balances: Option<Vec<TokenBalance>>,
+    #[serde(default)]
+    pub post_token_balances: Option<Vec<TokenBalance>>,
+}
+
+/// Instructions invoked by the instruction at `index` of the transaction,
+/// parsed like the transaction's instructions with `jsonParsed` and compiled
+/// (account and program indexes) otherwise
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InnerInstructions {
+    pub index: u8,
+    pub instructions: Vec<Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBalance {
+    pub account_index: u8,
+    pub mint: String,
+    pub owner: Option<String>,
+    pub ui_token_amount: UiTokenAmount,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UiTokenAmount {
+    pub amount: String,
+    pub decimals: u8,
+}
+
+impl Client {
+    pub async fn get_signatures_for_address_page(
+        &self,
+        address: &Pubkey,
+        query: &SignaturesQuery,
+    ) -> Result<Vec<SignatureInfo>> {
+        let json = RpcCall::get_signatures_for_address(address, query, self.config());
+        self.post(&json).await
+    }
+
+    /// Fetches up to `max` signatures for `address`, newest first, paging
+    /// from `query.before` down to but excluding `query.until`. The limit of
+    /// `query` is ignored; pages are as large as nodes allow.
+    pub async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        mut query: SignaturesQuery,
+        max: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut signatures: Vec<SignatureInfo> = Vec::new();
+        while signatures.len() < max {
+            let limit = (max - signatures.len()).min(SIGNATURES_MAX_LIMIT);
+            query.limit = Some(limit);
+            let page = self
+                .get_signatures_for_address_page(address, &query)
+                .await?;
+            let last_page = page.len() < limit;
+            signatures.extend(page);
+            if last_page {
+                break;
+            }
+            query.before = signatures.last().map(|s| s.signature.clone());
+        }
+        signatures.truncate(max);
+        Ok(signatures)
+    }
+
+    /// `None` when the node does not know the transaction
+    pub async fn get_transaction(
+        &self,
+        signature: &str,
+        encoding: TransactionEncoding,
+    ) -> Result<Option<Transaction>> {
+        let json = RpcCall::get_transaction(signature, encoding, self.config());
+        self.post(&json).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::test;
+
+    #[test]
+    async fn test_fixture_transaction_history() {
+        let address = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let client = Client::new_with_transport(
+            FixtureTransport::new()
+                .with_response(
+                    "getSignaturesForAddress",
+                    serde_json::json!([
+                        {
+                            "signature": "sig2",
+                            "slot": 11,
+                            "err": null,
+                            "memo": null,
+                            "blockTime": 1700000100,
+                            "confirmationStatus": "finalized"
+                        },
+                        {
+                            "signature": "sig1",
+                            "slot": 10,
+                            "err": { "InstructionError": [0, "Custom"] },
+                            "memo": null,
+                            "blockTime": null,
+                            "confirmationStatus": null
+                        }
+                    ]),
+                )
+                .with_response(
+                    "getTransaction",
+                    serde_json::json!({
+                        "slot": 11,
+                        "blockTime": 1700000100,
+                        "version": 0,
+                        "meta": {
+                            "err": null,
+                            "fee": 5000,
+                            "preBalances": [10, 0],
+                            "postBalances": [5, 0],
+                            "logMessages": ["Program log: hi"]
+                        },
+                        "transaction": {
+                            "signatures": ["sig2"],
+                            "message": {
+                                "accountKeys": [{
+                                    "pubkey": address.to_string(),
+                                    "signer": true,
+                                    "writable": true,
+                                    "source": "transaction"
+                                }],
+                                "recentBlockhash": "hash",
+                                "instructions": [{
+                                    "programId": program_id.to_string(),
+                                    "accounts": [address.to_string()],
+                                    "data": "3Bxs4h24hBtQy9rw"
+                                }]
+                            }
+                        }
+                    }),
+                ),
+        );
+
+        let signatures = client
+            .get_signatures_for_address(&address, SignaturesQuery::default(), 10)
+            .await
+            .unwrap();
+        assert_eq!(signatures.len(), 2);
+        let newest = client
+            .get_signatures_for_address(&address, SignaturesQuery::default(), 1)
+            .await
+            .unwrap();
+        assert_eq!(newest.len(), 1);
+        assert_eq!(newest[0].signature, "sig2");
+        assert_eq!(signatures[0].signature, "sig2");
+        assert_eq!(
+            signatures[0].confirmation_status,
+            Some(Commitment::Finalized)
+        );
+        assert!(signatures[1].err.is_some());
+
+        let transaction = client
+            .get_transaction("sig2", TransactionEncoding::JsonParsed)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transaction.meta.unwrap().fee, 5000);
+        let EncodedTransaction::Json(parsed) = transaction.transaction else {
+            panic!("expected a parsed transaction");
+        };
+        assert_eq!(parsed.message.account_keys[0].pubkey, address);
+        assert!(matches!(
+            &parsed.message.instructions[0],
+            ParsedInstruction::Raw { program_id: id, .. } if *id == program_id
+        ));
+
+        let binary: Transaction = serde_json::from_value(serde_json::json!({
+            "slot": 11,
+            "blockTime": null,
+            "meta": null,
+            "transaction": ["AQID", "base64"]
+        }))
+        .unwrap();
+        assert_eq!(binary.transaction.decode().unwrap(), Some(vec![1, 2, 3]));
+    }
+}
@@ -0,0 +1,87 @@
+use super::*;
+
+/// A transport delivers a serialized JSON-RPC request to a node and hands
+/// back the raw response body. `Client` is agnostic of how that happens.
+#[async_trait::async_trait]
+pub trait Transport: std::fmt::Debug + Send + Sync {
+    async fn send(&self, body: String) -> Result<String>;
+
+    /// Like `send`, handing the response body to `sink` in chunks as they
+    /// arrive instead of as a whole, and returning its size. An error from
+    /// `sink` aborts the request. Transports that cannot stream deliver the
+    /// body returned by `send` as a single chunk.
+    async fn send_streaming(
+        &self,
+        body: String,
+        sink: &mut (dyn FnMut(&[u8]) -> Result<()> + Send),
+    ) -> Result<usize> {
+        let response = self.send(body).await?;
+        sink(response.as_bytes())?;
+        Ok(response.len())
+    }
+}
+
+/// Transport that POSTs requests over HTTP(S) using `reqwest`
+#[derive(Clone, Debug)]
+pub struct HttpTransport {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    pub fn new(url: String, timeout: u64) -> Self {
+        let client = reqwest::Client::builder()
+            .gzip(true)
+            .timeout(Duration::from_secs(timeout))
+            .build()
+            .unwrap();
+        Self::with_client(url, client)
+    }
+
+    pub fn with_client(url: String, client: reqwest::Client) -> Self {
+        Self { url, client }
+    }
+}
+
+impl HttpTransport {
+    /// Sends the request, failing on statuses that mean the endpoint is
+    /// throttling or unhealthy
+    async fn post(&self, body: String) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?;
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            return Err(Error::HttpStatus {
+                status: status.as_u16(),
+                body: response.text().await?,
+            });
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for HttpTransport {
+    async fn send(&self, body: String) -> Result<String> {
+        Ok(self.post(body).await?.text().await?)
+    }
+
+    async fn send_streaming(
+        &self,
+        body: String,
+        sink: &mut (dyn FnMut(&[u8]) -> Result<()> + Send),
+    ) -> Result<usize> {
+        let mut response = self.post(body).await?;
+        let mut len = 0;
+        while let Some(chunk) = response.chunk().await? {
+            len += chunk.len();
+            sink(&chunk)?;
+        }
+        Ok(len)
+    }
+}
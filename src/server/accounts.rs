@@ -4,46 +4,59 @@ use axum::extract::Path;
 use std::str::FromStr;
 
 use crate::cli::accounts::{self, HeliumBalances};
-use crate::server::positions::{Dao, LockedBalances};
+use crate::daos::{Dao, Daos};
+use crate::server::positions::LockedBalances;
+use std::collections::BTreeMap;
 
-#[derive(serde::Serialize, Deserialize, Default, PartialEq, Debug, Copy, Clone)]
+#[derive(serde::Serialize, Deserialize, Default, PartialEq, Debug, Clone)]
 pub struct Balances {
     pub vehnt: VehntBalance,
     pub hnt: HntBalance,
-    pub iot: DntBalance,
-    pub mobile: DntBalance,
-    pub veiot: u128,
-    pub vemobile: u128,
+    /// The balance of the token of each subDAO, by its symbol
+    #[serde(flatten)]
+    pub dnt: BTreeMap<String, DntBalance>,
+    /// The voting power in each subDAO, by the name of the voting power
+    #[serde(flatten)]
+    pub ve: BTreeMap<String, u128>,
 }
 
 impl Add for Balances {
     type Output = Self;
     fn add(self, other: Self) -> Self {
+        let mut ve = self.ve;
+        for (name, amount) in other.ve {
+            *ve.entry(name).or_default() += amount;
+        }
+        let mut dnt = self.dnt;
+        for (symbol, balance) in other.dnt {
+            let entry = dnt.entry(symbol).or_default();
+            *entry = *entry + balance;
+        }
         Self {
             vehnt: self.vehnt + other.vehnt,
             hnt: self.hnt + other.hnt,
-            iot: self.iot + other.iot,
-            mobile: self.mobile + other.mobile,
-            veiot: self.veiot + other.veiot,
-            vemobile: self.vemobile + other.vemobile,
+            dnt,
+            ve,
         }
     }
 }
 
 impl Balances {
-    pub fn absorb_position_balances(&mut self, locked_balances: LockedBalances) {
-        self.vehnt = locked_balances.vehnt;
-        self.veiot = locked_balances.veiot;
-        self.vemobile = locked_balances.vemobile;
+    pub fn absorb_position_balances(&mut self, locked_balances: &LockedBalances) {
+        self.vehnt = locked_balances.vehnt.clone();
+        self.ve.extend(
+            locked_balances
+                .ve
+                .iter()
+                .map(|(name, ve)| (name.clone(), *ve)),
+        );
 
         self.hnt.locked_amount = locked_balances.locked_hnt;
         self.hnt.total_amount += locked_balances.locked_hnt;
-        self.iot.absorb_pending_amount(locked_balances.pending_iot);
-        self.iot.absorb_locked_amount(locked_balances.locked_iot);
-        self.mobile
-            .absorb_pending_amount(locked_balances.pending_mobile);
-        self.mobile
-            .absorb_locked_amount(locked_balances.locked_mobile);
+        for (symbol, balance) in self.dnt.iter_mut() {
+            balance.absorb_pending_amount(locked_balances.pending_of(symbol));
+            balance.absorb_locked_amount(locked_balances.locked_of(symbol));
+        }
     }
 }
 #[derive(serde::Serialize, Deserialize, Default, Copy, Clone, Debug, PartialEq)]
@@ -96,22 +109,26 @@ impl Add for HntBalance {
     }
 }
 
-#[derive(serde::Serialize, Deserialize, Default, Copy, Clone, Debug, PartialEq)]
+#[derive(serde::Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
 pub struct VehntBalance {
     pub total: u128,
-    pub iot_delegated: u128,
-    pub mobile_delegated: u128,
     pub undelegated: u128,
+    /// The veHNT delegated to each subDAO, as `{symbol}_delegated`
+    #[serde(flatten)]
+    pub delegated: BTreeMap<String, u128>,
 }
 
 impl Add for VehntBalance {
     type Output = Self;
     fn add(self, other: Self) -> Self {
+        let mut delegated = self.delegated;
+        for (name, amount) in other.delegated {
+            *delegated.entry(name).or_default() += amount;
+        }
         Self {
             total: self.total + other.total,
-            iot_delegated: self.iot_delegated + other.iot_delegated,
-            mobile_delegated: self.mobile_delegated + other.mobile_delegated,
             undelegated: self.undelegated + other.undelegated,
+            delegated,
         }
     }
 }
@@ -121,10 +138,12 @@ impl From<HeliumBalances> for Balances {
         Self {
             vehnt: VehntBalance::default(),
             hnt: HntBalance::from(value.hnt),
-            iot: DntBalance::from(value.iot),
-            mobile: DntBalance::from(value.mobile),
-            veiot: 0,
-            vemobile: 0,
+            dnt: value
+                .sub_daos
+                .into_iter()
+                .map(|(symbol, balance)| (symbol, DntBalance::from(balance)))
+                .collect(),
+            ve: BTreeMap::new(),
         }
     }
 }
@@ -150,12 +169,9 @@ impl From<accounts::Balance> for HntBalance {
     }
 }
 
-#[derive(Deserialize, Default)]
-pub struct FullPositions {
-    pub vehnt: Vec<positions::Position>,
-    pub vemobile: Vec<positions::Position>,
-    pub veiot: Vec<positions::Position>,
-}
+/// The positions of an account, by the name of the voting power of their DAO
+pub type FullPositions = BTreeMap<String, Vec<positions::Position>>;
+
 #[derive(Deserialize, Default)]
 pub struct Account {
     #[serde(default = "FullPositions::default")]
@@ -169,7 +185,16 @@ pub async fn get_account(
     Path(account): Path<String>,
 ) -> HandlerResult {
     if let Ok(pubkey) = Pubkey::from_str(&account) {
-        match HeliumBalances::fetch(&rpc_client, &pubkey).await {
+        let daos = match positions.lock().await.as_ref() {
+            Some(positions) => positions.daos().clone(),
+            None => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    DATA_NOT_INIT_MSG.to_string(),
+                ))
+            }
+        };
+        match HeliumBalances::fetch(&rpc_client, &daos, &pubkey).await {
             Err(e) => {
                 println!("Error fetching account: {}", e);
                 Err((
@@ -187,26 +212,22 @@ pub async fn get_account(
                     ));
                 }
                 let positions = positions.as_ref().unwrap();
-
-                #[derive(serde::Serialize, Default)]
-                pub struct Positions<'a> {
-                    vehnt: Vec<&'a positions::Position>,
-                    vemobile: Vec<&'a positions::Position>,
-                    veiot: Vec<&'a positions::Position>,
+                for dao in positions.daos().sub_daos() {
+                    balances.ve.insert(dao.ve_name(), 0);
                 }
 
                 if let Some(account) = positions.positions_by_owner.get(&pubkey) {
-                    balances.absorb_position_balances(account.balances);
+                    balances.absorb_position_balances(&account.balances);
 
                     fn position_keys_to_positions<'a>(
                         account: &Pubkey,
-                        account_positions: &Vec<Pubkey>,
-                        positions: &'a HashMap<Pubkey, positions::Position>,
+                        account_positions: &[Pubkey],
+                        positions: Option<&'a HashMap<Pubkey, positions::Position>>,
                     ) -> std::result::Result<Vec<&'a positions::Position>, (StatusCode, String)>
                     {
                         let mut list_of_positions: Vec<&'a positions::Position> = Vec::new();
                         for p in account_positions {
-                            match positions.get(p) {
+                            match positions.and_then(|positions| positions.get(p)) {
                                 None => {
                                     let error = format!("Expected to find position {p} for account {account} but none found!");
                                     println!("{error}");
@@ -217,23 +238,17 @@ pub async fn get_account(
                         }
                         Ok(list_of_positions)
                     }
-                    let list_of_positions = Positions {
-                        vehnt: position_keys_to_positions(
-                            &pubkey,
-                            &account.positions.vehnt,
-                            &positions.vehnt_positions,
-                        )?,
-                        veiot: position_keys_to_positions(
-                            &pubkey,
-                            &account.positions.veiot,
-                            &positions.veiot_positions,
-                        )?,
-                        vemobile: position_keys_to_positions(
-                            &pubkey,
-                            &account.positions.vemobile,
-                            &positions.vemobile_positions,
-                        )?,
-                    };
+                    let mut list_of_positions = BTreeMap::new();
+                    for dao in positions.daos().iter() {
+                        list_of_positions.insert(
+                            dao.ve_name(),
+                            position_keys_to_positions(
+                                &pubkey,
+                                account.positions.of(dao),
+                                positions.positions_of(dao),
+                            )?,
+                        );
+                    }
                     Ok(response::Json(json!({
                         "balances": balances,
                         "positions": list_of_positions,
@@ -274,27 +289,30 @@ pub async fn get_account(
 
 #[derive(serde::Serialize)]
 struct TopResult {
+    /// The voting power the accounts are ranked by
     #[serde(skip_serializing)]
-    pub dao: Dao,
+    pub ve: u128,
     pub pubkey: String,
-    pub positions: Positions,
+    pub positions: BTreeMap<String, Vec<String>>,
     #[serde(flatten)]
     pub locked_balances: LockedBalances,
 }
 
-#[derive(serde::Serialize, Deserialize)]
-pub struct Positions {
-    vehnt: Vec<String>,
-    veiot: Vec<String>,
-    vemobile: Vec<String>,
-}
-
-impl From<&positions::account::Positions> for Positions {
-    fn from(value: &positions::account::Positions) -> Self {
-        Positions {
-            vehnt: value.vehnt.iter().map(|p| p.to_string()).collect(),
-            veiot: value.veiot.iter().map(|p| p.to_string()).collect(),
-            vemobile: value.vemobile.iter().map(|p| p.to_string()).collect(),
+impl TopResult {
+    fn new(owner: &Pubkey, account: &positions::Account, dao: &Dao, daos: &Daos) -> Self {
+        let mut locked_balances = account.balances.clone();
+        locked_balances.fill(daos);
+        Self {
+            ve: account.balances.ve_of(dao),
+            pubkey: owner.to_string(),
+            positions: daos
+                .iter()
+                .map(|dao| {
+                    let keys = account.positions.of(dao);
+                    (dao.ve_name(), keys.iter().map(|p| p.to_string()).collect())
+                })
+                .collect(),
+            locked_balances,
         }
     }
 }
@@ -302,18 +320,7 @@ impl From<&positions::account::Positions> for Positions {
 use std::cmp::{Ord, Ordering};
 impl Ord for TopResult {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.dao {
-            Dao::Hnt => self
-                .locked_balances
-                .vehnt
-                .total
-                .cmp(&other.locked_balances.vehnt.total),
-            Dao::Mobile => self
-                .locked_balances
-                .vemobile
-                .cmp(&other.locked_balances.vemobile),
-            Dao::Iot => self.locked_balances.veiot.cmp(&other.locked_balances.veiot),
-        }
+        self.ve.cmp(&other.ve)
     }
 }
 impl PartialEq for TopResult {
@@ -328,27 +335,11 @@ impl PartialOrd for TopResult {
     }
 }
 
-pub async fn get_top_vehnt_accounts(
-    positions: Extension<Arc<Mutex<Option<positions::Memory>>>>,
-) -> HandlerResult {
-    get_top_dao_accounts(positions, Dao::Hnt).await
-}
-
-pub async fn get_top_vemobile_accounts(
-    positions: Extension<Arc<Mutex<Option<positions::Memory>>>>,
-) -> HandlerResult {
-    get_top_dao_accounts(positions, Dao::Mobile).await
-}
-
-pub async fn get_top_veiot_accounts(
-    positions: Extension<Arc<Mutex<Option<positions::Memory>>>>,
-) -> HandlerResult {
-    get_top_dao_accounts(positions, Dao::Iot).await
-}
-
-pub async fn get_top_dao_accounts(
+/// The accounts with the most voting power in a DAO, by the name of its
+/// voting power or its symbol
+pub async fn get_top_accounts(
     Extension(positions): Extension<Arc<Mutex<Option<positions::Memory>>>>,
-    dao: Dao,
+    Path(dao): Path<String>,
 ) -> HandlerResult {
     let positions = positions.lock().await;
     if positions.is_none() {
@@ -358,16 +349,18 @@ pub async fn get_top_dao_accounts(
         ));
     }
     let positions = positions.as_ref().unwrap();
+    let daos = positions.daos();
+    let Some(dao) = daos.by_name(&dao) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("\"{dao}\" is not a known DAO"),
+        ));
+    };
 
     let mut owners_and_balances: Vec<TopResult> = positions
         .positions_by_owner
         .iter()
-        .map(|(owner, account)| TopResult {
-            dao,
-            pubkey: owner.to_string(),
-            positions: Positions::from(&account.positions),
-            locked_balances: account.balances,
-        })
+        .map(|(owner, account)| TopResult::new(owner, account, dao, daos))
         .collect();
     owners_and_balances.sort();
     owners_and_balances.reverse();
@@ -0,0 +1,481 @@
+use super::signatures::{self, PageParams};
+use super::*;
+use crate::decoder::{self, Action, HeliumProgram};
+use crate::types::{DC_MINT, HNT_MINT, IOT_MINT, MOBILE_MINT};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use axum::extract::Path;
+use std::{collections::HashSet, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Token {
+    Hnt,
+    Iot,
+    Mobile,
+    Dc,
+}
+
+impl Token {
+    const ALL: [Token; 4] = [Token::Hnt, Token::Iot, Token::Mobile, Token::Dc];
+
+    fn mint(&self) -> &'static str {
+        match self {
+            Token::Hnt => HNT_MINT,
+            Token::Iot => IOT_MINT,
+            Token::Mobile => MOBILE_MINT,
+            Token::Dc => DC_MINT,
+        }
+    }
+
+    fn from_mint(mint: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|token| token.mint() == mint)
+    }
+}
+
+/// A Helium-relevant effect of a transaction on an account
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActivityEvent {
+    /// Tokens sent or received. `from` is `None` for minted tokens. Both are
+    /// the owners of the token accounts when known.
+    Transfer {
+        token: Token,
+        amount: u64,
+        from: Option<String>,
+        to: String,
+        incoming: bool,
+    },
+    Burn {
+        token: Token,
+        amount: u64,
+    },
+    /// A position or delegation instruction that the account took part in
+    Position {
+        program: HeliumProgram,
+        action: Action,
+        position: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Activity {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub fee: Option<u64>,
+    pub events: Vec<ActivityEvent>,
+}
+
+/// The account and its HNT, IOT, MOBILE and DC token accounts, whose
+/// signatures together make up the account's activity
+fn activity_addresses(account: &Pubkey) -> Result<Vec<Pubkey>> {
+    let mut addresses = vec![*account];
+    for token in Token::ALL {
+        let mint = Pubkey::from_str(token.mint())?;
+        addresses.push(spl_associated_token_account::get_associated_token_address(
+            account, &mint,
+        ));
+    }
+    Ok(addresses)
+}
+
+/// The Helium-relevant events of `account` in a transaction. Transfers and
+/// burns are read from the token instructions parsed by the node, including
+/// the ones invoked by other programs.
+pub fn activity_events(
+    account: &Pubkey,
+    token_accounts: &[Pubkey],
+    transaction: &rpc::Transaction,
+) -> Result<Vec<ActivityEvent>> {
+    let rpc::EncodedTransaction::Json(parsed) = &transaction.transaction else {
+        return Err(Error::Custom("only jsonParsed transactions can be decoded"));
+    };
+    let keys: Vec<String> = parsed
+        .message
+        .account_keys
+        .iter()
+        .map(|key| key.pubkey.to_string())
+        .collect();
+    let account_str = account.to_string();
+    let mut owned: HashSet<String> = token_accounts.iter().map(|a| a.to_string()).collect();
+    // token account to its mint and owner
+    let mut token_info: HashMap<String, (String, Option<String>)> = HashMap::new();
+    if let Some(meta) = &transaction.meta {
+        let balances = meta
+            .pre_token_balances
+            .iter()
+            .chain(&meta.post_token_balances);
+        for balance in balances.flatten() {
+            let Some(key) = keys.get(balance.account_index as usize) else {
+                continue;
+            };
+            if balance.owner.as_deref() == Some(account_str.as_str()) {
+                owned.insert(key.clone());
+            }
+            token_info.insert(key.clone(), (balance.mint.clone(), balance.owner.clone()));
+        }
+    }
+    let owner_of = |token_account: &str| {
+        token_info
+            .get(token_account)
+            .and_then(|(_, owner)| owner.clone())
+            .unwrap_or_else(|| token_account.to_string())
+    };
+
+    let mut instructions: Vec<Value> = parsed
+        .message
+        .instructions
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<std::result::Result<_, _>>()?;
+    if let Some(inner) = transaction
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.inner_instructions.as_ref())
+    {
+        instructions.extend(inner.iter().flat_map(|i| i.instructions.iter().cloned()));
+    }
+
+    let mut events = Vec::new();
+    for instruction in &instructions {
+        let parsed = &instruction["parsed"];
+        let info = &parsed["info"];
+        let amount = info["amount"]
+            .as_str()
+            .or_else(|| info["tokenAmount"]["amount"].as_str())
+            .and_then(|amount| amount.parse::<u64>().ok());
+        let mint_of = |token_account: &str| {
+            info["mint"]
+                .as_str()
+                .map(str::to_string)
+                .or_else(|| token_info.get(token_account).map(|(mint, _)| mint.clone()))
+        };
+        let (Some(kind), Some(amount)) = (parsed["type"].as_str(), amount) else {
+            continue;
+        };
+        let event = match kind {
+            "transfer" | "transferChecked" => {
+                let (Some(source), Some(destination)) =
+                    (info["source"].as_str(), info["destination"].as_str())
+                else {
+                    continue;
+                };
+                let incoming = owned.contains(destination);
+                if !incoming && !owned.contains(source) {
+                    continue;
+                }
+                let Some(token) = mint_of(source).and_then(|mint| Token::from_mint(&mint)) else {
+                    continue;
+                };
+                ActivityEvent::Transfer {
+                    token,
+                    amount,
+                    from: Some(owner_of(source)),
+                    to: owner_of(destination),
+                    incoming,
+                }
+            }
+            "mintTo" | "mintToChecked" => {
+                let Some(destination) = info["account"].as_str() else {
+                    continue;
+                };
+                let Some(token) = mint_of(destination).and_then(|mint| Token::from_mint(&mint))
+                else {
+                    continue;
+                };
+                if !owned.contains(destination) {
+                    continue;
+                }
+                ActivityEvent::Transfer {
+                    token,
+                    amount,
+                    from: None,
+                    to: owner_of(destination),
+                    incoming: true,
+                }
+            }
+            "burn" | "burnChecked" => {
+                let Some(source) = info["account"].as_str() else {
+                    continue;
+                };
+                let Some(token) = mint_of(source).and_then(|mint| Token::from_mint(&mint)) else {
+                    continue;
+                };
+                if !owned.contains(source) {
+                    continue;
+                }
+                ActivityEvent::Burn { token, amount }
+            }
+            _ => continue,
+        };
+        events.push(event);
+    }
+
+    for instruction in decoder::decode_transaction(transaction)? {
+        if instruction
+            .accounts
+            .iter()
+            .any(|(_, key)| *key == account_str)
+        {
+            events.push(ActivityEvent::Position {
+                program: instruction.program,
+                position: instruction.account("position").map(|p| p.to_string()),
+                action: instruction.action,
+            });
+        }
+    }
+    Ok(events)
+}
+
+/// Lists the decoded transactions of an account, newest first, a page at a
+/// time. Failed transactions and transactions without Helium-relevant
+/// events are left out.
+pub async fn get(
+    Extension(rpc_client): Extension<Arc<rpc::Client>>,
+    Path(account): Path<String>,
+    Query(params): Query<PageParams>,
+) -> HandlerResult {
+    let Ok(pubkey) = Pubkey::from_str(&account) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("\"{account}\" is not a valid base58 encoded Solana pubkey"),
+        ));
+    };
+    let limit = params.limit();
+    let activity = async {
+        let addresses = activity_addresses(&pubkey)?;
+        let (signatures, next) =
+            signatures::signatures_page(&rpc_client, &addresses, params.before, limit).await?;
+        let signatures: Vec<_> = signatures.into_iter().filter(|s| s.err.is_none()).collect();
+        let transactions = signatures::get_transactions(&rpc_client, &signatures).await?;
+        let mut activity = Vec::new();
+        for (signature, transaction) in signatures.into_iter().zip(transactions) {
+            let Some(transaction) = transaction else {
+                continue;
+            };
+            let events = activity_events(&pubkey, &addresses[1..], &transaction)?;
+            if !events.is_empty() {
+                activity.push(Activity {
+                    signature: signature.signature,
+                    slot: transaction.slot,
+                    block_time: transaction.block_time,
+                    fee: transaction.meta.as_ref().map(|meta| meta.fee),
+                    events,
+                });
+            }
+        }
+        Ok::<_, Error>((activity, next))
+    };
+    match activity.await {
+        Ok((activity, next)) => Ok(response::Json(json!({
+            "activity": activity,
+            "next": next,
+        }))
+        .into()),
+        Err(e) => {
+            println!("Error fetching activity of account {account}: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error fetching activity of account {account}"),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    fn token_instruction(kind: &str, info: Value) -> Value {
+        json!({
+            "program": "spl-token",
+            "programId": TOKEN_PROGRAM_ID,
+            "parsed": { "type": kind, "info": info },
+        })
+    }
+
+    fn balance(account_index: u8, mint: &str, owner: &Pubkey) -> rpc::TokenBalance {
+        rpc::TokenBalance {
+            account_index,
+            mint: mint.to_string(),
+            owner: Some(owner.to_string()),
+            ui_token_amount: rpc::UiTokenAmount {
+                amount: "0".to_string(),
+                decimals: 0,
+            },
+        }
+    }
+
+    /// A transaction whose first instruction invokes `inner`
+    fn transaction(
+        keys: &[Pubkey],
+        balances: Vec<rpc::TokenBalance>,
+        instructions: Vec<Value>,
+        inner: Vec<Value>,
+    ) -> rpc::Transaction {
+        rpc::Transaction {
+            slot: 10,
+            block_time: Some(1_700_000_000),
+            meta: Some(rpc::TransactionMeta {
+                err: None,
+                fee: 5000,
+                pre_balances: vec![],
+                post_balances: vec![],
+                inner_instructions: Some(vec![rpc::InnerInstructions {
+                    index: 0,
+                    instructions: inner,
+                }]),
+                log_messages: None,
+                pre_token_balances: Some(balances.clone()),
+                post_token_balances: Some(balances),
+            }),
+            transaction: rpc::EncodedTransaction::Json(rpc::ParsedTransaction {
+                signatures: vec!["sig".to_string()],
+                message: rpc::ParsedMessage {
+                    account_keys: keys
+                        .iter()
+                        .map(|key| rpc::ParsedAccountKey {
+                            pubkey: *key,
+                            signer: false,
+                            writable: true,
+                        })
+                        .collect(),
+                    recent_blockhash: String::new(),
+                    instructions: instructions
+                        .into_iter()
+                        .map(|i| serde_json::from_value(i).unwrap())
+                        .collect(),
+                },
+            }),
+            version: None,
+        }
+    }
+
+    fn events(
+        account: &Pubkey,
+        token_accounts: &[Pubkey],
+        transaction: &rpc::Transaction,
+    ) -> Value {
+        serde_json::to_value(activity_events(account, token_accounts, transaction).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_transfers() {
+        let (account, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // an associated token account, one known only from the balances,
+        // and the other party's
+        let (ata, token_account, theirs) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let transaction = transaction(
+            &[ata, token_account, theirs],
+            vec![
+                balance(0, HNT_MINT, &account),
+                balance(1, IOT_MINT, &account),
+                balance(2, HNT_MINT, &other),
+            ],
+            vec![
+                token_instruction(
+                    "transfer",
+                    json!({ "source": ata.to_string(), "destination": theirs.to_string(), "amount": "100" }),
+                ),
+                token_instruction(
+                    "transferChecked",
+                    json!({
+                        "source": theirs.to_string(),
+                        "destination": token_account.to_string(),
+                        "mint": IOT_MINT,
+                        "tokenAmount": { "amount": "7", "decimals": 6 },
+                    }),
+                ),
+                // between other accounts
+                token_instruction(
+                    "transfer",
+                    json!({ "source": theirs.to_string(), "destination": theirs.to_string(), "amount": "1" }),
+                ),
+            ],
+            vec![],
+        );
+        assert_eq!(
+            events(&account, &[ata], &transaction),
+            json!([
+                {
+                    "type": "transfer",
+                    "token": "hnt",
+                    "amount": 100,
+                    "from": account.to_string(),
+                    "to": other.to_string(),
+                    "incoming": false,
+                },
+                {
+                    "type": "transfer",
+                    "token": "iot",
+                    "amount": 7,
+                    "from": other.to_string(),
+                    "to": account.to_string(),
+                    "incoming": true,
+                },
+            ])
+        );
+        assert_eq!(
+            events(&other, &[theirs], &transaction)[0]["incoming"],
+            json!(true)
+        );
+        assert_eq!(events(&Pubkey::new_unique(), &[], &transaction), json!([]));
+    }
+
+    #[test]
+    fn test_inner_mint_and_burn() {
+        let account = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let unknown_mint = Pubkey::new_unique().to_string();
+        let program = json!({
+            "programId": Pubkey::new_unique().to_string(),
+            "accounts": [],
+            "data": "",
+        });
+        let transaction = transaction(
+            &[token_account],
+            vec![balance(0, MOBILE_MINT, &account)],
+            vec![program],
+            vec![
+                token_instruction(
+                    "mintTo",
+                    json!({ "account": token_account.to_string(), "mint": MOBILE_MINT, "amount": "50" }),
+                ),
+                token_instruction(
+                    "burnChecked",
+                    json!({
+                        "account": token_account.to_string(),
+                        "mint": DC_MINT,
+                        "tokenAmount": { "amount": "20", "decimals": 0 },
+                    }),
+                ),
+                // of a token that is not Helium's
+                token_instruction(
+                    "burn",
+                    json!({ "account": token_account.to_string(), "mint": unknown_mint, "amount": "1" }),
+                ),
+            ],
+        );
+        assert_eq!(
+            events(&account, &[token_account], &transaction),
+            json!([
+                {
+                    "type": "transfer",
+                    "token": "mobile",
+                    "amount": 50,
+                    "from": null,
+                    "to": account.to_string(),
+                    "incoming": true,
+                },
+                { "type": "burn", "token": "dc", "amount": 20 },
+            ])
+        );
+    }
+}
@@ -3,6 +3,7 @@ use super::super::{
     rpc,
 };
 use super::*;
+use anchor_lang::solana_program::pubkey::Pubkey;
 use chrono::{Datelike, Utc};
 
 #[derive(Debug)]
@@ -56,7 +57,7 @@ pub async fn get(
     Extension(memory): Extension<Arc<Mutex<Memory>>>,
     Extension(stakes_memory): Extension<Arc<Mutex<Option<positions::Memory>>>>,
 ) -> HandlerResult {
-    let (mobile_vehnt, iot_vehnt, ts) = {
+    let (daos, vehnt, ts) = {
         let stakes_memory = stakes_memory.lock().await;
         if stakes_memory.is_none() {
             return Err((
@@ -65,11 +66,16 @@ pub async fn get(
             ));
         }
         let stakes_memory = stakes_memory.as_ref().unwrap();
-        (
-            stakes_memory.latest_data.stats.mobile.total.vehnt,
-            stakes_memory.latest_data.stats.iot.total.vehnt,
-            stakes_memory.latest_data.stats.timestamp,
-        )
+        let stats = &stakes_memory.latest_data.stats;
+        let vehnt: Vec<(Pubkey, u128)> = stakes_memory
+            .daos()
+            .sub_daos()
+            .map(|dao| {
+                let vehnt = stats.sub_daos.get(&dao.symbol).map_or(0, |s| s.total.vehnt);
+                (dao.key, vehnt)
+            })
+            .collect();
+        (stakes_memory.daos().clone(), vehnt, stats.timestamp)
     };
     let mut data: Vec<EpochSummary> = {
         // we do a deep copy because we will be mutating the data
@@ -79,9 +85,21 @@ pub async fn get(
 
     // we take most recent delegated_stakes data and make a future epoch out of it
     let last_epoch = data[data.len() - 1].epoch + 1;
-    let current_stats =
-        EpochSummary::from_partial_data(last_epoch, mobile_vehnt, iot_vehnt, ts).unwrap();
+    let current_stats = EpochSummary::from_partial_data(last_epoch, vehnt, ts).unwrap();
     data.push(current_stats);
 
-    Ok(response::Json(json!(data)).into())
+    match data
+        .iter()
+        .map(|summary| summary.to_json(&daos))
+        .collect::<Result<Vec<Value>>>()
+    {
+        Ok(data) => Ok(response::Json(json!(data)).into()),
+        Err(e) => {
+            println!("Error serializing epoch info: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error serializing epoch info".to_string(),
+            ))
+        }
+    }
 }
@@ -41,8 +41,11 @@ impl From<response::Redirect> for MyResponse {
 }
 
 pub mod accounts;
+mod activity;
 mod epoch_info;
 mod positions;
+mod rpc_metrics;
+mod signatures;
 
 use axum::response::Response;
 use std::sync::Arc;
@@ -51,7 +54,12 @@ use tokio::sync::Mutex;
 const DATA_NOT_INIT_MSG: &str = "Data not initialized yet. Please try again in a few minutes.";
 
 #[derive(Debug, Clone, clap::Args)]
-pub struct Server {}
+pub struct Server {
+    /// Apply position changes as they happen, from websocket subscriptions
+    /// to the node, on top of the periodic refresh
+    #[arg(long)]
+    live: bool,
+}
 
 impl Server {
     pub async fn run(self, rpc_client: rpc::Client) -> Result {
@@ -71,18 +79,9 @@ impl Server {
         // build our application with a route
         let app = Router::new()
             .route("/v1/accounts/:account", get(accounts::get_account))
-            .route(
-                "/v1/accounts/vehnt/top",
-                get(accounts::get_top_vehnt_accounts),
-            )
-            .route(
-                "/v1/accounts/veiot/top",
-                get(accounts::get_top_veiot_accounts),
-            )
-            .route(
-                "/v1/accounts/vemobile/top",
-                get(accounts::get_top_vemobile_accounts),
-            )
+            .route("/v1/accounts/:account/activity", get(activity::get))
+            // the DAO by the name of its voting power, such as `veiot`
+            .route("/v1/accounts/:account/top", get(accounts::get_top_accounts))
             .route("/v1/delegated_stakes", get(positions::delegated_stakes))
             .route(
                 "/v1/delegated_stakes/csv",
@@ -93,7 +92,13 @@ impl Server {
                 get(positions::vehnt_positions_metadata),
             )
             .route("/v1/positions", get(positions::vehnt_positions))
-            .route("/v1/positions/:position", get(positions::vehnt_position))
+            // a DAO, or a veHNT position as before DAOs were discovered
+            .route("/v1/positions/:dao", get(positions::dao_positions))
+            .route("/v1/positions/:dao/:position", get(positions::dao_position))
+            .route(
+                "/v1/positions/:dao/:position/history",
+                get(positions::dao_position_history),
+            )
             .route(
                 "/v1/positions/info",
                 get(positions::vehnt_positions_metadata),
@@ -104,24 +109,19 @@ impl Server {
                 get(positions::vehnt_position),
             )
             .route(
-                "/v1/positions/vehnt/metadata",
-                get(positions::vehnt_positions_metadata),
+                "/v1/positions/vehnt/:position/history",
+                get(positions::vehnt_position_history),
             )
-            .route("/v1/positions/veiot", get(positions::veiot_positions))
             .route(
-                "/v1/positions/veiot/:position",
-                get(positions::veiot_position),
-            )
-            .route("/v1/positions/vemobile", get(positions::vemobile_positions))
-            .route(
-                "/v1/positions/vemobile/:position",
-                get(positions::vemobile_position),
+                "/v1/positions/vehnt/metadata",
+                get(positions::vehnt_positions_metadata),
             )
             .route(
                 "/v1/positions/csv",
                 get(positions::server_latest_positions_as_csv),
             )
             .route("/v1/epoch/info", get(epoch_info::get))
+            .route("/v1/rpc/metrics", get(rpc_metrics::get))
             .layer(Extension(rpc_client.clone()))
             .layer(Extension(positions_memory.clone()))
             .layer(Extension(epoch_info_memory.clone()));
@@ -130,6 +130,8 @@ impl Server {
         println!("Binding to port {}...", server_endpoint);
         let addr = std::net::SocketAddr::from(([0, 0, 0, 0], server_endpoint.parse().unwrap()));
         tokio::select!(
+            result = positions::follow_positions(rpc_client.clone(), rpc::ws_endpoint_from_env(),
+                positions_memory.clone(), epoch_info_memory.clone()), if self.live => result,
             result = positions::get_positions(rpc_client.clone(), positions_memory,
                 epoch_info_memory.clone()) => result,
             result = epoch_info::get_epoch_info(rpc_client, epoch_info_memory) => result,
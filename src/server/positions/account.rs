@@ -6,38 +6,92 @@ pub struct Account {
     pub positions: Positions,
 }
 
+/// The keys of an owner's positions, by the name of the voting power of
+/// their DAO
 #[derive(Debug, Default)]
-pub struct Positions {
-    pub vehnt: Vec<Pubkey>,
-    pub veiot: Vec<Pubkey>,
-    pub vemobile: Vec<Pubkey>,
-}
+pub struct Positions(BTreeMap<String, Vec<Pubkey>>);
+
+impl Positions {
+    pub fn of(&self, dao: &Dao) -> &[Pubkey] {
+        self.0
+            .get(&dao.ve_name())
+            .map_or(&[], |keys| keys.as_slice())
+    }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum Dao {
-    Hnt,
-    Iot,
-    Mobile,
+    pub fn of_mut(&mut self, dao: &Dao) -> &mut Vec<Pubkey> {
+        self.0.entry(dao.ve_name()).or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(|keys| keys.is_empty())
+    }
 }
 
-#[derive(Debug, Default, Copy, Clone, serde::Serialize)]
+#[derive(Debug, Default, Clone, serde::Serialize)]
 pub struct LockedBalances {
     pub vehnt: VehntBalance,
     pub locked_hnt: u64,
-    // pending from vehnt delegation
-    pub pending_iot: u64,
-    pub pending_mobile: u64,
+    // pending rewards of vehnt delegation, as `pending_{symbol}`
+    #[serde(flatten)]
+    pub pending: BTreeMap<String, u64>,
 
-    // subdao locks and voting shares
-    pub veiot: u128,
-    pub locked_iot: u64,
-    pub vemobile: u128,
-    pub locked_mobile: u64,
+    // subdao voting shares and locks, as `ve{symbol}` and `locked_{symbol}`
+    #[serde(flatten)]
+    pub ve: BTreeMap<String, u128>,
+    #[serde(flatten)]
+    pub locked: BTreeMap<String, u64>,
+}
+
+impl LockedBalances {
+    /// The voting power of the owner in a DAO
+    pub fn ve_of(&self, dao: &Dao) -> u128 {
+        if dao.is_sub_dao {
+            self.ve.get(&dao.ve_name()).copied().unwrap_or_default()
+        } else {
+            self.vehnt.total
+        }
+    }
+
+    /// The tokens locked in the positions of a subDAO, by its symbol
+    pub fn locked_of(&self, symbol: &str) -> u64 {
+        self.locked
+            .get(&format!("locked_{symbol}"))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The rewards of a subDAO pending for the delegated positions, by its
+    /// symbol
+    pub fn pending_of(&self, symbol: &str) -> u64 {
+        self.pending
+            .get(&format!("pending_{symbol}"))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Lists every subDAO, with zero balances for those the owner has no
+    /// positions in or delegations to
+    pub fn fill(&mut self, daos: &Daos) {
+        for dao in daos.sub_daos() {
+            self.vehnt
+                .delegated
+                .entry(format!("{}_delegated", dao.symbol))
+                .or_default();
+            self.pending
+                .entry(format!("pending_{}", dao.symbol))
+                .or_default();
+            self.ve.entry(dao.ve_name()).or_default();
+            self.locked
+                .entry(format!("locked_{}", dao.symbol))
+                .or_default();
+        }
+    }
 }
 
 impl Account {
     pub fn initialize_with_element(
-        dao: Dao,
+        daos: &Daos,
+        dao: &Dao,
         positions: &HashMap<Pubkey, Position>,
         pubkey: Pubkey,
     ) -> Result<Self> {
@@ -45,13 +99,15 @@ impl Account {
             balances: LockedBalances::default(),
             positions: Positions::default(),
         };
-        s.push_entry(dao, positions, pubkey)?;
+        s.push_entry(daos, dao, positions, pubkey)?;
         Ok(s)
     }
 
+    /// Adds a position of `dao`, one of `daos`, to the account
     pub fn push_entry(
         &mut self,
-        dao: Dao,
+        daos: &Daos,
+        dao: &Dao,
         positions: &HashMap<Pubkey, Position>,
         pubkey: Pubkey,
     ) -> Result {
@@ -59,44 +115,47 @@ impl Account {
             None => {
                 return Err(Error::MissingPosition { position: pubkey });
             }
-            Some(p) => match dao {
-                Dao::Iot => {
-                    self.balances.locked_iot += p.locked_tokens;
-                    self.balances.veiot += p.voting_weight;
-                    self.positions.veiot.push(pubkey);
-                }
-                Dao::Mobile => {
-                    self.balances.locked_mobile += p.locked_tokens;
-                    self.balances.vemobile += p.voting_weight;
-                    self.positions.vemobile.push(pubkey);
-                }
-                Dao::Hnt => {
-                    self.balances.locked_hnt += p.locked_tokens;
-                    self.balances.vehnt.total += p.voting_weight;
-                    self.positions.vehnt.push(pubkey);
+            Some(p) if dao.is_sub_dao => {
+                *self
+                    .balances
+                    .locked
+                    .entry(format!("locked_{}", dao.symbol))
+                    .or_default() += p.locked_tokens;
+                *self.balances.ve.entry(dao.ve_name()).or_default() += p.voting_weight;
+                self.positions.of_mut(dao).push(pubkey);
+            }
+            Some(p) => {
+                self.balances.locked_hnt += p.locked_tokens;
+                self.balances.vehnt.total += p.voting_weight;
+                self.positions.of_mut(dao).push(pubkey);
 
-                    if let Some(delegated) = &p.delegated {
-                        match delegated.sub_dao {
-                            SubDao::Iot => {
-                                self.balances.vehnt.iot_delegated += p.voting_weight;
-                                self.balances.pending_iot += delegated.pending_rewards;
-                            }
-                            SubDao::Mobile => {
-                                self.balances.vehnt.mobile_delegated += p.voting_weight;
-                                self.balances.pending_mobile += delegated.pending_rewards;
-                            }
-                            SubDao::Unknown => {
-                                println!(
-                                        "Unknown subdao for delegated position {} for account {pubkey}!",
-                                        p.position_key
-                                    );
-                            }
+                if let Some(delegated) = &p.delegated {
+                    let sub_dao = Pubkey::from_str(&delegated.sub_dao_key)
+                        .ok()
+                        .and_then(|key| daos.by_key(&key));
+                    match sub_dao {
+                        Some(sub_dao) => {
+                            *self
+                                .balances
+                                .vehnt
+                                .delegated
+                                .entry(format!("{}_delegated", sub_dao.symbol))
+                                .or_default() += p.voting_weight;
+                            *self
+                                .balances
+                                .pending
+                                .entry(format!("pending_{}", sub_dao.symbol))
+                                .or_default() += delegated.pending_rewards;
                         }
-                    } else {
-                        self.balances.vehnt.undelegated += p.voting_weight;
+                        None => println!(
+                            "Unknown subdao {} for delegated position {} for account {pubkey}!",
+                            delegated.sub_dao_key, p.position_key
+                        ),
                     }
+                } else {
+                    self.balances.vehnt.undelegated += p.voting_weight;
                 }
-            },
+            }
         }
         Ok(())
     }
@@ -0,0 +1,637 @@
+use super::*;
+use crate::daos::Daos;
+use crate::decoder::{self, Action, DecodedInstruction};
+use crate::server::signatures::{self, PageParams};
+use crate::types::HELIUM_DAO_ID;
+use std::collections::HashSet;
+
+/// Something that happened to a position, in the transaction that did it
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PositionEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    Created {
+        lockup_type: LockupType,
+        periods: u32,
+    },
+    Deposited {
+        amount: u64,
+    },
+    Withdrawn {
+        amount: u64,
+    },
+    /// The lockup was reset, usually to extend it
+    Extended {
+        lockup_type: LockupType,
+        periods: u32,
+    },
+    /// Tokens were moved to a position created by the same transaction
+    Split {
+        from: String,
+        to: String,
+        amount: u64,
+    },
+    /// Tokens were moved out of a position closed by the same transaction
+    Merged {
+        from: String,
+        to: String,
+        amount: u64,
+    },
+    Transferred {
+        from: String,
+        to: String,
+        amount: u64,
+    },
+    /// The subDAOs of delegation events go by their symbol, or by their
+    /// account if they were not discovered
+    Delegated {
+        sub_dao: String,
+    },
+    /// Undelegated and delegated again by the same transaction
+    ChangedSubDao {
+        from: String,
+        to: String,
+    },
+    Undelegated {
+        sub_dao: String,
+    },
+    RewardsClaimed {
+        sub_dao: String,
+        epoch: u64,
+        /// `None` if the transfer of the rewards was not found
+        amount: Option<u64>,
+    },
+    Closed,
+}
+
+/// The delegated position account of a position, which exists while the
+/// position is delegated
+pub fn delegated_position_key(position: &Pubkey) -> Result<Pubkey> {
+    let helium_dao_id = Pubkey::from_str(HELIUM_DAO_ID)?;
+    Ok(Pubkey::find_program_address(&[b"delegated_position", position.as_ref()], &helium_dao_id).0)
+}
+
+/// Fetches a page of the transactions of the given accounts, newest first,
+/// and lists the events of `position` in them, along with the cursor of the
+/// next page. Failed transactions are skipped.
+pub async fn get_position_history(
+    rpc_client: &rpc::Client,
+    daos: &Daos,
+    position: &Pubkey,
+    addresses: &[Pubkey],
+    before: Option<String>,
+    limit: usize,
+) -> Result<(Vec<PositionEvent>, Option<String>)> {
+    let (signatures, next) =
+        signatures::signatures_page(rpc_client, addresses, before, limit).await?;
+    let signatures: Vec<_> = signatures.into_iter().filter(|s| s.err.is_none()).collect();
+    let transactions = signatures::get_transactions(rpc_client, &signatures).await?;
+
+    let mut events = Vec::new();
+    for (signature, transaction) in signatures.iter().zip(transactions) {
+        // the node may have pruned the transaction
+        if let Some(transaction) = transaction {
+            events.extend(position_events(
+                &signature.signature,
+                daos,
+                position,
+                &transaction,
+            )?);
+        }
+    }
+    Ok((events, next))
+}
+
+/// The events of `position` in a transaction
+pub fn position_events(
+    signature: &str,
+    daos: &Daos,
+    position: &Pubkey,
+    transaction: &rpc::Transaction,
+) -> Result<Vec<PositionEvent>> {
+    let decoded = decoder::decode_transaction(transaction)?;
+    let created: HashSet<Pubkey> = decoded
+        .iter()
+        .filter(|i| matches!(i.action, Action::CreatePosition { .. }))
+        .filter_map(|i| i.account("position"))
+        .collect();
+    let closed: HashSet<Pubkey> = decoded
+        .iter()
+        .filter(|i| i.action == Action::ClosePosition)
+        .filter_map(|i| i.account("position"))
+        .collect();
+    let concerns = |i: &DecodedInstruction, action: Action| {
+        i.action == action && i.account("position").as_ref() == Some(position)
+    };
+    let delegated_to = decoded
+        .iter()
+        .find(|i| concerns(i, Action::Delegate))
+        .map(|i| sub_dao(i, daos));
+    let undelegated_from = decoded
+        .iter()
+        .find(|i| concerns(i, Action::Undelegate))
+        .map(|i| sub_dao(i, daos));
+
+    let mut events = Vec::new();
+    for instruction in &decoded {
+        let kind = match &instruction.action {
+            Action::Transfer { amount } => {
+                let (Some(from), Some(to)) = (
+                    instruction.account("source_position"),
+                    instruction.account("target_position"),
+                ) else {
+                    continue;
+                };
+                if from != *position && to != *position {
+                    continue;
+                }
+                let (from_key, to_key, amount) = (from.to_string(), to.to_string(), *amount);
+                if created.contains(&to) {
+                    EventKind::Split {
+                        from: from_key,
+                        to: to_key,
+                        amount,
+                    }
+                } else if closed.contains(&from) {
+                    EventKind::Merged {
+                        from: from_key,
+                        to: to_key,
+                        amount,
+                    }
+                } else {
+                    EventKind::Transferred {
+                        from: from_key,
+                        to: to_key,
+                        amount,
+                    }
+                }
+            }
+            _ if instruction.account("position").as_ref() != Some(position) => continue,
+            Action::CreatePosition {
+                lockup_type,
+                periods,
+            } => EventKind::Created {
+                lockup_type: *lockup_type,
+                periods: *periods,
+            },
+            Action::Deposit { amount } => EventKind::Deposited { amount: *amount },
+            Action::Withdraw { amount } => EventKind::Withdrawn { amount: *amount },
+            Action::ResetLockup {
+                lockup_type,
+                periods,
+            } => EventKind::Extended {
+                lockup_type: *lockup_type,
+                periods: *periods,
+            },
+            Action::Delegate => match undelegated_from {
+                // reported with the undelegation
+                Some(_) => continue,
+                None => EventKind::Delegated {
+                    sub_dao: sub_dao(instruction, daos),
+                },
+            },
+            Action::Undelegate => match &delegated_to {
+                Some(to) => EventKind::ChangedSubDao {
+                    from: sub_dao(instruction, daos),
+                    to: to.clone(),
+                },
+                None => EventKind::Undelegated {
+                    sub_dao: sub_dao(instruction, daos),
+                },
+            },
+            Action::ClaimRewards { epoch } => EventKind::RewardsClaimed {
+                sub_dao: sub_dao(instruction, daos),
+                epoch: *epoch,
+                amount: instruction
+                    .account("delegator_ata")
+                    .and_then(|ata| amount_received(transaction, instruction.index, &ata)),
+            },
+            Action::ClosePosition => EventKind::Closed,
+        };
+        events.push(PositionEvent {
+            signature: signature.to_string(),
+            slot: transaction.slot,
+            block_time: transaction.block_time,
+            kind,
+        });
+    }
+    Ok(events)
+}
+
+fn sub_dao(instruction: &DecodedInstruction, daos: &Daos) -> String {
+    instruction
+        .account("sub_dao")
+        .map_or_else(|| "unknown".to_string(), |key| daos.name_of(&key))
+}
+
+/// The tokens transferred or minted to `account` by the instructions that
+/// the top-level instruction at `index` invoked
+fn amount_received(transaction: &rpc::Transaction, index: usize, account: &Pubkey) -> Option<u64> {
+    let account = account.to_string();
+    let instructions = transaction
+        .meta
+        .as_ref()?
+        .inner_instructions
+        .as_ref()?
+        .iter()
+        .filter(|inner| inner.index as usize == index)
+        .flat_map(|inner| inner.instructions.iter());
+    let mut received = None;
+    for instruction in instructions {
+        let parsed = &instruction["parsed"];
+        let info = &parsed["info"];
+        let to = match parsed["type"].as_str() {
+            Some("transfer") | Some("transferChecked") => &info["destination"],
+            Some("mintTo") | Some("mintToChecked") => &info["account"],
+            _ => continue,
+        };
+        if to.as_str() != Some(account.as_str()) {
+            continue;
+        }
+        let amount = info["amount"]
+            .as_str()
+            .or_else(|| info["tokenAmount"]["amount"].as_str())
+            .and_then(|amount| amount.parse::<u64>().ok())?;
+        received = Some(received.unwrap_or(0) + amount);
+    }
+    received
+}
+
+pub async fn vehnt_position_history(
+    Extension(rpc_client): Extension<Arc<rpc::Client>>,
+    Extension(memory): Extension<Arc<Mutex<Option<Memory>>>>,
+    Path(position): Path<String>,
+    Query(params): Query<PageParams>,
+) -> HandlerResult {
+    // the history is still served before the DAOs are discovered, with
+    // subDAOs by their account
+    let daos = memory
+        .lock()
+        .await
+        .as_ref()
+        .map(|memory| memory.daos().clone())
+        .unwrap_or_default();
+    position_history(&rpc_client, &daos, &position, true, params).await
+}
+
+pub async fn dao_position_history(
+    Extension(rpc_client): Extension<Arc<rpc::Client>>,
+    Extension(memory): Extension<Arc<Mutex<Option<Memory>>>>,
+    Path((dao, position)): Path<(String, String)>,
+    Query(params): Query<PageParams>,
+) -> HandlerResult {
+    let (daos, is_sub_dao) = {
+        let memory = memory.lock().await;
+        let Some(memory) = memory.as_ref() else {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                DATA_NOT_INIT_MSG.to_string(),
+            ));
+        };
+        match memory.daos().by_name(&dao) {
+            Some(dao) => (memory.daos().clone(), dao.is_sub_dao),
+            None => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    format!("\"{dao}\" is not a known DAO"),
+                ))
+            }
+        }
+    };
+    position_history(&rpc_client, &daos, &position, !is_sub_dao, params).await
+}
+
+/// Only veHNT positions are delegated, so the history of the delegated
+/// position is only fetched for those
+async fn position_history(
+    rpc_client: &rpc::Client,
+    daos: &Daos,
+    position: &str,
+    delegated: bool,
+    params: PageParams,
+) -> HandlerResult {
+    let Ok(pubkey) = Pubkey::from_str(position) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("\"{position}\" is not a valid base58 encoded Solana pubkey"),
+        ));
+    };
+    let history = async {
+        let delegated_position = if delegated {
+            Some(delegated_position_key(&pubkey)?)
+        } else {
+            None
+        };
+        let mut addresses = vec![pubkey];
+        addresses.extend(delegated_position);
+        let limit = params.limit();
+        let (events, next) =
+            get_position_history(rpc_client, daos, &pubkey, &addresses, params.before, limit)
+                .await?;
+        Ok::<_, Error>((delegated_position, events, next))
+    };
+    match history.await {
+        Ok((delegated_position, events, next)) => Ok(response::Json(json!({
+            "position": position,
+            "delegated_position": delegated_position.map(|key| key.to_string()),
+            "events": events,
+            "next": next,
+        }))
+        .into()),
+        Err(e) => {
+            println!("Error fetching history of position {position}: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error fetching history of position {position}"),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::daos::Dao;
+    use crate::types::HELIUM_VSR_ID;
+    use anchor_lang::InstructionData;
+    use helium_anchor_gen::{
+        helium_sub_daos::{instruction as dao, ClaimRewardsArgsV0},
+        voter_stake_registry::{
+            instruction as vsr, InitializePositionArgsV0, LockupKind, TransferArgsV0,
+        },
+    };
+    use rpc::ParsedInstruction;
+
+    /// An instruction with `len` accounts, of which those in `at` are given
+    fn instruction(
+        program_id: &str,
+        data: Vec<u8>,
+        len: usize,
+        at: &[(usize, Pubkey)],
+    ) -> ParsedInstruction {
+        let mut accounts: Vec<String> =
+            (0..len).map(|_| Pubkey::new_unique().to_string()).collect();
+        for (i, key) in at {
+            accounts[*i] = key.to_string();
+        }
+        ParsedInstruction::Raw {
+            program_id: Pubkey::from_str(program_id).unwrap(),
+            accounts,
+            data: bs58::encode(data).into_string(),
+        }
+    }
+
+    fn transaction(
+        instructions: Vec<ParsedInstruction>,
+        inner_instructions: Vec<rpc::InnerInstructions>,
+    ) -> rpc::Transaction {
+        rpc::Transaction {
+            slot: 10,
+            block_time: Some(1_700_000_000),
+            meta: Some(rpc::TransactionMeta {
+                err: None,
+                fee: 5000,
+                pre_balances: vec![],
+                post_balances: vec![],
+                inner_instructions: Some(inner_instructions),
+                log_messages: None,
+                pre_token_balances: None,
+                post_token_balances: None,
+            }),
+            transaction: rpc::EncodedTransaction::Json(rpc::ParsedTransaction {
+                signatures: vec!["sig".to_string()],
+                message: rpc::ParsedMessage {
+                    account_keys: vec![],
+                    recent_blockhash: String::new(),
+                    instructions,
+                },
+            }),
+            version: None,
+        }
+    }
+
+    fn dao(symbol: &str, is_sub_dao: bool) -> Dao {
+        Dao {
+            symbol: symbol.to_string(),
+            key: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            decimals: 6,
+            registrar: Pubkey::new_unique(),
+            is_sub_dao,
+        }
+    }
+
+    /// The Helium DAO and the `iot` and `mobile` subDAOs
+    fn daos() -> Daos {
+        Daos::new(vec![
+            dao("hnt", false),
+            dao("iot", true),
+            dao("mobile", true),
+        ])
+    }
+
+    fn kinds(daos: &Daos, position: &Pubkey, transaction: &rpc::Transaction) -> Vec<EventKind> {
+        position_events("sig", daos, position, transaction)
+            .unwrap()
+            .into_iter()
+            .map(|event| event.kind)
+            .collect()
+    }
+
+    fn transfer(from: Pubkey, to: Pubkey, amount: u64) -> ParsedInstruction {
+        let data = vsr::TransferV0 {
+            args: TransferArgsV0 { amount },
+        }
+        .data();
+        instruction(HELIUM_VSR_ID, data, 12, &[(2, from), (5, to)])
+    }
+
+    fn sub_dao_instruction(data: Vec<u8>, position: Pubkey, sub_dao: Pubkey) -> ParsedInstruction {
+        instruction(HELIUM_DAO_ID, data, 14, &[(1, position), (7, sub_dao)])
+    }
+
+    #[test]
+    fn test_split() {
+        let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let create = vsr::InitializePositionV0 {
+            args: InitializePositionArgsV0 {
+                kind: LockupKind::Cliff,
+                periods: 30,
+            },
+        }
+        .data();
+        let transaction = transaction(
+            vec![
+                instruction(HELIUM_VSR_ID, create, 18, &[(8, to)]),
+                transfer(from, to, 50),
+            ],
+            vec![],
+        );
+        let split = EventKind::Split {
+            from: from.to_string(),
+            to: to.to_string(),
+            amount: 50,
+        };
+        assert_eq!(
+            kinds(&Daos::default(), &from, &transaction),
+            vec![split.clone()]
+        );
+        assert_eq!(
+            kinds(&Daos::default(), &to, &transaction),
+            vec![
+                EventKind::Created {
+                    lockup_type: LockupType::Cliff,
+                    periods: 30,
+                },
+                split,
+            ]
+        );
+        assert!(kinds(&Daos::default(), &Pubkey::new_unique(), &transaction).is_empty());
+    }
+
+    #[test]
+    fn test_merge_and_transfer() {
+        let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let close = instruction(
+            HELIUM_VSR_ID,
+            vsr::ClosePositionV0 {}.data(),
+            7,
+            &[(1, from)],
+        );
+        let merge = transaction(vec![transfer(from, to, 50), close], vec![]);
+        let merged = EventKind::Merged {
+            from: from.to_string(),
+            to: to.to_string(),
+            amount: 50,
+        };
+        assert_eq!(
+            kinds(&Daos::default(), &from, &merge),
+            vec![merged.clone(), EventKind::Closed]
+        );
+        assert_eq!(kinds(&Daos::default(), &to, &merge), vec![merged]);
+
+        // neither position is created nor closed
+        let moved = transaction(vec![transfer(from, to, 5)], vec![]);
+        assert_eq!(
+            kinds(&Daos::default(), &to, &moved),
+            vec![EventKind::Transferred {
+                from: from.to_string(),
+                to: to.to_string(),
+                amount: 5,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_delegation_changes() {
+        let (daos, position) = (daos(), Pubkey::new_unique());
+        let (iot, mobile) = (
+            daos.by_name("iot").unwrap(),
+            daos.by_name("mobile").unwrap(),
+        );
+        let undelegate = sub_dao_instruction(dao::CloseDelegationV0 {}.data(), position, iot.key);
+        let delegate = sub_dao_instruction(dao::DelegateV0 {}.data(), position, mobile.key);
+
+        let changed = transaction(vec![undelegate.clone(), delegate.clone()], vec![]);
+        assert_eq!(
+            kinds(&daos, &position, &changed),
+            vec![EventKind::ChangedSubDao {
+                from: "iot".to_string(),
+                to: "mobile".to_string(),
+            }]
+        );
+        assert_eq!(
+            kinds(&daos, &position, &transaction(vec![delegate], vec![])),
+            vec![EventKind::Delegated {
+                sub_dao: "mobile".to_string()
+            }]
+        );
+        assert_eq!(
+            kinds(&daos, &position, &transaction(vec![undelegate], vec![])),
+            vec![EventKind::Undelegated {
+                sub_dao: "iot".to_string()
+            }]
+        );
+
+        // a subDAO that was not discovered goes by its account
+        let sub_dao = Pubkey::new_unique();
+        let delegate = sub_dao_instruction(dao::DelegateV0 {}.data(), position, sub_dao);
+        assert_eq!(
+            kinds(&daos, &position, &transaction(vec![delegate], vec![])),
+            vec![EventKind::Delegated {
+                sub_dao: sub_dao.to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_claimed_amount() {
+        let (daos, position, ata) = (daos(), Pubkey::new_unique(), Pubkey::new_unique());
+        let sub_dao = daos.by_name("iot").unwrap().key;
+        let claim = instruction(
+            HELIUM_DAO_ID,
+            dao::ClaimRewardsV0 {
+                args: ClaimRewardsArgsV0 { epoch: 19_500 },
+            }
+            .data(),
+            18,
+            &[(0, position), (6, sub_dao), (11, ata)],
+        );
+        let token_program = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+        let invoked = |kind: &str, info: Value| {
+            json!({
+                "program": "spl-token",
+                "programId": token_program,
+                "parsed": { "type": kind, "info": info },
+            })
+        };
+        let inner = rpc::InnerInstructions {
+            index: 0,
+            instructions: vec![
+                invoked(
+                    "transferChecked",
+                    json!({
+                        "destination": ata.to_string(),
+                        "tokenAmount": { "amount": "120" },
+                    }),
+                ),
+                invoked(
+                    "mintTo",
+                    json!({ "account": ata.to_string(), "amount": "3" }),
+                ),
+                // to another account
+                invoked(
+                    "transfer",
+                    json!({ "destination": token_program, "amount": "1000" }),
+                ),
+            ],
+        };
+        let claimed = |amount| {
+            vec![EventKind::RewardsClaimed {
+                sub_dao: "iot".to_string(),
+                epoch: 19_500,
+                amount,
+            }]
+        };
+        assert_eq!(
+            kinds(
+                &daos,
+                &position,
+                &transaction(vec![claim.clone()], vec![inner])
+            ),
+            claimed(Some(123))
+        );
+        assert_eq!(
+            kinds(&daos, &position, &transaction(vec![claim], vec![])),
+            claimed(None)
+        );
+    }
+}
@@ -18,7 +18,7 @@ pub async fn delegated_stakes(
         let memory = memory.as_ref().unwrap();
         if let Some(timestamp) = query.timestamp {
             if let Some(data) = memory.data.get(&timestamp) {
-                Ok(data.vehnt.clone())
+                Ok(data.vehnt().cloned().unwrap_or_default())
             } else {
                 Err((
                     StatusCode::NOT_FOUND,
@@ -26,7 +26,7 @@ pub async fn delegated_stakes(
                 ))
             }
         } else {
-            Ok(memory.latest_data.vehnt.clone())
+            Ok(memory.latest_data.vehnt().cloned().unwrap_or_default())
         }
     }?;
 
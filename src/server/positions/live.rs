@@ -0,0 +1,187 @@
+use super::*;
+use crate::types::{HELIUM_DAO_ID, HELIUM_VSR_ID};
+use anchor_lang::{AccountDeserialize, Discriminator};
+use helium_anchor_gen::{
+    helium_sub_daos::DelegatedPositionV0,
+    voter_stake_registry::{PositionV0, Registrar, VotingMintConfigV0, PRECISION_FACTOR},
+};
+
+/// How long to wait before reconnecting after the websocket closes
+const RECONNECT_DELAY: time::Duration = time::Duration::from_secs(5);
+
+/// Applies changes of positions and delegated positions to the memory as
+/// the node notifies them, so that single positions and account balances
+/// are seconds fresh. Nodes do not notify accounts that a program closes, so
+/// closed positions and undelegations, along with the changes missed while
+/// the connection is down, are caught up by the refresh of `get_positions`.
+pub async fn follow_positions(
+    rpc_client: Arc<rpc::Client>,
+    ws_url: String,
+    memory: Arc<Mutex<Option<Memory>>>,
+    epoch_memory: Arc<Mutex<epoch_info::Memory>>,
+) -> Result {
+    loop {
+        if let Err(e) = follow(&rpc_client, &ws_url, &memory, &epoch_memory).await {
+            println!("Error following positions: {e}");
+        }
+        println!("Reconnecting to {ws_url}...");
+        time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Returns when the connection closes
+async fn follow(
+    rpc_client: &rpc::Client,
+    ws_url: &str,
+    memory: &Mutex<Option<Memory>>,
+    epoch_memory: &Mutex<epoch_info::Memory>,
+) -> Result {
+    let pubsub = rpc_client.pubsub(ws_url).await?;
+    let helium_vsr_id = Pubkey::from_str(HELIUM_VSR_ID)?;
+    let helium_dao_id = Pubkey::from_str(HELIUM_DAO_ID)?;
+    let mut positions = pubsub
+        .program_subscribe(
+            &helium_vsr_id,
+            vec![rpc::GetProgramAccountsFilter::discriminator(
+                &PositionV0::DISCRIMINATOR,
+            )],
+        )
+        .await?;
+    let mut delegated_positions = pubsub
+        .program_subscribe(
+            &helium_dao_id,
+            vec![rpc::GetProgramAccountsFilter::discriminator(
+                &DelegatedPositionV0::DISCRIMINATOR,
+            )],
+        )
+        .await?;
+    println!("Following position changes over {ws_url}");
+    loop {
+        let result = tokio::select! {
+            update = positions.next() => match update {
+                None => return Err(rpc::Error::SubscriptionClosed.into()),
+                Some(update) => match update {
+                    Ok(update) => {
+                        let (key, account) = update.value;
+                        apply_position(rpc_client, memory, key, account).await
+                    }
+                    Err(e) => Err(e.into()),
+                },
+            },
+            update = delegated_positions.next() => match update {
+                None => return Err(rpc::Error::SubscriptionClosed.into()),
+                Some(update) => match update {
+                    Ok(update) => {
+                        let (key, account) = update.value;
+                        apply_delegated_position(rpc_client, memory, epoch_memory, key, account)
+                            .await
+                    }
+                    Err(e) => Err(e.into()),
+                },
+            },
+        };
+        // a failed update is corrected by the next refresh
+        if let Err(e) = result {
+            println!("Error applying position change: {e}");
+        }
+    }
+}
+
+/// The voting mint of a registrar, which never changes once created
+async fn voting_mint(rpc_client: &rpc::Client, registrar: &Pubkey) -> Result<VotingMintConfigV0> {
+    let account = rpc_client
+        .get_multiple_accounts_cached(&[registrar], Some(rpc::CachePolicy::Immutable))
+        .await?
+        .value
+        .pop()
+        .flatten()
+        .ok_or(rpc::Error::AccountNotFound)?;
+    let registrar = Registrar::try_deserialize(&mut account.data.as_slice())?;
+    Ok(registrar.voting_mints[0])
+}
+
+async fn apply_position(
+    rpc_client: &rpc::Client,
+    memory: &Mutex<Option<Memory>>,
+    key: Pubkey,
+    account: rpc::Account,
+) -> Result {
+    let position_v0 = PositionV0::try_deserialize(&mut account.data.as_slice())?;
+    let voting_mint_config = voting_mint(rpc_client, &position_v0.registrar).await?;
+
+    // voting power is computed at the time of the snapshot, like the other
+    // positions, and the delegation is kept until the delegated position
+    // changes or the next refresh. Positions of a DAO discovered since then
+    // wait for the next refresh.
+    let (dao, timestamp, known) = {
+        let memory = memory.lock().await;
+        let Some(memory) = memory.as_ref() else {
+            return Ok(());
+        };
+        let Some(dao) = memory.latest_data.daos.by_mint(&voting_mint_config.mint) else {
+            return Ok(());
+        };
+        (
+            dao.clone(),
+            memory.latest_data.stats.timestamp,
+            memory.get_position(dao, &key).cloned(),
+        )
+    };
+    let (owner, owner_kind) = match &known {
+        Some(known) => (Pubkey::from_str(&known.owner)?, known.owner_kind),
+        None => {
+            let owner = rpc_client.get_owner_by_mint(&position_v0.mint).await?;
+            let owner_kind = match rpc_client.get_owner_kinds(&[&owner]).await {
+                Ok(kinds) => kinds[0],
+                Err(e) => {
+                    println!("Warning: could not get the kind of owner {owner}: {e}");
+                    rpc::OwnerKind::Unknown
+                }
+            };
+            (owner, owner_kind)
+        }
+    };
+    let mut position =
+        Position::try_from_positionv0(owner, key, position_v0, timestamp, &voting_mint_config)
+            .await?;
+    position.voting_weight /= PRECISION_FACTOR;
+    position.owner_kind = owner_kind;
+    position.delegated = known.and_then(|known| known.delegated);
+
+    if let Some(memory) = memory.lock().await.as_mut() {
+        memory.apply_position(&dao, key, position)?;
+    }
+    Ok(())
+}
+
+async fn apply_delegated_position(
+    rpc_client: &rpc::Client,
+    memory: &Mutex<Option<Memory>>,
+    epoch_memory: &Mutex<epoch_info::Memory>,
+    key: Pubkey,
+    account: rpc::Account,
+) -> Result {
+    let delegated_position = DelegatedPositionV0::try_deserialize(&mut account.data.as_slice())?;
+    // pending rewards depend on the position's lockup
+    let position_account = rpc_client.get_account(&delegated_position.position).await?;
+    let position_v0 = PositionV0::try_deserialize(&mut position_account.data.as_slice())?;
+    let voting_mint_config = voting_mint(rpc_client, &position_v0.registrar).await?;
+    let epochs = epoch_memory.lock().await.latest_data.clone();
+    let daos = match memory.lock().await.as_ref() {
+        Some(memory) => memory.daos().clone(),
+        None => return Ok(()),
+    };
+    let delegated = DelegatedPosition::try_from_delegated_position_v0(
+        key,
+        delegated_position,
+        &daos,
+        &epochs,
+        &position_v0,
+        &voting_mint_config,
+    )?;
+
+    if let Some(memory) = memory.lock().await.as_mut() {
+        memory.set_delegation(&delegated_position.position, Some(delegated))?;
+    }
+    Ok(())
+}
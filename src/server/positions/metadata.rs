@@ -33,5 +33,16 @@ pub async fn vehnt_positions_metadata(
         }
     }?;
 
-    Ok(response::Json(json!(data.stats)).into())
+    #[derive(serde::Serialize)]
+    struct MetadataResponse<'a> {
+        #[serde(flatten)]
+        stats: &'a crate::cli::positions::Metadata,
+        slots: Option<rpc::SlotRange>,
+    }
+
+    Ok(response::Json(json!(MetadataResponse {
+        stats: &data.stats,
+        slots: data.slots,
+    }))
+    .into())
 }
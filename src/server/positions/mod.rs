@@ -1,13 +1,16 @@
 use super::positions;
 use super::{accounts::VehntBalance, *};
-pub use crate::cli::positions::{AllPositionsData, LockupType, Position, PositionOwners};
-use crate::types::SubDao;
+pub use crate::cli::positions::{
+    AllPositionsData, DelegatedPosition, LockupType, Position, PositionOwners, RefreshCache,
+};
+use crate::daos::{Dao, Daos};
 use anchor_lang::solana_program::pubkey::Pubkey;
 use axum::{
     extract::Path,
     http::{header, HeaderValue},
     response::{IntoResponse, Response},
 };
+use std::collections::BTreeMap;
 use std::ops::DerefMut;
 use std::str::FromStr;
 use tokio::{fs::File, io::AsyncReadExt};
@@ -27,13 +30,18 @@ pub use metadata::vehnt_positions_metadata;
 mod timer;
 pub use timer::get_positions;
 
+mod history;
+pub use history::{dao_position_history, vehnt_position_history};
+
+mod live;
+pub use live::follow_positions;
+
 #[derive(Debug)]
 pub struct Memory {
     data: HashMap<i64, Arc<AllPositionsData>>,
 
-    pub vehnt_positions: HashMap<Pubkey, Position>,
-    pub veiot_positions: HashMap<Pubkey, Position>,
-    pub vemobile_positions: HashMap<Pubkey, Position>,
+    /// The positions of each DAO, by the name of its voting power
+    pub positions: HashMap<String, HashMap<Pubkey, Position>>,
 
     pub latest_data: Arc<AllPositionsData>,
     pub positions_by_owner: HashMap<Pubkey, Account>,
@@ -43,21 +51,19 @@ impl Memory {
     fn latest_delegated_positions_file(&self) -> String {
         format!(
             "./delegated_positions_{}.csv",
-            self.latest_data.vehnt.timestamp
+            self.latest_data.stats.timestamp
         )
     }
 
     fn latest_positions_file(&self) -> String {
-        format!("./positions_{}.csv", self.latest_data.vehnt.timestamp)
+        format!("./positions_{}.csv", self.latest_data.stats.timestamp)
     }
 
     #[allow(unused)]
     pub async fn new(latest_data: positions::AllPositionsData) -> Result<Memory> {
         let mut memory = Self {
             data: HashMap::new(),
-            vehnt_positions: HashMap::new(),
-            veiot_positions: HashMap::new(),
-            vemobile_positions: HashMap::new(),
+            positions: HashMap::new(),
             latest_data: Arc::new(positions::AllPositionsData::default()),
             positions_by_owner: HashMap::new(),
         };
@@ -85,14 +91,15 @@ impl Memory {
             pub vehnt: u128,
             pub lockup_type: &'a LockupType,
             pub delegated_position_key: Option<&'a str>,
-            pub delegated_sub_dao: Option<SubDao>,
+            pub delegated_sub_dao: Option<&'a str>,
             pub delagated_last_claimed_epoch: Option<u64>,
             pub delegated_pending_rewards: Option<u64>,
         }
 
         let mut position_wtr = Writer::from_path(self.latest_positions_file())?;
         let mut delegated_position_wtr = Writer::from_path(self.latest_delegated_positions_file())?;
-        for position in self.latest_data.vehnt.positions.iter() {
+        let vehnt = self.latest_data.vehnt();
+        for position in vehnt.iter().flat_map(|vehnt| vehnt.positions.iter()) {
             if let Some(delegated) = &position.delegated {
                 position_wtr.serialize(Position {
                     position_key: &position.position_key,
@@ -105,7 +112,7 @@ impl Memory {
                     vehnt: position.voting_weight,
                     lockup_type: &position.lockup_type,
                     delegated_position_key: Some(&delegated.delegated_position_key),
-                    delegated_sub_dao: Some(delegated.sub_dao),
+                    delegated_sub_dao: Some(&delegated.sub_dao),
                     delagated_last_claimed_epoch: Some(delegated.last_claimed_epoch),
                     delegated_pending_rewards: Some(delegated.pending_rewards),
                 })?;
@@ -127,7 +134,10 @@ impl Memory {
                 })?;
             }
         }
-        for position in self.latest_data.vehnt.delegated_positions.iter() {
+        for position in vehnt
+            .iter()
+            .flat_map(|vehnt| vehnt.delegated_positions.iter())
+        {
             delegated_position_wtr.serialize(position)?;
         }
         Ok(())
@@ -137,14 +147,19 @@ impl Memory {
         rpc_client: &Arc<rpc::Client>,
         epoch_summaries: Arc<Mutex<epoch_info::Memory>>,
         position_owner_map: &mut PositionOwners,
+        refresh_cache: &mut RefreshCache,
     ) -> Result<AllPositionsData> {
         let epoch_summaries = {
             let lock = epoch_summaries.lock().await;
             lock.latest_data.clone()
         };
-        let mut latest_data =
-            crate::cli::positions::get_data(rpc_client, epoch_summaries, position_owner_map)
-                .await?;
+        let mut latest_data = crate::cli::positions::get_data(
+            rpc_client,
+            epoch_summaries,
+            position_owner_map,
+            refresh_cache,
+        )
+        .await?;
         latest_data.scale_down();
         Ok(latest_data)
     }
@@ -157,23 +172,17 @@ impl Memory {
         self.latest_data = latest_data.clone();
 
         // organize into map of positions pubkey to full position data
-        self.vehnt_positions = latest_data
-            .vehnt
-            .positions
-            .iter()
-            .map(|p| (Pubkey::from_str(&p.position_key).unwrap(), p.clone()))
-            .collect();
-        self.veiot_positions = latest_data
-            .veiot
+        self.positions = latest_data
             .positions
             .iter()
-            .map(|p| (Pubkey::from_str(&p.position_key).unwrap(), p.clone()))
-            .collect();
-        self.vemobile_positions = latest_data
-            .vemobile
-            .positions
-            .iter()
-            .map(|p| (Pubkey::from_str(&p.position_key).unwrap(), p.clone()))
+            .map(|(name, data)| {
+                let positions = data
+                    .positions
+                    .iter()
+                    .map(|p| (Pubkey::from_str(&p.position_key).unwrap(), p.clone()))
+                    .collect();
+                (name.clone(), positions)
+            })
             .collect();
 
         // organize into map of owner pubkey to [position pubkey]
@@ -182,52 +191,49 @@ impl Memory {
         fn organize_positions_for_dao(
             positions_by_owner: &mut HashMap<Pubkey, Account>,
             positions: &Vec<Position>,
-            dao: Dao,
+            daos: &Daos,
+            dao: &Dao,
             positions_map: &HashMap<Pubkey, Position>,
         ) -> Result {
             for position in positions {
                 let owner = Pubkey::from_str(&position.owner)?;
                 let position = Pubkey::from_str(&position.position_key)?;
                 if let Some(entry) = positions_by_owner.get_mut(&owner) {
-                    entry.push_entry(dao, positions_map, position)?;
+                    entry.push_entry(daos, dao, positions_map, position)?;
                 } else {
                     positions_by_owner.insert(
                         owner,
-                        Account::initialize_with_element(dao, positions_map, position)?,
+                        Account::initialize_with_element(daos, dao, positions_map, position)?,
                     );
                 }
             }
             Ok(())
         }
-        organize_positions_for_dao(
-            &mut positions_by_owner,
-            &latest_data.vehnt.positions,
-            Dao::Hnt,
-            &self.vehnt_positions,
-        )?;
-        organize_positions_for_dao(
-            &mut positions_by_owner,
-            &latest_data.veiot.positions,
-            Dao::Iot,
-            &self.veiot_positions,
-        )?;
-        organize_positions_for_dao(
-            &mut positions_by_owner,
-            &latest_data.vemobile.positions,
-            Dao::Mobile,
-            &self.vemobile_positions,
-        )?;
+        for dao in latest_data.daos.iter() {
+            if let (Some(data), Some(positions_map)) = (
+                latest_data.positions.get(&dao.ve_name()),
+                self.positions.get(&dao.ve_name()),
+            ) {
+                organize_positions_for_dao(
+                    &mut positions_by_owner,
+                    &data.positions,
+                    &latest_data.daos,
+                    dao,
+                    positions_map,
+                )?;
+            }
+        }
 
         self.positions_by_owner = positions_by_owner;
 
         // start a new Hashmap of all cached positions
         let mut data = HashMap::new();
-        data.insert(latest_data.vehnt.timestamp, latest_data.clone());
+        data.insert(latest_data.stats.timestamp, latest_data.clone());
 
         // Only keep data that is less than 16 minutes old
         let current_time = Utc::now().timestamp();
         for (key, value) in &self.data {
-            if value.vehnt.timestamp > current_time - 60 * 16 {
+            if value.stats.timestamp > current_time - 60 * 16 {
                 data.insert(*key, value.clone());
             }
         }
@@ -245,6 +251,88 @@ impl Memory {
     }
 }
 
+impl Memory {
+    /// The DAOs of the latest refresh
+    pub fn daos(&self) -> &Daos {
+        &self.latest_data.daos
+    }
+
+    pub fn positions_of(&self, dao: &Dao) -> Option<&HashMap<Pubkey, Position>> {
+        self.positions.get(&dao.ve_name())
+    }
+
+    fn positions_of_mut(&mut self, dao: &Dao) -> &mut HashMap<Pubkey, Position> {
+        self.positions.entry(dao.ve_name()).or_default()
+    }
+
+    pub fn get_position(&self, dao: &Dao, key: &Pubkey) -> Option<&Position> {
+        self.positions_of(dao)?.get(key)
+    }
+
+    /// Adds or replaces a single position and updates the balances of its
+    /// owner. The lists of positions, their metadata and the CSV files are
+    /// only updated by the next full refresh.
+    pub fn apply_position(&mut self, dao: &Dao, key: Pubkey, position: Position) -> Result {
+        let owner = Pubkey::from_str(&position.owner)?;
+        let previous_owner = self
+            .positions_of_mut(dao)
+            .insert(key, position)
+            .map(|previous| Pubkey::from_str(&previous.owner))
+            .transpose()?;
+        if let Some(previous_owner) = previous_owner.filter(|previous| *previous != owner) {
+            self.reindex_owner(&previous_owner)?;
+        }
+        let keys = self
+            .positions_by_owner
+            .entry(owner)
+            .or_default()
+            .positions
+            .of_mut(dao);
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+        self.reindex_owner(&owner)
+    }
+
+    /// Sets or clears the delegation of a veHNT position and updates the
+    /// balances of its owner
+    pub fn set_delegation(&mut self, key: &Pubkey, delegated: Option<DelegatedPosition>) -> Result {
+        let Some(dao) = self.daos().dao().cloned() else {
+            return Ok(());
+        };
+        let Some(position) = self.positions_of_mut(&dao).get_mut(key) else {
+            return Ok(());
+        };
+        position.delegated = delegated;
+        let owner = Pubkey::from_str(&position.owner)?;
+        self.reindex_owner(&owner)
+    }
+
+    /// Recomputes the balances of an owner from its positions, dropping
+    /// the positions that are no longer known or no longer owned by it
+    fn reindex_owner(&mut self, owner: &Pubkey) -> Result {
+        let Some(account) = self.positions_by_owner.remove(owner) else {
+            return Ok(());
+        };
+        let owner_str = owner.to_string();
+        let mut reindexed = Account::default();
+        for dao in self.daos().iter() {
+            let Some(positions) = self.positions_of(dao) else {
+                continue;
+            };
+            for key in account.positions.of(dao) {
+                if positions.get(key).is_some_and(|p| p.owner == owner_str) {
+                    reindexed.push_entry(self.daos(), dao, positions, *key)?;
+                }
+            }
+        }
+        if !reindexed.positions.is_empty() {
+            self.positions_by_owner.insert(*owner, reindexed);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PositionParams {
     timestamp: Option<i64>,
@@ -256,27 +344,34 @@ pub async fn vehnt_positions(
     memory: Extension<Arc<Mutex<Option<Memory>>>>,
     query: Query<PositionParams>,
 ) -> HandlerResult {
-    positions(memory, query, Dao::Hnt).await
-}
-
-pub async fn veiot_positions(
-    memory: Extension<Arc<Mutex<Option<Memory>>>>,
-    query: Query<PositionParams>,
-) -> HandlerResult {
-    positions(memory, query, Dao::Iot).await
+    positions(memory, query, "vehnt").await
 }
 
-pub async fn vemobile_positions(
+/// The positions of a DAO, by the name of its voting power or its symbol.
+/// Anything else is taken for the key of a veHNT position, which this route
+/// served before it served every DAO.
+pub async fn dao_positions(
     memory: Extension<Arc<Mutex<Option<Memory>>>>,
     query: Query<PositionParams>,
+    Path(dao): Path<String>,
 ) -> HandlerResult {
-    positions(memory, query, Dao::Mobile).await
+    let known = {
+        let memory = memory.lock().await;
+        memory
+            .as_ref()
+            .is_some_and(|memory| memory.daos().by_name(&dao).is_some())
+    };
+    if known || Pubkey::from_str(&dao).is_err() {
+        positions(memory, query, &dao).await
+    } else {
+        position(memory, "vehnt", &dao).await
+    }
 }
 
 async fn positions(
     Extension(memory): Extension<Arc<Mutex<Option<Memory>>>>,
     query: Query<PositionParams>,
-    dao: Dao,
+    dao: &str,
 ) -> HandlerResult {
     const DEFAULT_LIMIT: usize = 500;
     let query = query.0;
@@ -290,13 +385,9 @@ async fn positions(
     }
     let memory = memory.as_ref().unwrap();
 
-    let data = if let Some(timestamp) = query.timestamp {
+    let all_data = if let Some(timestamp) = query.timestamp {
         if let Some(data) = memory.data.get(&timestamp) {
-            Ok(match dao {
-                Dao::Hnt => &data.vehnt,
-                Dao::Iot => &data.veiot,
-                Dao::Mobile => &data.vemobile,
-            })
+            Ok(data)
         } else {
             Err((
                 StatusCode::NOT_FOUND,
@@ -304,12 +395,14 @@ async fn positions(
             ))
         }
     } else {
-        Ok(match dao {
-            Dao::Hnt => &memory.latest_data.vehnt,
-            Dao::Iot => &memory.latest_data.veiot,
-            Dao::Mobile => &memory.latest_data.vemobile,
-        })
+        Ok(&memory.latest_data)
     }?;
+    let Some(data) = all_data.dao_positions(dao) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("\"{dao}\" is not a known DAO"),
+        ));
+    };
 
     let start = query.start.map_or(0, |start| start);
     if start > data.positions.len() {
@@ -335,12 +428,14 @@ async fn positions(
         pub timestamp: i64,
         pub positions: Vec<Position>,
         pub positions_total_len: usize,
+        pub slots: Option<rpc::SlotRange>,
     }
 
     let data = Data {
         positions_total_len: data.positions_total_len,
         positions,
         timestamp: data.timestamp,
+        slots: all_data.slots,
     };
 
     Ok(response::Json(json!(data)).into())
@@ -348,31 +443,24 @@ async fn positions(
 
 pub async fn vehnt_position(
     memory: Extension<Arc<Mutex<Option<Memory>>>>,
-    p: Path<String>,
-) -> HandlerResult {
-    position(memory, p, Dao::Hnt).await
-}
-
-pub async fn veiot_position(
-    memory: Extension<Arc<Mutex<Option<Memory>>>>,
-    p: Path<String>,
+    Path(position): Path<String>,
 ) -> HandlerResult {
-    position(memory, p, Dao::Iot).await
+    self::position(memory, "vehnt", &position).await
 }
 
-pub async fn vemobile_position(
+pub async fn dao_position(
     memory: Extension<Arc<Mutex<Option<Memory>>>>,
-    p: Path<String>,
+    Path((dao, position)): Path<(String, String)>,
 ) -> HandlerResult {
-    position(memory, p, Dao::Mobile).await
+    self::position(memory, &dao, &position).await
 }
 
-pub async fn position(
+async fn position(
     Extension(memory): Extension<Arc<Mutex<Option<Memory>>>>,
-    Path(position): Path<String>,
-    dao: Dao,
+    dao: &str,
+    position: &str,
 ) -> HandlerResult {
-    if let Ok(pubkey) = Pubkey::from_str(&position) {
+    if let Ok(pubkey) = Pubkey::from_str(position) {
         let memory = memory.lock().await;
         if memory.is_none() {
             return Err((
@@ -381,12 +469,13 @@ pub async fn position(
             ));
         }
         let memory = memory.as_ref().unwrap();
-        let memory = match dao {
-            Dao::Hnt => &memory.vehnt_positions,
-            Dao::Iot => &memory.veiot_positions,
-            Dao::Mobile => &memory.vemobile_positions,
+        let Some(dao) = memory.daos().by_name(dao) else {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("\"{dao}\" is not a known DAO"),
+            ));
         };
-        if let Some(position) = memory.get(&pubkey) {
+        if let Some(position) = memory.get_position(dao, &pubkey) {
             Ok(response::Json(json!(position)).into())
         } else {
             Err((
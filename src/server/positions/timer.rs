@@ -6,11 +6,16 @@ pub async fn get_positions(
     epoch_memory: Arc<Mutex<epoch_info::Memory>>,
 ) -> Result {
     let mut position_owner_map = PositionOwners::default();
+    let mut refresh_cache = RefreshCache::default();
     loop {
         println!("Pulling latest data");
-        let mut latest_data =
-            Memory::pull_latest_data(&rpc_client, epoch_memory.clone(), &mut position_owner_map)
-                .await;
+        let mut latest_data = Memory::pull_latest_data(
+            &rpc_client,
+            epoch_memory.clone(),
+            &mut position_owner_map,
+            &mut refresh_cache,
+        )
+        .await;
         // if the first pull fails, keep trying until it succeeds
         let mut attempts = 0;
         while let Err(e) = latest_data {
@@ -20,6 +25,7 @@ pub async fn get_positions(
                 &rpc_client,
                 epoch_memory.clone(),
                 &mut position_owner_map,
+                &mut refresh_cache,
             )
             .await;
             // if we fail 3 times in a row, back off for 5 minutes
@@ -0,0 +1,12 @@
+use super::*;
+
+/// Per-method RPC metrics since the server started, along with the calls
+/// held back by the rate limiter and for how long
+pub async fn get(Extension(rpc_client): Extension<Arc<rpc::Client>>) -> HandlerResult {
+    Ok(response::Json(json!({
+        "methods": rpc_client.metrics().snapshot(),
+        "rate_limiter": rpc_client.rate_limiter().stats(),
+        "latency_buckets_ms": rpc::LATENCY_BUCKETS_MS,
+    }))
+    .into())
+}
@@ -0,0 +1,284 @@
+use super::*;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use futures::{StreamExt, TryStreamExt};
+use std::collections::HashSet;
+
+const DEFAULT_LIMIT: usize = 25;
+const MAX_LIMIT: usize = 100;
+/// `getTransaction` calls sent at once for a page
+const TRANSACTION_CONCURRENCY: usize = 10;
+
+/// A page of the transactions of an account, newest first
+#[derive(Debug, Deserialize)]
+pub struct PageParams {
+    /// The `next` cursor of the previous page
+    pub before: Option<String>,
+    limit: Option<usize>,
+}
+
+impl PageParams {
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+/// Where a page ends: the last signature listed and its slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cursor {
+    slot: Option<u64>,
+    signature: String,
+}
+
+impl Cursor {
+    /// Cursors are `{slot}:{signature}`. A bare signature is accepted too,
+    /// as a cursor of unknown slot.
+    fn parse(cursor: &str) -> Self {
+        match cursor.split_once(':') {
+            Some((slot, signature)) if slot.parse::<u64>().is_ok() => Self {
+                slot: slot.parse().ok(),
+                signature: signature.to_string(),
+            },
+            _ => Self {
+                slot: None,
+                signature: cursor.to_string(),
+            },
+        }
+    }
+
+    /// Whether `signature` was listed by the page ending at the cursor.
+    /// Pages end with whole slots, so that is any signature of its slot on.
+    fn covers(&self, signature: &rpc::SignatureInfo) -> bool {
+        match self.slot {
+            Some(slot) => signature.slot >= slot,
+            None => signature.signature == self.signature,
+        }
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.slot {
+            Some(slot) => write!(f, "{slot}:{}", self.signature),
+            None => f.write_str(&self.signature),
+        }
+    }
+}
+
+/// The signatures of a page of the transactions of any of `addresses`,
+/// newest first, along with the cursor of the next page if there may be one
+pub async fn signatures_page(
+    rpc_client: &rpc::Client,
+    addresses: &[Pubkey],
+    before: Option<String>,
+    limit: usize,
+) -> Result<(Vec<rpc::SignatureInfo>, Option<String>)> {
+    let cursor = before.as_deref().map(Cursor::parse);
+    let pages = futures::future::try_join_all(
+        addresses
+            .iter()
+            .map(|address| address_page(rpc_client, address, cursor.as_ref(), limit)),
+    )
+    .await?;
+    let (signatures, next) = merge_pages(pages, limit);
+    Ok((signatures, next.map(|cursor| cursor.to_string())))
+}
+
+/// The signatures of `address` after `cursor`, newest first, in whole
+/// slots: a page of `limit` signatures is cut before its oldest slot, which
+/// may go on in the next one, or extended until that slot is complete when
+/// it is the only one. Along with whether there may be older signatures.
+async fn address_page(
+    rpc_client: &rpc::Client,
+    address: &Pubkey,
+    cursor: Option<&Cursor>,
+    limit: usize,
+) -> Result<(Vec<rpc::SignatureInfo>, bool)> {
+    let mut query = rpc::SignaturesQuery {
+        limit: Some(limit),
+        before: cursor.map(|cursor| cursor.signature.clone()),
+        until: None,
+    };
+    let mut signatures: Vec<rpc::SignatureInfo> = Vec::new();
+    loop {
+        let page = rpc_client
+            .get_signatures_for_address_page(address, &query)
+            .await?;
+        let last_page = page.len() < limit;
+        if let Some(last) = page.last() {
+            query.before = Some(last.signature.clone());
+        }
+        signatures.extend(
+            page.into_iter()
+                .filter(|s| !cursor.is_some_and(|cursor| cursor.covers(s))),
+        );
+        if last_page {
+            return Ok((signatures, false));
+        }
+        if let Some(oldest_slot) = signatures.last().map(|s| s.slot) {
+            let complete = signatures
+                .iter()
+                .take_while(|s| s.slot > oldest_slot)
+                .count();
+            if complete > 0 {
+                signatures.truncate(complete);
+                return Ok((signatures, true));
+            }
+        }
+    }
+}
+
+/// Merges the pages of several addresses into one of about `limit`
+/// signatures, newest first. Nodes order the transactions of a slot by their
+/// position in the block, which signatures do not tell, so a slot is always
+/// listed whole, ordered by signature, and the next page starts at the slot
+/// after the last one listed. Only a slot of more than `limit` signatures
+/// makes a page longer.
+fn merge_pages(
+    pages: Vec<(Vec<rpc::SignatureInfo>, bool)>,
+    limit: usize,
+) -> (Vec<rpc::SignatureInfo>, Option<Cursor>) {
+    // slots before this one may be missing signatures of some address
+    let oldest_complete_slot = pages
+        .iter()
+        .filter(|(_, more)| *more)
+        .filter_map(|(page, _)| page.last().map(|s| s.slot))
+        .max();
+    let mut seen = HashSet::new();
+    let mut signatures: Vec<rpc::SignatureInfo> = pages
+        .into_iter()
+        .flat_map(|(page, _)| page)
+        .filter(|s| !oldest_complete_slot.is_some_and(|slot| s.slot < slot))
+        .filter(|s| seen.insert(s.signature.clone()))
+        .collect();
+    signatures.sort_by(|a, b| {
+        b.slot
+            .cmp(&a.slot)
+            .then_with(|| a.signature.cmp(&b.signature))
+    });
+    let mut more = oldest_complete_slot.is_some();
+    if let Some(cut_slot) = signatures.get(limit).map(|s| s.slot) {
+        let mut end = signatures.iter().take_while(|s| s.slot > cut_slot).count();
+        if end == 0 {
+            end = signatures.iter().take_while(|s| s.slot == cut_slot).count();
+        }
+        signatures.truncate(end);
+        more = true;
+    }
+    let next = match more {
+        true => signatures.last().map(|s| Cursor {
+            slot: Some(s.slot),
+            signature: s.signature.clone(),
+        }),
+        false => None,
+    };
+    (signatures, next)
+}
+
+/// The `jsonParsed` transactions of `signatures`, in the same order, fetched
+/// a few at a time. `None` for the transactions the node no longer has.
+pub async fn get_transactions(
+    rpc_client: &rpc::Client,
+    signatures: &[rpc::SignatureInfo],
+) -> Result<Vec<Option<rpc::Transaction>>> {
+    Ok(futures::stream::iter(signatures)
+        .map(|s| rpc_client.get_transaction(&s.signature, rpc::TransactionEncoding::JsonParsed))
+        .buffered(TRANSACTION_CONCURRENCY)
+        .try_collect()
+        .await?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+    use tokio::test;
+
+    /// Answers `getSignaturesForAddress` like a node, from transactions
+    /// listed oldest first, in block order within a slot
+    #[derive(Debug)]
+    struct Node(Vec<(u64, &'static str, Vec<Pubkey>)>);
+
+    #[async_trait::async_trait]
+    impl rpc::Transport for Node {
+        async fn send(&self, body: String) -> rpc::Result<String> {
+            let request: Value = serde_json::from_str(&body).unwrap();
+            let address = Pubkey::from_str(request["params"][0].as_str().unwrap()).unwrap();
+            let config = &request["params"][1];
+            let end = match config["before"].as_str() {
+                Some(before) => self.0.iter().position(|(_, s, _)| *s == before).unwrap(),
+                None => self.0.len(),
+            };
+            let result: Vec<Value> = self.0[..end]
+                .iter()
+                .rev()
+                .filter(|(_, _, addresses)| addresses.contains(&address))
+                .take(config["limit"].as_u64().unwrap() as usize)
+                .map(|(slot, signature, _)| {
+                    json!({ "signature": signature, "slot": slot, "err": null, "memo": null })
+                })
+                .collect();
+            Ok(json!({ "jsonrpc": "2.0", "result": result, "id": request["id"] }).to_string())
+        }
+    }
+
+    #[test]
+    async fn test_signatures_pages() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // signatures of a slot out of their block order, shared by both
+        // addresses, and slots longer than a page
+        let node = Node(vec![
+            (7, "f", vec![b]),
+            (7, "e", vec![a]),
+            (8, "d", vec![a]),
+            (9, "z", vec![a]),
+            (9, "c", vec![b]),
+            (10, "b1", vec![b]),
+            (10, "ab", vec![a, b]),
+            (10, "a1", vec![a]),
+            (10, "a2", vec![a]),
+        ]);
+        let all: Vec<&str> = vec!["a1", "a2", "ab", "b1", "c", "z", "d", "e", "f"];
+        let rpc_client = rpc::Client::new_with_transport(node);
+        for limit in 1..=all.len() + 1 {
+            let mut listed = Vec::new();
+            let mut before = None;
+            loop {
+                let (page, next) = signatures_page(&rpc_client, &[a, b], before, limit)
+                    .await
+                    .unwrap();
+                assert!(!page.is_empty());
+                listed.extend(page.into_iter().map(|s| s.signature));
+                match next {
+                    Some(next) => before = Some(next),
+                    None => break,
+                }
+            }
+            assert_eq!(listed, all, "limit {limit}");
+        }
+    }
+
+    #[test]
+    async fn test_page_ends_with_slot() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let node = Node(vec![
+            (8, "d", vec![b]),
+            (9, "c", vec![a]),
+            (9, "b", vec![b]),
+            (10, "a", vec![a]),
+        ]);
+        let rpc_client = rpc::Client::new_with_transport(node);
+        let (page, next) = signatures_page(&rpc_client, &[a, b], None, 2)
+            .await
+            .unwrap();
+        let page: Vec<String> = page.into_iter().map(|s| s.signature).collect();
+        assert_eq!(page, ["a"]);
+        assert_eq!(next.as_deref(), Some("10:a"));
+        // a bare signature as cursor only skips that signature
+        let (page, next) = signatures_page(&rpc_client, &[a, b], Some("a".to_string()), 3)
+            .await
+            .unwrap();
+        let page: Vec<String> = page.into_iter().map(|s| s.signature).collect();
+        assert_eq!(page, ["b", "c", "d"]);
+        assert_eq!(next, None);
+    }
+}
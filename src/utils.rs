@@ -1,20 +1,17 @@
 use super::types::*;
 
 pub fn format_dnt(dnt: u64) -> String {
-    let dnt = dnt / DNT_DIVIDER as u64;
-    dnt.to_string()
-        .as_bytes()
-        .rchunks(3)
-        .rev()
-        .map(std::str::from_utf8)
-        .collect::<Result<Vec<&str>, _>>()
-        .unwrap()
-        .join(",")
+    format_tokens(dnt, 6)
 }
 
 pub fn format_hnt(hnt: u64) -> String {
-    let vehnt = hnt / TOKEN_DIVIDER as u64;
-    vehnt
+    format_tokens(hnt, 8)
+}
+
+/// Whole tokens of a mint with `decimals`, with thousands separators
+pub fn format_tokens(amount: u64, decimals: u8) -> String {
+    let tokens = amount / 10u64.pow(decimals as u32);
+    tokens
         .to_string()
         .as_bytes()
         .rchunks(3)